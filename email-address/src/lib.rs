@@ -0,0 +1,190 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Validated email addresses, shared by the crates which look up or use
+//! account settings.
+
+use std::{fmt, str::FromStr};
+
+/// An email address which has been checked to be made of a non-empty local
+/// part and a non-empty domain separated by a single `@`.
+///
+/// Whitespace around the address, such as the newline left over from reading
+/// a line of input, is ignored when parsing.
+///
+/// The address is kept as it was entered, which is what servers expect e.g.
+/// as a username. Domains which aren't valid internationalized domain names
+/// are rejected, and the ASCII (IDNA) form of the domain, e.g.
+/// `xn--bcher-kva.example` for `bücher.example`, is kept alongside it for DNS
+/// lookups and URLs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EmailAddress {
+    address: String,
+
+    /// The index of the `@` separating the local part from the domain.
+    separator: usize,
+
+    ascii_domain: String,
+}
+
+impl EmailAddress {
+    /// The full address, as entered.
+    pub fn as_str(&self) -> &str {
+        &self.address
+    }
+
+    /// The part of the address before the `@`.
+    pub fn local_part(&self) -> &str {
+        &self.address[..self.separator]
+    }
+
+    /// The part of the address after the `@`, as entered.
+    pub fn domain(&self) -> &str {
+        &self.address[self.separator + 1..]
+    }
+
+    /// The ASCII form of the domain, to look it up in DNS or build URLs with.
+    pub fn ascii_domain(&self) -> &str {
+        &self.ascii_domain
+    }
+}
+
+impl FromStr for EmailAddress {
+    type Err = EmailAddressError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let mut parts = s.split('@');
+        let (local_part, domain) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(local_part), Some(domain), None) => (local_part, domain),
+            (_, None, _) => return Err(EmailAddressError::MissingAtSign),
+            _ => return Err(EmailAddressError::MultipleAtSigns),
+        };
+
+        if local_part.is_empty() {
+            return Err(EmailAddressError::EmptyLocalPart);
+        }
+
+        // Every label of the domain needs to be non-empty, which also rules out
+        // an empty domain.
+        if domain.split('.').any(str::is_empty) {
+            return Err(EmailAddressError::InvalidDomain);
+        }
+
+        if s.chars().any(char::is_whitespace) {
+            return Err(EmailAddressError::Whitespace);
+        }
+
+        let ascii_domain =
+            idna::domain_to_ascii(domain).map_err(|_| EmailAddressError::InvalidDomain)?;
+
+        Ok(Self {
+            address: s.to_string(),
+            separator: local_part.len(),
+            ascii_domain,
+        })
+    }
+}
+
+impl fmt::Display for EmailAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.address)
+    }
+}
+
+/// The reason a string isn't a valid [`EmailAddress`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EmailAddressError {
+    MissingAtSign,
+    MultipleAtSigns,
+    EmptyLocalPart,
+    InvalidDomain,
+    Whitespace,
+}
+
+impl fmt::Display for EmailAddressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            EmailAddressError::MissingAtSign => "missing '@'",
+            EmailAddressError::MultipleAtSigns => "more than one '@'",
+            EmailAddressError::EmptyLocalPart => "nothing before the '@'",
+            EmailAddressError::InvalidDomain => "empty or invalid domain",
+            EmailAddressError::Whitespace => "contains whitespace",
+        };
+
+        write!(f, "invalid email address: {}", reason)
+    }
+}
+
+impl std::error::Error for EmailAddressError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Test that a valid address is split into its parts.
+    #[test]
+    fn valid_address() {
+        let address: EmailAddress = "sylah@domain.test".parse().unwrap();
+
+        assert_eq!(address.as_str(), "sylah@domain.test");
+        assert_eq!(address.local_part(), "sylah");
+        assert_eq!(address.domain(), "domain.test");
+        assert_eq!(address.ascii_domain(), "domain.test");
+    }
+
+    // Test that surrounding whitespace is trimmed rather than rejected.
+    #[test]
+    fn surrounding_whitespace_is_trimmed() {
+        let address: EmailAddress = " a@b.com\n".parse().unwrap();
+
+        assert_eq!(address.as_str(), "a@b.com");
+        assert_eq!(address.domain(), "b.com");
+    }
+
+    // Test that addresses with non-ASCII parts are accepted, that their parts
+    // are split on character rather than byte boundaries, and that they're
+    // kept as entered, with the ASCII form of the domain alongside.
+    #[test]
+    fn idn_address() {
+        let address: EmailAddress = "josé@Bücher.example".parse().unwrap();
+
+        assert_eq!(address.local_part(), "josé");
+        assert_eq!(address.domain(), "Bücher.example");
+        assert_eq!(address.as_str(), "josé@Bücher.example");
+        assert_eq!(address.ascii_domain(), "xn--bcher-kva.example");
+
+        // Already encoded domains are left as they are.
+        let address: EmailAddress = "sylah@xn--bcher-kva.example".parse().unwrap();
+        assert_eq!(address.domain(), "xn--bcher-kva.example");
+        assert_eq!(address.ascii_domain(), "xn--bcher-kva.example");
+    }
+
+    // Test that malformed addresses are rejected with the right reason.
+    #[test]
+    fn invalid_addresses() {
+        let cases = [
+            ("no-at-sign", EmailAddressError::MissingAtSign),
+            ("two@@ats", EmailAddressError::MultipleAtSigns),
+            ("a@b@c.test", EmailAddressError::MultipleAtSigns),
+            ("@no-local.test", EmailAddressError::EmptyLocalPart),
+            ("no-domain@", EmailAddressError::InvalidDomain),
+            ("sylah@domain..test", EmailAddressError::InvalidDomain),
+            ("sylah@.domain.test", EmailAddressError::InvalidDomain),
+            // Punycode which doesn't decode, and a label starting with a
+            // combining mark, aren't valid internationalized labels.
+            ("sylah@xn--a.test", EmailAddressError::InvalidDomain),
+            ("sylah@\u{301}a.test", EmailAddressError::InvalidDomain),
+            ("syl ah@domain.test", EmailAddressError::Whitespace),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(
+                input.parse::<EmailAddress>(),
+                Err(expected),
+                "unexpected result for {input:?}"
+            );
+        }
+    }
+}
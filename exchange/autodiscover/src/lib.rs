@@ -0,0 +1,1431 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! A prototype of Exchange autodiscover using the POX service.
+
+use hickory_resolver::TokioAsyncResolver;
+use reqwest::{Client, Request, StatusCode, Url};
+use serde::Deserialize;
+use std::fmt;
+
+pub use email_address::{EmailAddress, EmailAddressError};
+
+use xml::{reader, writer};
+
+// The schema for POX autodiscovery requests.
+const REQUEST_SCHEMA: &str =
+    "http://schemas.microsoft.com/exchange/autodiscover/outlook/requestschema/2006";
+// The schema for POX autodiscovery responses.
+const RESPONSE_SCHEMA: &str =
+    "http://schemas.microsoft.com/exchange/autodiscover/outlook/responseschema/2006a";
+
+/// The outcome of an autodiscover request.
+#[derive(Debug)]
+pub enum Discovery {
+    /// The server provided settings, including EWS endpoints, for the address.
+    Found(AutodiscoverResult),
+
+    /// The server requires authentication, or rejected the provided password.
+    Unauthorized,
+
+    /// The server responded with an unexpected status.
+    Failed { status: StatusCode, body: String },
+}
+
+/// An error preventing autodiscover from completing.
+#[derive(Debug)]
+pub enum AutodiscoverError {
+    /// The address to run autodiscover for, or one the server redirected to,
+    /// isn't a valid email address.
+    InvalidAddress(EmailAddressError),
+
+    /// None of the autodiscover endpoints for the address could be reached,
+    /// or gave a usable response. `last` is the error from the last endpoint
+    /// tried.
+    AllEndpointsFailed { last: Box<AutodiscoverError> },
+
+    /// A server sent a response which isn't valid autodiscover XML.
+    MalformedResponse(String),
+
+    /// Servers redirected more than [`MAX_REDIRECTS`] times.
+    TooManyRedirects,
+
+    /// The request body couldn't be written.
+    Request(writer::Error),
+
+    /// A request couldn't be sent, or its response couldn't be received.
+    Transport(reqwest::Error),
+}
+
+impl fmt::Display for AutodiscoverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AutodiscoverError::InvalidAddress(err) => write!(f, "invalid address: {err}"),
+            AutodiscoverError::AllEndpointsFailed { last } => {
+                write!(f, "no autodiscover endpoint could be reached: {last}")
+            }
+            AutodiscoverError::MalformedResponse(reason) => {
+                write!(f, "malformed autodiscover response: {reason}")
+            }
+            AutodiscoverError::TooManyRedirects => {
+                write!(f, "gave up autodiscover after {MAX_REDIRECTS} redirects")
+            }
+            AutodiscoverError::Request(err) => write!(f, "failed to write request: {err}"),
+            AutodiscoverError::Transport(err) => write!(f, "request failed: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for AutodiscoverError {}
+
+impl From<EmailAddressError> for AutodiscoverError {
+    fn from(err: EmailAddressError) -> Self {
+        AutodiscoverError::InvalidAddress(err)
+    }
+}
+
+impl From<writer::Error> for AutodiscoverError {
+    fn from(err: writer::Error) -> Self {
+        AutodiscoverError::Request(err)
+    }
+}
+
+impl From<reqwest::Error> for AutodiscoverError {
+    fn from(err: reqwest::Error) -> Self {
+        AutodiscoverError::Transport(err)
+    }
+}
+
+/// The maximum number of redirects followed during a single discovery, which
+/// guards against servers redirecting in a loop.
+const MAX_REDIRECTS: usize = 10;
+
+/// Runs autodiscover for the given address and (optional) password, using a
+/// default HTTP client.
+pub async fn discover(
+    address: &EmailAddress,
+    password: Option<&str>,
+) -> Result<Discovery, AutodiscoverError> {
+    let client = Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()?;
+    discover_with_client(&client, address, password).await
+}
+
+/// Runs autodiscover for the given address and (optional) password, using the
+/// given HTTP client.
+///
+/// This lets autodiscover share a client with the EWS requests which follow
+/// it, and with them its connection pool and any proxy or TLS configuration.
+/// The client should be built with [`reqwest::redirect::Policy::none`], as
+/// autodiscover follows HTTP redirects itself: a client following them would
+/// turn the POST request into a GET one the server can't answer.
+///
+/// The Autodiscover V2 (JSON) endpoint on `autodiscover.{domain}` is asked
+/// first. If it doesn't provide an EWS URL, POX endpoints are tried in the
+/// order Microsoft documents: the address's domain itself, then
+/// `autodiscover.{domain}`, then the host named by the domain's
+/// `_autodiscover._tcp` SRV record. The first endpoint which either provides
+/// URLs or asks for credentials wins; if none does, the outcome of the last
+/// one tried is returned.
+///
+/// Redirects are followed, whether they're HTTP redirects or responses with a
+/// `redirectUrl` action (which send the same request to another URL), or
+/// `redirectAddr` action (which restart discovery for another address). An
+/// error is returned after [`MAX_REDIRECTS`] of them.
+///
+/// Spec: https://learn.microsoft.com/en-us/exchange/client-developer/exchange-web-services/autodiscover-for-exchange
+pub async fn discover_with_client(
+    client: &Client,
+    address: &EmailAddress,
+    password: Option<&str>,
+) -> Result<Discovery, AutodiscoverError> {
+    if let Some(url) = autodiscover_v2_url(address) {
+        if let Some(result) = discover_v2(client, &url).await {
+            return Ok(Discovery::Found(result));
+        }
+    }
+
+    let session = Session::new(client);
+    let step = discover_for_address(&session, address, password).await?;
+    follow_redirects(&session, address.clone(), password, step).await
+}
+
+// The context a discovery runs in: the client requests go through, and the
+// check a URL a server redirects to has to pass for the redirect to be
+// followed.
+struct Session<'a> {
+    client: &'a Client,
+    may_follow: fn(&Url) -> bool,
+}
+
+impl<'a> Session<'a> {
+    // Only HTTPS URLs are followed, so that credentials are never sent in the
+    // clear.
+    fn new(client: &'a Client) -> Self {
+        Self {
+            client,
+            may_follow: |url| url.scheme() == "https",
+        }
+    }
+}
+
+// Follows the redirects a first autodiscover step leads to, until one of them
+// gives an answer.
+async fn follow_redirects(
+    session: &Session<'_>,
+    mut address: EmailAddress,
+    password: Option<&str>,
+    mut step: Step,
+) -> Result<Discovery, AutodiscoverError> {
+    for _ in 0..MAX_REDIRECTS {
+        step = match step {
+            Step::Done(discovery) => return Ok(discovery),
+            Step::RedirectUrl(url) => discover_at(session, &url, &address, password).await?,
+            Step::RedirectAddr(redirected) => {
+                address = redirected;
+                discover_for_address(session, &address, password).await?
+            }
+        };
+    }
+
+    match step {
+        Step::Done(discovery) => Ok(discovery),
+        _ => Err(AutodiscoverError::TooManyRedirects),
+    }
+}
+
+// The outcome of a single autodiscover request.
+#[derive(Debug)]
+enum Step {
+    // The server answered the request.
+    Done(Discovery),
+
+    // The request should be sent again, to the given URL.
+    RedirectUrl(String),
+
+    // Autodiscover should start over for the given address.
+    RedirectAddr(EmailAddress),
+}
+
+// Tries each autodiscover endpoint for the given address in turn.
+async fn discover_for_address(
+    session: &Session<'_>,
+    address: &EmailAddress,
+    password: Option<&str>,
+) -> Result<Step, AutodiscoverError> {
+    let [first, second] = autodiscover_urls(address.ascii_domain());
+    let outcome = discover_at_first(session, &first, &[second], address, password).await;
+    if is_conclusive(&outcome) {
+        return outcome;
+    }
+
+    // Only resort to DNS once the well-known endpoints have failed, as most
+    // domains don't publish an SRV record.
+    match lookup_srv_url(address.ascii_domain()).await {
+        Some(url) => discover_at(session, &url, address, password)
+            .await
+            .map_err(|last| AutodiscoverError::AllEndpointsFailed {
+                last: Box::new(last),
+            }),
+        None => outcome,
+    }
+}
+
+// Whether an autodiscover outcome should stop us from trying other endpoints.
+// A request for credentials counts, as the caller needs to provide them before
+// going any further, and so does a redirect. Settings without an EWS URL don't,
+// as they're no use to the caller.
+fn is_conclusive(outcome: &Result<Step, AutodiscoverError>) -> bool {
+    match outcome {
+        Err(_) | Ok(Step::Done(Discovery::Failed { .. })) => false,
+        Ok(Step::Done(Discovery::Found(result))) => {
+            result.ews_urls.internal.is_some() || result.ews_urls.external.is_some()
+        }
+        _ => true,
+    }
+}
+
+// Tries `first`, then each of the `others` in turn, returning the first
+// conclusive outcome. If none was, the last unsuccessful response is returned,
+// or if none of the endpoints could be reached at all, an error wrapping the
+// last one's.
+async fn discover_at_first(
+    session: &Session<'_>,
+    first: &str,
+    others: &[String],
+    address: &EmailAddress,
+    password: Option<&str>,
+) -> Result<Step, AutodiscoverError> {
+    let mut outcome = discover_at(session, first, address, password).await;
+    for url in others {
+        if is_conclusive(&outcome) {
+            break;
+        }
+
+        match discover_at(session, url, address, password).await {
+            // Keep the most informative outcome: a response from a server,
+            // rather than a failure to reach another one.
+            Err(_) if outcome.is_ok() => {}
+            result => outcome = result,
+        }
+    }
+
+    outcome.map_err(|last| AutodiscoverError::AllEndpointsFailed {
+        last: Box::new(last),
+    })
+}
+
+// Sends an autodiscover request to the given URL and interprets the response.
+async fn discover_at(
+    session: &Session<'_>,
+    url: &str,
+    address: &EmailAddress,
+    password: Option<&str>,
+) -> Result<Step, AutodiscoverError> {
+    let req = build_request(session.client, url, address, password)?;
+    let res = session.client.execute(req).await?;
+
+    let status = res.status();
+    if status == StatusCode::UNAUTHORIZED {
+        return Ok(Step::Done(Discovery::Unauthorized));
+    }
+
+    if status.is_redirection() {
+        if let Some(location) = redirect_location(&res, session.may_follow) {
+            return Ok(Step::RedirectUrl(location));
+        }
+    }
+
+    let body = res.text().await?;
+    if status != StatusCode::OK {
+        return Ok(Step::Done(Discovery::Failed { status, body }));
+    }
+
+    Ok(match get_urls_from_autodiscover_response(body)? {
+        AutodiscoverResponse::Settings(result) => Step::Done(Discovery::Found(result)),
+        AutodiscoverResponse::RedirectUrl(url) => match Url::parse(&url) {
+            Ok(parsed) if (session.may_follow)(&parsed) => Step::RedirectUrl(url),
+            _ => {
+                return Err(AutodiscoverError::MalformedResponse(format!(
+                    "refusing to follow redirect to {url}"
+                )))
+            }
+        },
+        AutodiscoverResponse::RedirectAddr(address) => Step::RedirectAddr(address),
+    })
+}
+
+// The URL an HTTP redirect points to, if it's one we're willing to follow.
+fn redirect_location(res: &reqwest::Response, may_follow: fn(&Url) -> bool) -> Option<String> {
+    let location = res
+        .headers()
+        .get(reqwest::header::LOCATION)?
+        .to_str()
+        .ok()?;
+    let url = res.url().join(location).ok()?;
+
+    may_follow(&url).then(|| url.into())
+}
+
+// Builds the URL of the Autodiscover V2 endpoint asking for the EWS endpoint
+// of the given address.
+// Spec: https://learn.microsoft.com/en-us/exchange/client-developer/exchange-web-services/autodiscover-for-exchange#autodiscover-v2
+// Returns `None` if the address's domain can't be used as a host name.
+fn autodiscover_v2_url(address: &EmailAddress) -> Option<Url> {
+    let base = format!(
+        "https://autodiscover.{}/autodiscover/autodiscover.json",
+        address.ascii_domain()
+    );
+
+    Url::parse_with_params(&base, [("Email", address.as_str()), ("Protocol", "EWS")]).ok()
+}
+
+// The body of a successful Autodiscover V2 response.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AutodiscoverV2Response {
+    protocol: String,
+    url: String,
+}
+
+// Asks the Autodiscover V2 endpoint at the given URL for the EWS endpoint.
+// Failures aren't errors, since POX autodiscover can still be tried.
+async fn discover_v2(client: &Client, url: &Url) -> Option<AutodiscoverResult> {
+    let res = client.get(url.clone()).send().await.ok()?;
+    if res.status() != StatusCode::OK {
+        return None;
+    }
+
+    parse_v2_response(&res.text().await.ok()?)
+}
+
+// Parses an Autodiscover V2 response. V2 only serves clients connecting over
+// the Internet, so the URL it provides is an external one.
+fn parse_v2_response(body: &str) -> Option<AutodiscoverResult> {
+    let res: AutodiscoverV2Response = serde_json::from_str(body).ok()?;
+    if res.protocol != "EWS" {
+        return None;
+    }
+
+    Some(AutodiscoverResult {
+        ews_urls: AutodiscoverUrls {
+            internal: None,
+            external: Some(res.url),
+        },
+        ..Default::default()
+    })
+}
+
+// Builds the URLs of the well-known autodiscover endpoints for the given
+// domain, in the order they should be tried.
+fn autodiscover_urls(domain: &str) -> [String; 2] {
+    [
+        format!("https://{domain}/autodiscover/autodiscover.xml"),
+        format!("https://autodiscover.{domain}/autodiscover/autodiscover.xml"),
+    ]
+}
+
+// A target advertised by an SRV record.
+#[derive(Debug, PartialEq)]
+struct SrvTarget {
+    priority: u16,
+    weight: u16,
+    port: u16,
+    host: String,
+}
+
+impl SrvTarget {
+    // Builds the URL of the autodiscover endpoint on the target host. The
+    // port is only included if it isn't the default one for HTTPS.
+    fn autodiscover_url(&self) -> String {
+        // DNS names are fully qualified, so may end with a dot.
+        let host = self.host.trim_end_matches('.');
+        if self.port == 443 {
+            format!("https://{host}/autodiscover/autodiscover.xml")
+        } else {
+            format!("https://{host}:{}/autodiscover/autodiscover.xml", self.port)
+        }
+    }
+}
+
+// Picks the target to use among those advertised for a service: the one with
+// the lowest priority, breaking ties in favour of the highest weight.
+fn pick_srv_target(targets: Vec<SrvTarget>) -> Option<SrvTarget> {
+    targets
+        .into_iter()
+        .min_by_key(|target| (target.priority, std::cmp::Reverse(target.weight)))
+}
+
+// Looks up the `_autodiscover._tcp` SRV record for the given domain, and
+// builds the URL of the autodiscover endpoint on the host it points to.
+// Failures are treated the same as the record not existing, as there's
+// nothing left to try either way.
+async fn lookup_srv_url(domain: &str) -> Option<String> {
+    let resolver = TokioAsyncResolver::tokio_from_system_conf().ok()?;
+    let records = resolver
+        .srv_lookup(format!("_autodiscover._tcp.{domain}."))
+        .await
+        .ok()?;
+
+    let targets = records
+        .iter()
+        .map(|record| SrvTarget {
+            priority: record.priority(),
+            weight: record.weight(),
+            port: record.port(),
+            host: record.target().to_utf8(),
+        })
+        .collect();
+
+    pick_srv_target(targets).map(|target| target.autodiscover_url())
+}
+
+// Builds an autodiscover request to the given URL for the given address and
+// (optional) password. If a password is given, a Basic authentication header is
+// added to the request.
+fn build_request(
+    client: &Client,
+    url: &str,
+    address: &EmailAddress,
+    password: Option<&str>,
+) -> Result<Request, AutodiscoverError> {
+    let request_body = generate_autodiscover_request_body(address.as_str())?;
+    let mut req = client
+        .post(url)
+        .header("Content-Type", "text/xml; charset=utf-8")
+        .body(request_body);
+
+    if password.is_some() {
+        // If a password is provided, add Basic authentication.
+        req = req.basic_auth(address.as_str(), password);
+    }
+
+    // Build the request.
+    Ok(req.build()?)
+}
+
+// Generates the body for a POX EWS autodiscover request for the given email address.
+// Spec: https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/pox-autodiscover-request-for-exchange
+fn generate_autodiscover_request_body(email: &str) -> Result<String, AutodiscoverError> {
+    // Buffer to use for writing the body.
+    let mut buf = Vec::new();
+
+    // `xml-rs` writer. We add indentation to help with readability when
+    // debugging, but that's not strictly necessary.
+    let mut writer = writer::EmitterConfig::new()
+        .perform_indent(true)
+        .create_writer(&mut buf);
+
+    // Write the request's body using `XmlEvent`s.
+    let events = vec![
+        writer::XmlEvent::from(
+            writer::XmlEvent::start_element("Autodiscover").default_ns(REQUEST_SCHEMA),
+        ),
+        writer::XmlEvent::from(writer::XmlEvent::start_element("Request")),
+        writer::XmlEvent::from(writer::XmlEvent::start_element("EMailAddress")),
+        writer::XmlEvent::characters(email),
+        writer::XmlEvent::from(writer::XmlEvent::end_element()),
+        writer::XmlEvent::from(writer::XmlEvent::start_element("AcceptableResponseSchema")),
+        writer::XmlEvent::characters(RESPONSE_SCHEMA),
+        writer::XmlEvent::from(writer::XmlEvent::end_element()),
+        writer::XmlEvent::from(writer::XmlEvent::end_element()),
+        writer::XmlEvent::from(writer::XmlEvent::end_element()),
+    ];
+
+    // Write each event.
+    for evt in events {
+        writer.write(evt)?;
+    }
+
+    // Turn the buffer (which should now contain our complete XML document) into
+    // a string. `xml-rs` only ever emits UTF-8, so this shouldn't fail in
+    // practice.
+    String::from_utf8(buf).map_err(|err| {
+        writer::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, err)).into()
+    })
+}
+
+/// Where the client is connecting from relative to the Exchange server's
+/// network, which determines which of the advertised endpoints to use.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum NetworkLocation {
+    /// The client is connecting from outside the organization's network, so
+    /// the `EXPR` (Outlook Anywhere) endpoint should be preferred.
+    #[default]
+    OffNetwork,
+
+    /// The client is inside the organization's network, so the `EXCH`
+    /// endpoint should be preferred.
+    OnNetwork,
+}
+
+/// The settings found in an autodiscover response.
+#[derive(Debug, Default, PartialEq)]
+pub struct AutodiscoverResult {
+    /// The display name of the user the address belongs to.
+    pub display_name: Option<String>,
+
+    /// The URLs of the EWS endpoints.
+    pub ews_urls: AutodiscoverUrls,
+
+    /// The URLs of the offline address book (OAB) endpoints.
+    pub oab_urls: AutodiscoverUrls,
+}
+
+impl AutodiscoverResult {
+    /// The URL of the EWS endpoint to use from the given location, which is
+    /// what most callers are after.
+    pub fn ews_url(&self, location: NetworkLocation) -> Option<&str> {
+        self.ews_urls.preferred(location)
+    }
+}
+
+/// The URLs of one kind of endpoint found in an autodiscover response, keyed
+/// by the type of the `<Protocol>` element they were found in.
+#[derive(Debug, Default, PartialEq)]
+pub struct AutodiscoverUrls {
+    /// The URL for the `EXCH` protocol, i.e. for clients within the
+    /// organization's network.
+    pub internal: Option<String>,
+
+    /// The URL for the `EXPR` protocol, i.e. for clients outside of the
+    /// organization's network.
+    pub external: Option<String>,
+}
+
+impl AutodiscoverUrls {
+    /// Picks the URL to use for the given location, falling back to the other
+    /// one if the preferred protocol wasn't in the response.
+    pub fn preferred(&self, location: NetworkLocation) -> Option<&str> {
+        let (preferred, fallback) = match location {
+            NetworkLocation::OffNetwork => (&self.external, &self.internal),
+            NetworkLocation::OnNetwork => (&self.internal, &self.external),
+        };
+
+        preferred.as_deref().or(fallback.as_deref())
+    }
+}
+
+// What a successful autodiscover response tells us, depending on its
+// `<Action>`.
+#[derive(Debug, PartialEq)]
+enum AutodiscoverResponse {
+    // The response provides the settings for the address (`settings`).
+    Settings(AutodiscoverResult),
+
+    // The request should be sent to another URL (`redirectUrl`).
+    RedirectUrl(String),
+
+    // Autodiscover should be run for another address (`redirectAddr`).
+    RedirectAddr(EmailAddress),
+}
+
+// Parse the response from an autodiscover request and extract the settings it
+// provides, or where to go next if the response is a redirect.
+// Spec: https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/pox-autodiscover-response-for-exchange
+fn get_urls_from_autodiscover_response(
+    res: String,
+) -> Result<AutodiscoverResponse, AutodiscoverError> {
+    let mut result = AutodiscoverResult::default();
+
+    // Parse the response, skipping the UTF-8 byte order mark some servers
+    // prepend to it, since not every version of `xml-rs` copes with it.
+    let res = res.strip_prefix('\u{feff}').unwrap_or(&res);
+    let parser = reader::EventReader::new(res.as_bytes());
+
+    // Whether we've read the root element, which has to be <Autodiscover> for
+    // this to be an autodiscover response rather than e.g. a web page.
+    let mut seen_root = false;
+    // Whether we're currently inside a <User> element.
+    let mut in_user = false;
+    // Whether we're currently inside an <Account> element.
+    let mut in_account = false;
+    // Whether we're currently inside a <Protocol> element that's inside an <Account> element.
+    let mut in_protocol = false;
+    // The name of the element inside a <Protocol> element that we're currently
+    // reading the text of, if any.
+    let mut current_field: Option<String> = None;
+    // The type and URLs of the <Protocol> element we're currently in. They
+    // can come in any order, so we only act on them once the element ends.
+    let mut protocol_type: Option<String> = None;
+    let mut protocol_url: Option<String> = None;
+    let mut protocol_ews_url: Option<String> = None;
+    let mut protocol_oab_url: Option<String> = None;
+    // The action of the <Account> element, and where it redirects to if it's
+    // a redirect.
+    let mut action: Option<String> = None;
+    let mut redirect: Option<String> = None;
+
+    for e in parser {
+        match e {
+            Ok(reader::XmlEvent::StartElement { name, .. }) => {
+                let tag_name = name.local_name;
+                if !seen_root {
+                    if tag_name != "Autodiscover" {
+                        return Err(AutodiscoverError::MalformedResponse(format!(
+                            "unexpected root element <{tag_name}>"
+                        )));
+                    }
+                    seen_root = true;
+                }
+
+                match tag_name.as_str() {
+                    "User" => in_user = true,
+                    "DisplayName" if in_user => current_field = Some(tag_name),
+                    "Account" => in_account = true,
+                    "Protocol" if in_account => in_protocol = true,
+                    "Type" | "ASUrl" | "EwsUrl" | "OABUrl" if in_protocol => {
+                        current_field = Some(tag_name)
+                    }
+                    "Action" | "RedirectUrl" | "RedirectAddr" if in_account && !in_protocol => {
+                        current_field = Some(tag_name)
+                    }
+                    _ => {}
+                }
+            }
+            Ok(reader::XmlEvent::EndElement { name }) => {
+                let tag_name = name.local_name;
+                match tag_name.as_str() {
+                    "User" => {
+                        in_user = false;
+                    }
+                    "Account" => {
+                        in_account = false;
+                    }
+                    "Protocol" if in_protocol => {
+                        in_protocol = false;
+
+                        // EwsUrl is the dedicated EWS endpoint, whereas ASUrl
+                        // is that of the availability service, which only
+                        // happens to be the same on most servers.
+                        let ews_url = protocol_ews_url.take().or(protocol_url.take());
+                        let oab_url = protocol_oab_url.take();
+                        match protocol_type.take().as_deref() {
+                            Some("EXCH") => {
+                                result.ews_urls.internal = ews_url;
+                                result.oab_urls.internal = oab_url;
+                            }
+                            Some("EXPR") => {
+                                result.ews_urls.external = ews_url;
+                                result.oab_urls.external = oab_url;
+                            }
+                            // Other protocols (e.g. WEB) don't provide an
+                            // EWS endpoint.
+                            _ => {}
+                        }
+                    }
+                    "Type" | "ASUrl" | "EwsUrl" | "OABUrl" | "DisplayName" | "Action"
+                    | "RedirectUrl" | "RedirectAddr" => {
+                        current_field = None;
+                    }
+                    _ => {}
+                }
+            }
+            Ok(reader::XmlEvent::Characters(text)) => match current_field.as_deref() {
+                Some("Type") => protocol_type = Some(text),
+                Some("ASUrl") => protocol_url = Some(text),
+                Some("EwsUrl") => protocol_ews_url = Some(text),
+                Some("OABUrl") => protocol_oab_url = Some(text),
+                Some("DisplayName") => result.display_name = Some(text),
+                Some("Action") => action = Some(text),
+                Some("RedirectUrl" | "RedirectAddr") => redirect = Some(text),
+                _ => {}
+            },
+            Err(err) => return Err(AutodiscoverError::MalformedResponse(err.to_string())),
+            _ => {}
+        }
+    }
+
+    match action.as_deref() {
+        Some("redirectUrl") => {
+            let url = redirect.ok_or_else(|| {
+                AutodiscoverError::MalformedResponse(
+                    "redirectUrl response without a RedirectUrl".to_string(),
+                )
+            })?;
+            Ok(AutodiscoverResponse::RedirectUrl(url))
+        }
+        Some("redirectAddr") => {
+            let address = redirect.ok_or_else(|| {
+                AutodiscoverError::MalformedResponse(
+                    "redirectAddr response without a RedirectAddr".to_string(),
+                )
+            })?;
+            Ok(AutodiscoverResponse::RedirectAddr(address.parse()?))
+        }
+        _ => Ok(AutodiscoverResponse::Settings(result)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    use xml::namespace;
+
+    // Test that we generate valid bodies for autodiscovery requests.
+    #[test]
+    fn request_body_is_valid() {
+        // Address to test with.
+        let address = String::from("sylah@domain.test");
+        // The expected depth of each element in the XML document.
+        let expected_depths: HashMap<&str, i32> = HashMap::from([
+            ("Autodiscover", 0),
+            ("Request", 1),
+            ("EMailAddress", 2),
+            ("AcceptableResponseSchema", 2),
+        ]);
+
+        // Generate the body.
+        let req_body = generate_autodiscover_request_body(&address)
+            .expect("failed to generate a request body");
+
+        // The currend depth in the XML document.
+        let mut depth = 0;
+        // Whether we're in the EMailAddress element.
+        let mut in_address = false;
+        // Whether the EMailAddress includes text.
+        let mut text_in_address = false;
+        // Whether we're in the AcceptableResponseSchema element.
+        let mut in_res_schema = false;
+        // Whether the AcceptableResponseSchema includes text.
+        let mut text_in_res_schema = false;
+
+        // Parse the body.
+        let buf = req_body.into_bytes();
+        let parser = reader::EventReader::new(buf.as_slice());
+        for e in parser {
+            match e {
+                Ok(reader::XmlEvent::StartElement {
+                    name,
+                    attributes: _,
+                    namespace,
+                }) => {
+                    // Compare the current depth against the expected depth for this element.
+                    let tag_name = name.local_name.as_str();
+                    let expected_depth = expected_depths.get(tag_name).unwrap_or(&-1).to_owned();
+
+                    assert_eq!(depth, expected_depth, "Invalid depth for tag {}", tag_name);
+
+                    match tag_name {
+                        "Autodiscover" => {
+                            // Check that the Autodiscover element has the correct default namespace.
+                            let default_ns = namespace
+                                .get(namespace::NS_EMPTY_URI)
+                                .unwrap_or("missing default namespace for Autodiscover tag");
+
+                            assert_eq!(default_ns, REQUEST_SCHEMA);
+                        }
+                        "EMailAddress" => {
+                            in_address = true;
+                        }
+                        "AcceptableResponseSchema" => {
+                            in_res_schema = true;
+                        }
+                        _ => {}
+                    }
+
+                    // Increase the current depth.
+                    depth += 1;
+                }
+                Ok(reader::XmlEvent::Characters(text)) => {
+                    if in_address {
+                        // If we're in the EMailAddress element, check that the
+                        // element's content is the email address.
+                        assert_eq!(text, address);
+                        text_in_address = true;
+                    }
+
+                    if in_res_schema {
+                        // If we're in the AcceptableResponseSchema, check that we're
+                        // referring to the correct schema.
+                        assert_eq!(text, RESPONSE_SCHEMA);
+                        text_in_res_schema = true;
+                    }
+                }
+                Ok(reader::XmlEvent::EndElement { name }) => {
+                    // If we were in an element we're checking the content of,
+                    // check that it isn't empty, and track that we've left it.
+                    // TODO: Check that there isn't more than one element of each type.
+                    match name.local_name.as_str() {
+                        "EMailAddress" => {
+                            assert!(text_in_address);
+                            in_address = false;
+                        }
+                        "AcceptableResponseSchema" => {
+                            assert!(text_in_res_schema);
+                            in_res_schema = false;
+                        }
+                        _ => {}
+                    }
+                    // Decrease the current depth.
+                    depth -= 1;
+                }
+                Err(e) => {
+                    panic!("{}", e.to_string())
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // A response advertising both an internal and an external endpoint, with
+    // a non-EWS protocol in between.
+    const DUAL_PROTOCOL_RESPONSE: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<Autodiscover xmlns="http://schemas.microsoft.com/exchange/autodiscover/responseschema/2006">
+  <Response xmlns="http://schemas.microsoft.com/exchange/autodiscover/outlook/responseschema/2006a">
+    <Account>
+      <AccountType>email</AccountType>
+      <Action>settings</Action>
+      <Protocol>
+        <Type>EXCH</Type>
+        <ASUrl>https://mail.internal.test/EWS/Exchange.asmx</ASUrl>
+      </Protocol>
+      <Protocol>
+        <Type>WEB</Type>
+      </Protocol>
+      <Protocol>
+        <ASUrl>https://mail.external.test/EWS/Exchange.asmx</ASUrl>
+        <Type>EXPR</Type>
+      </Protocol>
+    </Account>
+  </Response>
+</Autodiscover>"#;
+
+    // Parses a response which is expected to provide settings rather than a
+    // redirect.
+    fn settings(res: String) -> AutodiscoverResult {
+        match get_urls_from_autodiscover_response(res).expect("failed to parse response") {
+            AutodiscoverResponse::Settings(result) => result,
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
+
+    // Test that both endpoints are extracted from a dual-protocol response,
+    // and that the configured location decides which one is preferred.
+    #[test]
+    fn preference_follows_network_location() {
+        let urls = settings(DUAL_PROTOCOL_RESPONSE.to_string()).ews_urls;
+
+        assert_eq!(
+            urls,
+            AutodiscoverUrls {
+                internal: Some("https://mail.internal.test/EWS/Exchange.asmx".to_string()),
+                external: Some("https://mail.external.test/EWS/Exchange.asmx".to_string()),
+            }
+        );
+        assert_eq!(
+            urls.preferred(NetworkLocation::OffNetwork),
+            Some("https://mail.external.test/EWS/Exchange.asmx")
+        );
+        assert_eq!(
+            urls.preferred(NetworkLocation::OnNetwork),
+            Some("https://mail.internal.test/EWS/Exchange.asmx")
+        );
+    }
+
+    // Test that only EWS-capable protocols are read, and that their EwsUrl is
+    // preferred over their ASUrl.
+    #[test]
+    fn ews_url_is_read_from_matching_protocol() {
+        let res = r#"<?xml version="1.0" encoding="utf-8"?>
+<Autodiscover xmlns="http://schemas.microsoft.com/exchange/autodiscover/responseschema/2006">
+  <Response xmlns="http://schemas.microsoft.com/exchange/autodiscover/outlook/responseschema/2006a">
+    <Account>
+      <Action>settings</Action>
+      <Protocol>
+        <Type>WEB</Type>
+        <EwsUrl>https://web.domain.test/EWS/Exchange.asmx</EwsUrl>
+      </Protocol>
+      <Protocol>
+        <Type>EXPR</Type>
+        <ASUrl>https://mail.domain.test/EWS/Availability.asmx</ASUrl>
+        <EwsUrl>https://mail.domain.test/EWS/Exchange.asmx</EwsUrl>
+      </Protocol>
+    </Account>
+  </Response>
+</Autodiscover>"#;
+
+        assert_eq!(
+            settings(res.to_string()).ews_urls,
+            AutodiscoverUrls {
+                internal: None,
+                external: Some("https://mail.domain.test/EWS/Exchange.asmx".to_string()),
+            }
+        );
+    }
+
+    // Test that every setting is extracted from a full response.
+    #[test]
+    fn full_response_is_parsed() {
+        let res = r#"<?xml version="1.0" encoding="utf-8"?>
+<Autodiscover xmlns="http://schemas.microsoft.com/exchange/autodiscover/responseschema/2006">
+  <Response xmlns="http://schemas.microsoft.com/exchange/autodiscover/outlook/responseschema/2006a">
+    <User>
+      <DisplayName>Sylah Example</DisplayName>
+      <LegacyDN>/o=Example/ou=Exchange/cn=Recipients/cn=sylah</LegacyDN>
+      <DeploymentId>644560b8-a1ce-429c-8ace-23395843f701</DeploymentId>
+    </User>
+    <Account>
+      <AccountType>email</AccountType>
+      <Action>settings</Action>
+      <Protocol>
+        <Type>EXCH</Type>
+        <Server>mail.internal.test</Server>
+        <ASUrl>https://mail.internal.test/EWS/Exchange.asmx</ASUrl>
+        <EwsUrl>https://mail.internal.test/EWS/Exchange.asmx</EwsUrl>
+        <OABUrl>https://mail.internal.test/OAB/d29844a9/</OABUrl>
+      </Protocol>
+      <Protocol>
+        <Type>EXPR</Type>
+        <Server>mail.external.test</Server>
+        <ASUrl>https://mail.external.test/EWS/Exchange.asmx</ASUrl>
+        <EwsUrl>https://mail.external.test/EWS/Exchange.asmx</EwsUrl>
+        <OABUrl>https://mail.external.test/OAB/d29844a9/</OABUrl>
+      </Protocol>
+    </Account>
+  </Response>
+</Autodiscover>"#;
+
+        let result = settings(res.to_string());
+        assert_eq!(
+            result,
+            AutodiscoverResult {
+                display_name: Some("Sylah Example".to_string()),
+                ews_urls: AutodiscoverUrls {
+                    internal: Some("https://mail.internal.test/EWS/Exchange.asmx".to_string()),
+                    external: Some("https://mail.external.test/EWS/Exchange.asmx".to_string()),
+                },
+                oab_urls: AutodiscoverUrls {
+                    internal: Some("https://mail.internal.test/OAB/d29844a9/".to_string()),
+                    external: Some("https://mail.external.test/OAB/d29844a9/".to_string()),
+                },
+            }
+        );
+        assert_eq!(
+            result.ews_url(NetworkLocation::OffNetwork),
+            Some("https://mail.external.test/EWS/Exchange.asmx")
+        );
+    }
+
+    // Test that the preferred endpoint falls back to the other protocol when
+    // only one is available.
+    #[test]
+    fn preference_falls_back_to_available_protocol() {
+        let urls = AutodiscoverUrls {
+            internal: Some("https://mail.internal.test/EWS/Exchange.asmx".to_string()),
+            external: None,
+        };
+
+        assert_eq!(
+            urls.preferred(NetworkLocation::OffNetwork),
+            Some("https://mail.internal.test/EWS/Exchange.asmx")
+        );
+    }
+
+    // Test that a UTF-8 byte order mark at the start of the response doesn't
+    // prevent it from being parsed.
+    #[test]
+    fn response_with_bom_is_parsed() {
+        let res = format!("\u{feff}{}", DUAL_PROTOCOL_RESPONSE);
+        let urls = settings(res).ews_urls;
+
+        assert_eq!(
+            urls.external.as_deref(),
+            Some("https://mail.external.test/EWS/Exchange.asmx")
+        );
+    }
+
+    // Test that the domain itself is tried before its autodiscover subdomain.
+    #[test]
+    fn well_known_urls_are_tried_in_order() {
+        assert_eq!(
+            autodiscover_urls("domain.test"),
+            [
+                "https://domain.test/autodiscover/autodiscover.xml",
+                "https://autodiscover.domain.test/autodiscover/autodiscover.xml",
+            ]
+        );
+    }
+
+    // Test that the V2 endpoint is asked for the EWS endpoint of the address.
+    #[test]
+    fn v2_url_asks_for_ews() {
+        let address = "sylah+test@domain.test".parse().unwrap();
+        assert_eq!(
+            autodiscover_v2_url(&address).unwrap().as_str(),
+            "https://autodiscover.domain.test/autodiscover/autodiscover.json?Email=sylah%2Btest%40domain.test&Protocol=EWS"
+        );
+
+        // The host uses the ASCII form of the domain, but the address is sent
+        // as entered.
+        let address = "josé@bücher.example".parse().unwrap();
+        assert_eq!(
+            autodiscover_v2_url(&address).unwrap().as_str(),
+            "https://autodiscover.xn--bcher-kva.example/autodiscover/autodiscover.json?Email=jos%C3%A9%40b%C3%BCcher.example&Protocol=EWS"
+        );
+    }
+
+    // Test that a V2 response provides an external EWS URL, and that one for
+    // another protocol is ignored.
+    #[test]
+    fn v2_response_is_parsed() {
+        let result = parse_v2_response(
+            r#"{"Protocol":"EWS","Url":"https://outlook.office365.com/EWS/Exchange.asmx"}"#,
+        )
+        .expect("response should provide an EWS URL");
+        assert_eq!(
+            result.ews_url(NetworkLocation::OnNetwork),
+            Some("https://outlook.office365.com/EWS/Exchange.asmx")
+        );
+
+        assert!(parse_v2_response(
+            r#"{"Protocol":"ActiveSync","Url":"https://outlook.office365.com/Microsoft-Server-ActiveSync"}"#
+        )
+        .is_none());
+        assert!(parse_v2_response("<html></html>").is_none());
+    }
+
+    // Test that the SRV target with the lowest priority and highest weight is
+    // picked, and that its URL only spells out a non-default port.
+    #[test]
+    fn srv_target_is_picked_by_priority_then_weight() {
+        let target = |priority, weight, port, host: &str| SrvTarget {
+            priority,
+            weight,
+            port,
+            host: host.to_string(),
+        };
+
+        let picked = pick_srv_target(vec![
+            target(10, 50, 443, "backup.domain.test."),
+            target(0, 10, 443, "light.domain.test."),
+            target(0, 20, 8443, "mail.domain.test."),
+        ])
+        .expect("a target should be picked");
+
+        assert_eq!(picked.host, "mail.domain.test.");
+        assert_eq!(
+            picked.autodiscover_url(),
+            "https://mail.domain.test:8443/autodiscover/autodiscover.xml"
+        );
+        assert_eq!(
+            target(0, 0, 443, "mail.domain.test.").autodiscover_url(),
+            "https://mail.domain.test/autodiscover/autodiscover.xml"
+        );
+        assert_eq!(pick_srv_target(vec![]), None);
+    }
+
+    // Test that endpoints are tried until one of them provides URLs.
+    #[tokio::test]
+    async fn failed_endpoints_fall_through_to_the_next() {
+        let mut server = mockito::Server::new_async().await;
+        let missing = server
+            .mock("POST", "/missing/autodiscover.xml")
+            .with_status(404)
+            .create_async()
+            .await;
+        let found = server
+            .mock("POST", "/autodiscover/autodiscover.xml")
+            .with_body(DUAL_PROTOCOL_RESPONSE)
+            .create_async()
+            .await;
+        let unused = server
+            .mock("POST", "/unused/autodiscover.xml")
+            .expect(0)
+            .create_async()
+            .await;
+
+        let urls = ["/missing", "/autodiscover", "/unused"]
+            .map(|path| format!("{}{path}/autodiscover.xml", server.url()));
+        let address = "sylah@domain.test".parse().unwrap();
+        let discovery = discover_at_first(
+            &Session::new(&Client::new()),
+            &urls[0],
+            &urls[1..],
+            &address,
+            None,
+        )
+        .await
+        .expect("discovery should succeed");
+
+        missing.assert_async().await;
+        found.assert_async().await;
+        unused.assert_async().await;
+        assert!(matches!(discovery, Step::Done(Discovery::Found(_))));
+    }
+
+    // Test that endpoints answering with something other than EWS settings,
+    // e.g. a web page or settings for other protocols only, don't stop the
+    // search.
+    #[tokio::test]
+    async fn responses_without_ews_urls_fall_through_to_the_next() {
+        let mut server = mockito::Server::new_async().await;
+        let web_page = server
+            .mock("POST", "/web/autodiscover.xml")
+            .with_body(
+                r#"<?xml version="1.0" encoding="utf-8"?>
+<html xmlns="http://www.w3.org/1999/xhtml"><body><p>Welcome!</p></body></html>"#,
+            )
+            .create_async()
+            .await;
+        let web_only = server
+            .mock("POST", "/web-only/autodiscover.xml")
+            .with_body(
+                r#"<?xml version="1.0" encoding="utf-8"?>
+<Autodiscover xmlns="http://schemas.microsoft.com/exchange/autodiscover/responseschema/2006">
+  <Response xmlns="http://schemas.microsoft.com/exchange/autodiscover/outlook/responseschema/2006a">
+    <Account>
+      <Action>settings</Action>
+      <Protocol>
+        <Type>WEB</Type>
+      </Protocol>
+    </Account>
+  </Response>
+</Autodiscover>"#,
+            )
+            .create_async()
+            .await;
+        let found = server
+            .mock("POST", "/autodiscover/autodiscover.xml")
+            .with_body(DUAL_PROTOCOL_RESPONSE)
+            .create_async()
+            .await;
+
+        let urls = ["/web", "/web-only", "/autodiscover"]
+            .map(|path| format!("{}{path}/autodiscover.xml", server.url()));
+        let address = "sylah@domain.test".parse().unwrap();
+        let discovery = discover_at_first(
+            &Session::new(&Client::new()),
+            &urls[0],
+            &urls[1..],
+            &address,
+            None,
+        )
+        .await
+        .expect("discovery should succeed");
+
+        web_page.assert_async().await;
+        web_only.assert_async().await;
+        found.assert_async().await;
+        match discovery {
+            Step::Done(Discovery::Found(result)) => assert_eq!(
+                result.ews_url(NetworkLocation::OffNetwork),
+                Some("https://mail.external.test/EWS/Exchange.asmx")
+            ),
+            other => panic!("unexpected outcome: {other:?}"),
+        }
+    }
+
+    // Test that both kinds of redirect responses are recognized.
+    #[test]
+    fn redirect_responses_are_parsed() {
+        let response = |action: &str, element: &str, target: &str| {
+            format!(
+                r#"<?xml version="1.0" encoding="utf-8"?>
+<Autodiscover xmlns="http://schemas.microsoft.com/exchange/autodiscover/responseschema/2006">
+  <Response xmlns="http://schemas.microsoft.com/exchange/autodiscover/outlook/responseschema/2006a">
+    <Account>
+      <Action>{action}</Action>
+      <{element}>{target}</{element}>
+    </Account>
+  </Response>
+</Autodiscover>"#
+            )
+        };
+
+        let res = response(
+            "redirectUrl",
+            "RedirectUrl",
+            "https://autodiscover.other.test/autodiscover/autodiscover.xml",
+        );
+        assert_eq!(
+            get_urls_from_autodiscover_response(res).expect("failed to parse response"),
+            AutodiscoverResponse::RedirectUrl(
+                "https://autodiscover.other.test/autodiscover/autodiscover.xml".to_string()
+            )
+        );
+
+        let res = response("redirectAddr", "RedirectAddr", "sylah@other.test");
+        assert_eq!(
+            get_urls_from_autodiscover_response(res).expect("failed to parse response"),
+            AutodiscoverResponse::RedirectAddr("sylah@other.test".parse().unwrap())
+        );
+    }
+
+    // Test that HTTP redirects are only followed to HTTPS URLs.
+    #[tokio::test]
+    async fn http_redirects_must_use_https() {
+        let mut server = mockito::Server::new_async().await;
+        let secure = server
+            .mock("POST", "/secure/autodiscover.xml")
+            .with_status(302)
+            .with_header(
+                "Location",
+                "https://mail.domain.test/autodiscover/autodiscover.xml",
+            )
+            .create_async()
+            .await;
+        let insecure = server
+            .mock("POST", "/insecure/autodiscover.xml")
+            .with_status(302)
+            .with_header(
+                "Location",
+                "http://mail.domain.test/autodiscover/autodiscover.xml",
+            )
+            .create_async()
+            .await;
+
+        let client = Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap();
+        let address = "sylah@domain.test".parse().unwrap();
+
+        let url = format!("{}/secure/autodiscover.xml", server.url());
+        let step = discover_at(&Session::new(&client), &url, &address, None)
+            .await
+            .expect("request should succeed");
+        secure.assert_async().await;
+        match step {
+            Step::RedirectUrl(url) => {
+                assert_eq!(
+                    url,
+                    "https://mail.domain.test/autodiscover/autodiscover.xml"
+                )
+            }
+            other => panic!("unexpected outcome: {other:?}"),
+        }
+
+        let url = format!("{}/insecure/autodiscover.xml", server.url());
+        let step = discover_at(&Session::new(&client), &url, &address, None)
+            .await
+            .expect("request should succeed");
+        insecure.assert_async().await;
+        assert!(matches!(step, Step::Done(Discovery::Failed { .. })));
+    }
+
+    // Test that a redirectUrl response pointing at a plain HTTP URL is refused
+    // rather than followed, as that would send credentials in the clear.
+    #[tokio::test]
+    async fn redirect_url_responses_must_use_https() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/autodiscover/autodiscover.xml")
+            .with_body(
+                r#"<?xml version="1.0" encoding="utf-8"?>
+<Autodiscover xmlns="http://schemas.microsoft.com/exchange/autodiscover/responseschema/2006">
+  <Response xmlns="http://schemas.microsoft.com/exchange/autodiscover/outlook/responseschema/2006a">
+    <Account>
+      <Action>redirectUrl</Action>
+      <RedirectUrl>http://mail.domain.test/autodiscover/autodiscover.xml</RedirectUrl>
+    </Account>
+  </Response>
+</Autodiscover>"#,
+            )
+            .create_async()
+            .await;
+
+        let url = format!("{}/autodiscover/autodiscover.xml", server.url());
+        let address = "sylah@domain.test".parse().unwrap();
+        let outcome = discover_at(
+            &Session::new(&Client::new()),
+            &url,
+            &address,
+            Some("hunter2"),
+        )
+        .await;
+
+        mock.assert_async().await;
+        assert!(
+            matches!(outcome, Err(AutodiscoverError::MalformedResponse(_))),
+            "unexpected outcome: {outcome:?}"
+        );
+    }
+
+    // Test that failing to reach any endpoint is reported as such, along with
+    // the transport error of the last one tried.
+    #[tokio::test]
+    async fn unreachable_endpoints_are_reported() {
+        // Nothing listens on port 1, so connections are refused.
+        let urls = [
+            "http://127.0.0.1:1/autodiscover/autodiscover.xml".to_string(),
+            "http://127.0.0.1:1/other/autodiscover.xml".to_string(),
+        ];
+        let address = "sylah@domain.test".parse().unwrap();
+        let outcome = discover_at_first(
+            &Session::new(&Client::new()),
+            &urls[0],
+            &urls[1..],
+            &address,
+            None,
+        )
+        .await;
+
+        match outcome {
+            Err(AutodiscoverError::AllEndpointsFailed { last }) => assert!(
+                matches!(*last, AutodiscoverError::Transport(_)),
+                "the last endpoint's error should be kept, got {last:?}"
+            ),
+            other => panic!("unexpected outcome: {other:?}"),
+        }
+    }
+
+    // Test that a response which isn't well-formed XML is rejected.
+    #[test]
+    fn malformed_response_is_rejected() {
+        let res = "<Autodiscover><Response></Autodiscover>".to_string();
+        assert!(matches!(
+            get_urls_from_autodiscover_response(res),
+            Err(AutodiscoverError::MalformedResponse(_))
+        ));
+
+        // Well-formed, but not an autodiscover response.
+        let res = "<html><body><Account /></body></html>".to_string();
+        assert!(matches!(
+            get_urls_from_autodiscover_response(res),
+            Err(AutodiscoverError::MalformedResponse(_))
+        ));
+    }
+
+    // Test that a server redirecting to itself is eventually given up on.
+    #[tokio::test]
+    async fn redirect_loops_are_cut_short() {
+        let mut server = mockito::Server::new_async().await;
+        let url = format!("{}/autodiscover/autodiscover.xml", server.url());
+        let mock = server
+            .mock("POST", "/autodiscover/autodiscover.xml")
+            .with_body(format!(
+                r#"<?xml version="1.0" encoding="utf-8"?>
+<Autodiscover xmlns="http://schemas.microsoft.com/exchange/autodiscover/responseschema/2006">
+  <Response xmlns="http://schemas.microsoft.com/exchange/autodiscover/outlook/responseschema/2006a">
+    <Account>
+      <Action>redirectUrl</Action>
+      <RedirectUrl>{url}</RedirectUrl>
+    </Account>
+  </Response>
+</Autodiscover>"#
+            ))
+            .expect(MAX_REDIRECTS)
+            .create_async()
+            .await;
+
+        // The mock server only speaks plain HTTP, so redirects to it need to
+        // be let through.
+        let client = Client::new();
+        let session = Session {
+            client: &client,
+            may_follow: |_| true,
+        };
+        let address = "sylah@domain.test".parse().unwrap();
+        let result =
+            follow_redirects(&session, address, None, Step::RedirectUrl(url.clone())).await;
+
+        mock.assert_async().await;
+        assert!(
+            matches!(result, Err(AutodiscoverError::TooManyRedirects)),
+            "redirect loop should fail"
+        );
+    }
+
+    // Test that discovery goes through the client it's given, rather than
+    // creating its own. The client sends its requests through a proxy, which
+    // gets asked to open a tunnel to each of the endpoints in turn.
+    #[tokio::test]
+    async fn discovery_uses_provided_client() {
+        let mut server = mockito::Server::new_async().await;
+        let mut tunnel_to = |host: &str| {
+            server
+                .mock("CONNECT", mockito::Matcher::Any)
+                .match_header("host", host)
+                .match_header("user-agent", "shared-client")
+                .with_status(403)
+        };
+        // Both the V2 and the POX endpoints live on `autodiscover.{domain}`.
+        let autodiscover_host = tunnel_to("autodiscover.domain.test:443")
+            .expect(2)
+            .create_async()
+            .await;
+        let domain = tunnel_to("domain.test:443").create_async().await;
+
+        let client = Client::builder()
+            .user_agent("shared-client")
+            .proxy(reqwest::Proxy::https(server.url()).unwrap())
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap();
+        let address = "sylah@domain.test".parse().unwrap();
+        let outcome = discover_with_client(&client, &address, None).await;
+
+        autodiscover_host.assert_async().await;
+        domain.assert_async().await;
+        assert!(
+            matches!(outcome, Err(AutodiscoverError::AllEndpointsFailed { .. })),
+            "unexpected outcome: {outcome:?}"
+        );
+    }
+}
@@ -0,0 +1,64 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::{env, fs};
+
+use ews::{
+    net::request,
+    types::{DeleteItem, DeleteType, HasResponseMessages, ItemId, Response},
+    xml::{read_response, request_to_string},
+};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct Config {
+    username: String,
+    password: String,
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let config = fs::read_to_string("config.toml").expect("Unable to read config.toml");
+    let config: Config = toml::from_str(&config).expect("Unable to parse config.toml");
+
+    // Take the item to delete from the command line, e.g. as printed in full by
+    // the `find_item` example.
+    let mut args = env::args().skip(1);
+    let (Some(id), Some(change_key)) = (args.next(), args.next()) else {
+        eprintln!("Usage: delete_item <item ID> <change key>");
+        return;
+    };
+
+    // Move the message to the Deleted Items folder rather than deleting it
+    // outright, so that it can be recovered.
+    let body = DeleteItem::new(
+        DeleteType::MoveToDeletedItems,
+        vec![ItemId::new(&id, &change_key)],
+    );
+
+    let body = request_to_string(body).expect("Failed to write request");
+
+    let response = request(&config.username, &config.password, body)
+        .await
+        .expect("Unable to complete request");
+
+    let response = match read_response(&response)
+        .expect("Unable to parse XML")
+        .into_result()
+    {
+        Ok(response) => response,
+        Err(fault) => {
+            eprintln!("Server rejected the request: {fault}");
+            return;
+        }
+    };
+    let Response::DeleteItemResponse(response) = response else {
+        panic!("Could not find DeleteItemResponse");
+    };
+
+    match response.into_result() {
+        Ok(_) => println!("Moved the message to Deleted Items."),
+        Err(err) => eprintln!("Failed to delete the message: {err}"),
+    }
+}
@@ -6,11 +6,17 @@ use std::fs;
 
 use ews::{
     net::request,
-    types::{FindItem, FolderId, ItemShape, Response, SoapEnvelope},
-    xml::write_request,
+    types::{
+        BasePoint, DistinguishedFolderIdName, FindItem, FolderId, HasResponseMessages,
+        IndexedPageItemView, ItemShape, Response,
+    },
+    xml::{read_response, request_to_string},
 };
 use serde::Deserialize;
 
+/// The number of messages to request at a time.
+const PAGE_SIZE: u32 = 50;
+
 #[derive(Deserialize)]
 struct Config {
     username: String,
@@ -22,56 +28,85 @@ async fn main() {
     let config = fs::read_to_string("config.toml").expect("Unable to read config.toml");
     let config: Config = toml::from_str(&config).expect("Unable to parse config.toml");
 
-    // Construct the `FindItem` operation to list the contents of the inbox.
-    // Note that there's no pagination or filtering here, so the response could
-    // be a lot of messages.
-    let body = FindItem::new(
-        ews::types::Traversal::Shallow,
-        ItemShape {
-            base_shape: ews::types::BaseShape::Default,
-        },
-        vec![FolderId::DistinguishedFolderId {
-            id: "inbox".to_string(),
-            change_key: None,
-            mailbox: None,
-        }],
-    );
-
-    // Write the request as bytes.
-    let mut body_bytes = Vec::new();
-    if let Err(err) = write_request(&mut body_bytes, body) {
-        eprintln!("Failed to write request: {err}");
-    }
+    let mut offset = 0;
+    loop {
+        // Construct the `FindItem` operation to list a page of the contents of
+        // the inbox.
+        let body = FindItem::new(
+            ews::types::Traversal::Shallow,
+            ItemShape {
+                base_shape: ews::types::BaseShape::Default,
+                additional_properties: None,
+            },
+            vec![FolderId::DistinguishedFolderId {
+                id: DistinguishedFolderIdName::Inbox,
+                change_key: None,
+                mailbox: None,
+            }],
+        )
+        .with_view(IndexedPageItemView {
+            max_entries_returned: Some(PAGE_SIZE),
+            offset,
+            base_point: BasePoint::Beginning,
+        });
 
-    // Send the request to Office365.
-    let response = request(&config.username, &config.password, body_bytes)
-        .await
-        .expect("Unable to complete request");
+        // Write the request out.
+        let body = match request_to_string(body) {
+            Ok(body) => body,
+            Err(err) => {
+                eprintln!("Failed to write request: {err}");
+                return;
+            }
+        };
 
-    let response: SoapEnvelope = serde_xml_rs::from_str(&response).expect("Unable to parse XML");
-    match response.body.contents {
-        Response::FindItemResponse(response) => {
-            // Print a summary of what we found.
-            for message in response.messages() {
-                let id_short = message
-                    .item_id()
-                    .id()
-                    .get(0..10)
-                    .expect("Huh, thought IDs would be long");
-                let change_key_short = message
-                    .item_id()
-                    .change_key()
-                    .get(0..10)
-                    .expect("Thought change keys would be short too");
+        // Send the request to Office365.
+        let response = request(&config.username, &config.password, body)
+            .await
+            .expect("Unable to complete request");
 
-                println!(
-                    "{}...:{}...: {}",
-                    id_short,
-                    change_key_short,
-                    message.subject()
-                );
+        let response = match read_response(&response)
+            .expect("Unable to parse XML")
+            .into_result()
+        {
+            Ok(response) => response,
+            Err(fault) => {
+                eprintln!("Server rejected the request: {fault}");
+                return;
             }
+        };
+        let Response::FindItemResponse(response) = response else {
+            panic!("Could not find FindItemResponse");
+        };
+        let response = response
+            .into_result()
+            .expect("Server failed to process request");
+
+        // Print a summary of what we found.
+        for message in response.messages() {
+            let item_id = message.item_id().expect("Server should send item IDs");
+            let id_short = item_id
+                .id()
+                .get(0..10)
+                .expect("Huh, thought IDs would be long");
+            let change_key_short = item_id
+                .change_key()
+                .get(0..10)
+                .expect("Thought change keys would be short too");
+
+            println!(
+                "{}...:{}...: {}",
+                id_short,
+                change_key_short,
+                message.subject().unwrap_or("(no subject)")
+            );
+        }
+
+        // Carry on from where the server tells us this page ended, if there's
+        // anything left.
+        let root_folder = response.root_folders()[0];
+        match root_folder.indexed_paging_offset() {
+            Some(next_offset) if !root_folder.includes_last_item_in_range() => offset = next_offset,
+            _ => break,
         }
-        _ => panic!("Could not find FindItemResponse"),
     }
 }
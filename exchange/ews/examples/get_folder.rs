@@ -0,0 +1,77 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::fs;
+
+use ews::{
+    net::{EwsClient, DEFAULT_ENDPOINT},
+    types::{
+        BaseShape, DistinguishedFolderIdName, FolderId, FolderShape, GetFolder,
+        HasResponseMessages, Response,
+    },
+};
+use serde::Deserialize;
+
+/// The folders to report on.
+const FOLDERS: [DistinguishedFolderIdName; 4] = [
+    DistinguishedFolderIdName::Inbox,
+    DistinguishedFolderIdName::Drafts,
+    DistinguishedFolderIdName::SentItems,
+    DistinguishedFolderIdName::DeletedItems,
+];
+
+#[derive(Deserialize)]
+struct Config {
+    username: String,
+    password: String,
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let config = fs::read_to_string("config.toml").expect("Unable to read config.toml");
+    let config: Config = toml::from_str(&config).expect("Unable to parse config.toml");
+
+    let client = EwsClient::new(DEFAULT_ENDPOINT, &config.username, &config.password)
+        .expect("default endpoint should be valid");
+
+    // The default shape includes the display name and item counts.
+    let body = GetFolder::new(
+        FolderShape {
+            base_shape: BaseShape::Default,
+        },
+        FOLDERS
+            .iter()
+            .map(|id| FolderId::DistinguishedFolderId {
+                id: id.clone(),
+                change_key: None,
+                mailbox: None,
+            })
+            .collect(),
+    );
+
+    let response = match client.send(body).await {
+        Ok(response) => response,
+        Err(err) => {
+            eprintln!("Request failed: {err}");
+            return;
+        }
+    };
+    let Response::GetFolderResponse(response) = response else {
+        panic!("Could not find GetFolderResponse");
+    };
+    let response = response
+        .into_result()
+        .expect("Server failed to process request");
+
+    // Print a summary of each folder.
+    for folder in response.folders() {
+        println!(
+            "{}: {} items ({} unread), {} subfolders",
+            folder.display_name().unwrap_or("(no name)"),
+            folder.total_count().unwrap_or_default(),
+            folder.unread_count().unwrap_or_default(),
+            folder.child_folder_count().unwrap_or_default(),
+        );
+    }
+}
@@ -0,0 +1,100 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::fs;
+
+use ews::{
+    net::{EwsClient, DEFAULT_ENDPOINT},
+    types::{
+        BaseShape, DistinguishedFolderIdName, FieldUri, FindItem, FolderId, GetItem,
+        HasResponseMessages, ItemShape, Response, Traversal,
+    },
+};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct Config {
+    username: String,
+    password: String,
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let config = fs::read_to_string("config.toml").expect("Unable to read config.toml");
+    let config: Config = toml::from_str(&config).expect("Unable to parse config.toml");
+
+    // Both requests go through the same client, so the connection to the
+    // server is reused.
+    let client = EwsClient::new(DEFAULT_ENDPOINT, &config.username, &config.password)
+        .expect("default endpoint should be valid");
+
+    // List the IDs of the messages in the inbox, so we have something to get.
+    let body = FindItem::new(
+        Traversal::Shallow,
+        ItemShape {
+            base_shape: BaseShape::IdOnly,
+            additional_properties: None,
+        },
+        vec![FolderId::DistinguishedFolderId {
+            id: DistinguishedFolderIdName::Inbox,
+            change_key: None,
+            mailbox: None,
+        }],
+    );
+
+    let response = match client.send(body).await {
+        Ok(response) => response,
+        Err(err) => {
+            eprintln!("Request failed: {err}");
+            return;
+        }
+    };
+    let Response::FindItemResponse(response) = response else {
+        panic!("Could not find FindItemResponse");
+    };
+    let response = response
+        .into_result()
+        .expect("Server failed to process request");
+
+    let Some(item_id) = response
+        .messages()
+        .into_iter()
+        .find_map(|message| message.item_id())
+    else {
+        println!("The inbox is empty, nothing to get.");
+        return;
+    };
+
+    // Now get the full content of the first message, including its body,
+    // which isn't part of the default shape.
+    let body = GetItem::new(
+        ItemShape {
+            base_shape: BaseShape::Default,
+            additional_properties: Some(vec![FieldUri::ItemBody]),
+        },
+        vec![item_id.clone()],
+    );
+
+    let response = match client.send(body).await {
+        Ok(response) => response,
+        Err(err) => {
+            eprintln!("Request failed: {err}");
+            return;
+        }
+    };
+    let Response::GetItemResponse(response) = response else {
+        panic!("Could not find GetItemResponse");
+    };
+    let response = response
+        .into_result()
+        .expect("Server failed to process request");
+
+    for message in response.messages() {
+        println!("Subject: {}", message.subject().unwrap_or("(no subject)"));
+
+        if let Some(body) = message.body() {
+            println!("Body ({:?}):\n{}", body.body_type(), body.content());
+        }
+    }
+}
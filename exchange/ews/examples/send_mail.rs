@@ -0,0 +1,93 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::{env, fs};
+
+use ews::{
+    net::request,
+    types::{
+        Body, BodyType, CreateItem, EwsItem, HasResponseMessages, Mailbox, Message,
+        MessageDisposition, Response,
+    },
+    xml::{read_response, request_to_string},
+    EmailAddress,
+};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct Config {
+    username: String,
+    password: String,
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let config = fs::read_to_string("config.toml").expect("Unable to read config.toml");
+    let config: Config = toml::from_str(&config).expect("Unable to parse config.toml");
+
+    // Take the recipient from the command line.
+    let Some(recipient) = env::args().nth(1) else {
+        eprintln!("Usage: send_mail <recipient address>");
+        return;
+    };
+    let recipient: EmailAddress = match recipient.parse() {
+        Ok(recipient) => recipient,
+        Err(err) => {
+            eprintln!("{err}");
+            return;
+        }
+    };
+
+    let message = Message::default()
+        .with_subject("Hello from ews")
+        .with_body(Body::new(
+            BodyType::Html,
+            "<p>This message was sent by the <code>send_mail</code> example.</p>",
+        ))
+        .with_to_recipients(vec![Mailbox::new(&recipient, None)]);
+
+    // Send the message and keep a copy in the Sent Items folder.
+    let body = CreateItem::new(
+        MessageDisposition::SendAndSaveCopy,
+        None,
+        vec![EwsItem::Message(message)],
+    );
+
+    let body = request_to_string(body).expect("Failed to write request");
+
+    let response = request(&config.username, &config.password, body)
+        .await
+        .expect("Unable to complete request");
+
+    let response = match read_response(&response)
+        .expect("Unable to parse XML")
+        .into_result()
+    {
+        Ok(response) => response,
+        Err(fault) => {
+            eprintln!("Server rejected the request: {fault}");
+            return;
+        }
+    };
+    let Response::CreateItemResponse(response) = response else {
+        panic!("Could not find CreateItemResponse");
+    };
+    let response = match response.into_result() {
+        Ok(response) => response,
+        Err(err) => {
+            eprintln!("Failed to send the message: {err}");
+            return;
+        }
+    };
+
+    // Exchange doesn't always return the ID of the copy it saves for a sent
+    // message, so there may be nothing to print.
+    let item_ids = response.item_ids();
+    if item_ids.is_empty() {
+        println!("Sent the message.");
+    }
+    for item_id in item_ids {
+        println!("Created {} {}", item_id.id(), item_id.change_key());
+    }
+}
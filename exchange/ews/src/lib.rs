@@ -13,3 +13,12 @@ pub mod types;
 
 /// The `xml` module provides utilities for processing of XML.
 pub mod xml;
+
+pub use types::EwsError as Error;
+
+pub use email_address::{EmailAddress, EmailAddressError};
+
+/// The `vcr` module records interactions with a server so that tests can
+/// replay them without network access.
+#[cfg(feature = "test-vcr")]
+pub mod vcr;
@@ -2,23 +2,1627 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use reqwest::Body;
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
 
-const ENDPOINT: &str = "https://outlook.office365.com/EWS/Exchange.asmx";
+use base64::prelude::*;
+use reqwest::{
+    header::{HeaderValue, AUTHORIZATION, USER_AGENT, WWW_AUTHENTICATE},
+    Body, StatusCode,
+};
+
+use crate::{
+    types::{
+        BasePoint, BaseShape, EwsError, EwsWrite, ExchangeVersion, FieldUri, FindItem, FolderId,
+        HasResponseMessages, IndexedPageItemView, ItemId, ItemShape, RequestHeader, Response,
+        Restriction, SoapHeader, Traversal,
+    },
+    xml::{read_response, request_to_string_with_headers},
+};
+
+mod ntlm;
+
+/// The EWS endpoint for Office365, which requests are sent to unless another
+/// endpoint is given.
+pub const DEFAULT_ENDPOINT: &str = "https://outlook.office365.com/EWS/Exchange.asmx";
+
+/// The `User-Agent` sent with requests unless another is given. Some servers
+/// and proxies reject or throttle requests without one.
+pub const DEFAULT_USER_AGENT: &str = concat!("thundercell-ews/", env!("CARGO_PKG_VERSION"));
+
+/// How long a request may take, from connecting to reading the whole response
+/// body, unless another timeout is given.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// The number of items [`EwsClient::unread_item_ids`] asks for at a time.
+/// Exchange returns at most 1000 items per `FindItem` by default.
+const UNREAD_ITEM_IDS_PAGE_SIZE: u32 = 1000;
+
+/// The credentials used to authenticate requests.
+#[derive(Clone)]
+pub enum Auth {
+    /// Basic auth with a username and password. Office365 is phasing this out
+    /// in favour of OAuth2.
+    Basic { username: String, password: String },
+
+    /// An OAuth2 access token, sent as a `Bearer` token.
+    Bearer { token: String },
+
+    /// NTLM (NTLMv2) with a username, password and Windows domain, as often
+    /// required by on-premises Exchange servers.
+    ///
+    /// NTLM authenticates a connection rather than a request, so each request
+    /// is preceded by a handshake which relies on the client reusing the same
+    /// connection for it. To make sure it does, a client using NTLM keeps a
+    /// single idle connection, and runs one handshake at a time, holding back
+    /// other requests until the one it authorizes has been answered.
+    Ntlm {
+        username: String,
+        password: String,
+        domain: String,
+    },
+}
+
+impl Auth {
+    /// Adds the credentials to a request.
+    fn apply(&self, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match self {
+            Auth::Basic { username, password } => builder.basic_auth(username, Some(password)),
+            Auth::Bearer { token } => builder.bearer_auth(token),
+            // The handshake needs a round trip, so it's done when sending.
+            Auth::Ntlm { .. } => builder,
+        }
+    }
+}
 
 /// Sends the given request body to Office365 with Basic auth. (Gross.)
 pub async fn request<B: Into<Body>>(
     username: &str,
     password: &str,
     body: B,
-) -> Result<String, reqwest::Error> {
-    let client = reqwest::Client::new();
-    let response = client
-        .post(ENDPOINT)
-        .basic_auth(username, Some(password))
-        .body(body)
-        .send()
-        .await?;
-
-    response.text().await
+) -> Result<String, EwsError> {
+    let auth = Auth::Basic {
+        username: username.to_string(),
+        password: password.to_string(),
+    };
+
+    send(DEFAULT_ENDPOINT, &auth, body).await
+}
+
+/// Sends the given request body to Office365, authenticating with an OAuth2
+/// access token.
+pub async fn request_with_bearer<B: Into<Body>>(token: &str, body: B) -> Result<String, EwsError> {
+    let auth = Auth::Bearer {
+        token: token.to_string(),
+    };
+
+    send(DEFAULT_ENDPOINT, &auth, body).await
+}
+
+/// Sends the given request body to an EWS endpoint, e.g. one found through
+/// autodiscover for an on-premises server or another tenant.
+pub async fn request_to_endpoint<B: Into<Body>>(
+    endpoint: &str,
+    auth: &Auth,
+    body: B,
+) -> Result<String, EwsError> {
+    send(endpoint, auth, body).await
+}
+
+/// Checks that an endpoint is an absolute HTTP(S) URL.
+fn parse_endpoint(endpoint: &str) -> Result<reqwest::Url, EwsError> {
+    match reqwest::Url::parse(endpoint) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => Ok(url),
+        _ => Err(EwsError::InvalidEndpoint(endpoint.to_string())),
+    }
+}
+
+/// Sends a single request through a client of its own, with
+/// [`DEFAULT_TIMEOUT`] and the default [`RetryPolicy`]. Callers sending more
+/// than one request should keep an [`EwsClient`] around instead, so that
+/// connections are reused.
+async fn send<B: Into<Body>>(endpoint: &str, auth: &Auth, body: B) -> Result<String, EwsError> {
+    Ok(EwsClient::new_with_auth(endpoint, auth.clone())?
+        .send_raw(body)
+        .await?)
+}
+
+/// The parameters of a `Bearer` challenge sent by a server in a
+/// `WWW-Authenticate` header, which tell the client where to get an OAuth2
+/// token from.
+///
+/// Exchange sends one alongside its 401 responses when modern auth is
+/// available, so a failed Basic auth attempt can be used to discover the
+/// tenant's authorization endpoint.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct BearerChallenge {
+    authorization_uri: Option<String>,
+    resource: Option<String>,
+    scope: Option<String>,
+}
+
+impl BearerChallenge {
+    /// Finds the `Bearer` challenge in the value of a `WWW-Authenticate`
+    /// header, which may contain challenges for several schemes.
+    pub fn parse(header: &str) -> Option<Self> {
+        let (_, params) = parse_challenges(header)
+            .into_iter()
+            .find(|(scheme, _)| scheme.eq_ignore_ascii_case("Bearer"))?;
+
+        let mut challenge = Self::default();
+        for (name, value) in params {
+            let field = match name.to_ascii_lowercase().as_str() {
+                "authorization_uri" => &mut challenge.authorization_uri,
+                "resource" => &mut challenge.resource,
+                "scope" => &mut challenge.scope,
+                _ => continue,
+            };
+            *field = Some(value);
+        }
+
+        Some(challenge)
+    }
+
+    /// Finds the `Bearer` challenge in the `WWW-Authenticate` headers of a
+    /// response.
+    ///
+    /// An [`EwsClient`] returns 401 responses as errors, so their headers are
+    /// only available to a [`RequestInterceptor`].
+    pub fn from_response(response: &reqwest::Response) -> Option<Self> {
+        response
+            .headers()
+            .get_all(reqwest::header::WWW_AUTHENTICATE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .find_map(Self::parse)
+    }
+
+    /// The endpoint to send the user to in order to authorize the client.
+    pub fn authorization_uri(&self) -> Option<&str> {
+        self.authorization_uri.as_deref()
+    }
+
+    /// The resource a token should be requested for.
+    pub fn resource(&self) -> Option<&str> {
+        self.resource.as_deref()
+    }
+
+    /// The scope a token should be requested for.
+    pub fn scope(&self) -> Option<&str> {
+        self.scope.as_deref()
+    }
+}
+
+/// Splits the value of a `WWW-Authenticate` header into its challenges, each
+/// made of a scheme and a list of parameters (RFC 9110, section 11.6.1).
+fn parse_challenges(header: &str) -> Vec<(String, Vec<(String, String)>)> {
+    let mut challenges: Vec<(String, Vec<(String, String)>)> = Vec::new();
+    let mut chars = header.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace() || *c == ',').is_some() {}
+
+        let token: String =
+            std::iter::from_fn(|| chars.next_if(|c| !c.is_whitespace() && !matches!(c, '=' | ',')))
+                .collect();
+        if token.is_empty() {
+            break;
+        }
+
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+        if chars.next_if_eq(&'=').is_none() {
+            // A token on its own starts a new challenge.
+            challenges.push((token, Vec::new()));
+            continue;
+        }
+
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+        let value = if chars.next_if_eq(&'"').is_some() {
+            let mut value = String::new();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' => break,
+                    '\\' => value.extend(chars.next()),
+                    c => value.push(c),
+                }
+            }
+            value
+        } else {
+            std::iter::from_fn(|| chars.next_if(|c| !c.is_whitespace() && *c != ',')).collect()
+        };
+
+        // Parameters before any scheme are malformed and ignored.
+        if let Some((_, params)) = challenges.last_mut() {
+            params.push((token, value));
+        }
+    }
+
+    challenges
+}
+
+/// An error occurring while sending a request over HTTP.
+#[derive(Debug)]
+pub enum HttpError {
+    /// No response was received before the request timed out.
+    TimedOut(reqwest::Error),
+
+    /// The request could not be sent, or the response could not be read.
+    Transport(reqwest::Error),
+
+    /// The server responded with an error status, e.g. 401 for invalid
+    /// credentials, or kept responding with one indicating it's temporarily
+    /// unable to handle the request.
+    Status(StatusCode),
+
+    /// The response body, once decompressed, isn't valid UTF-8.
+    InvalidBody(std::string::FromUtf8Error),
+
+    /// The server didn't answer the start of an NTLM handshake with a valid
+    /// challenge, e.g. because it doesn't support NTLM.
+    NtlmChallenge,
+
+    /// The OS couldn't provide the random bytes needed to answer an NTLM
+    /// challenge.
+    Random(getrandom::Error),
+}
+
+impl std::fmt::Display for HttpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HttpError::TimedOut(err) => write!(f, "request timed out: {err}"),
+            HttpError::Transport(err) => err.fmt(f),
+            HttpError::Status(status) => write!(f, "server responded with status {status}"),
+            HttpError::InvalidBody(err) => write!(f, "response body is not valid UTF-8: {err}"),
+            HttpError::NtlmChallenge => write!(f, "server sent no valid NTLM challenge"),
+            HttpError::Random(err) => write!(f, "failed to generate NTLM client challenge: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for HttpError {}
+
+impl From<reqwest::Error> for HttpError {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_timeout() {
+            HttpError::TimedOut(err)
+        } else {
+            HttpError::Transport(err)
+        }
+    }
+}
+
+/// How an [`EwsClient`] retries requests the server was temporarily unable to
+/// handle, i.e. those answered with a 429, 502, 503 or 504 status.
+///
+/// A 500 status isn't retried, as that's what Exchange uses to send SOAP
+/// faults.
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    /// The number of times a request is retried before giving up.
+    pub max_retries: u32,
+
+    /// The delay before the first retry, which doubles for each subsequent
+    /// one. A `Retry-After` header, giving either a number of seconds or an
+    /// HTTP date, takes precedence.
+    pub initial_backoff: Duration,
+
+    /// The longest delay before a retry. The doubling delay stops growing
+    /// once it reaches this, but a request isn't retried at all if the server
+    /// asks for a longer delay through `Retry-After`.
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The delay before retrying after the given number of retries, or `None`
+    /// if the server asks for a longer delay than the policy allows.
+    fn backoff(&self, retries: u32, response: &reqwest::Response) -> Option<Duration> {
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_retry_after);
+
+        match retry_after {
+            Some(delay) => (delay <= self.max_backoff).then_some(delay),
+            None => Some(
+                self.initial_backoff
+                    .saturating_mul(2u32.saturating_pow(retries))
+                    .min(self.max_backoff),
+            ),
+        }
+    }
+}
+
+/// Parses the value of a `Retry-After` header into the delay it asks for.
+///
+/// The value is either a number of seconds or an HTTP date, which gives no
+/// delay if it's already passed.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(
+        date.duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+/// Whether a status indicates the server may be able to handle the request
+/// if it's sent again later.
+fn is_transient(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// A hook run around each request sent by an [`EwsClient`], e.g. to collect
+/// metrics or add headers.
+pub trait RequestInterceptor: Send + Sync {
+    /// Called with each request right before it's sent. The request may be
+    /// modified.
+    fn before(&self, _request: &mut reqwest::Request) {}
+
+    /// Called with the response to each request, before its body is read.
+    fn after(&self, _response: &reqwest::Response) {}
+}
+
+/// A client for sending requests to a specific EWS endpoint.
+///
+/// The client keeps a pool of connections to the server, so it's much cheaper
+/// to send several requests through one client than through a client each.
+/// It's `Send` and `Sync`, and clones share the same pool, so a single client
+/// can be shared between tasks, either by reference or by cloning it.
+#[derive(Clone)]
+pub struct EwsClient {
+    client: reqwest::Client,
+    endpoint: reqwest::Url,
+    auth: Auth,
+    user_agent: String,
+    anchor_mailbox: Option<String>,
+    server_version: ExchangeVersion,
+    headers: Vec<RequestHeader>,
+    timeout: Duration,
+    retry_policy: RetryPolicy,
+    interceptors: Vec<Arc<dyn RequestInterceptor>>,
+
+    /// Held from the start of an NTLM handshake until the request it
+    /// authorizes has been answered, so that no other request takes the
+    /// connection in between.
+    ntlm_lock: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl EwsClient {
+    /// Creates a new client which sends requests to the given endpoint with
+    /// Basic auth.
+    ///
+    /// The endpoint must be an absolute HTTP(S) URL.
+    pub fn new(endpoint: &str, username: &str, password: &str) -> Result<Self, EwsError> {
+        Self::new_with_auth(
+            endpoint,
+            Auth::Basic {
+                username: username.to_string(),
+                password: password.to_string(),
+            },
+        )
+    }
+
+    /// Creates a new client which sends requests to the given endpoint with
+    /// the given credentials.
+    ///
+    /// The client asks for responses to be compressed with gzip or deflate,
+    /// and transparently decompresses them.
+    ///
+    /// The endpoint must be an absolute HTTP(S) URL.
+    pub fn new_with_auth(endpoint: &str, auth: Auth) -> Result<Self, EwsError> {
+        let endpoint = parse_endpoint(endpoint)?;
+
+        let client = match auth {
+            // NTLM isn't supported over HTTP/2, which may otherwise be
+            // negotiated for HTTPS endpoints. Keeping a single idle connection
+            // means the one a handshake ran on is the one picked up next.
+            Auth::Ntlm { .. } => reqwest::Client::builder()
+                .http1_only()
+                .pool_max_idle_per_host(1)
+                .build()?,
+            _ => reqwest::Client::new(),
+        };
+
+        Ok(Self {
+            client,
+            endpoint,
+            auth,
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            anchor_mailbox: None,
+            server_version: ExchangeVersion::default(),
+            headers: Vec::new(),
+            timeout: DEFAULT_TIMEOUT,
+            retry_policy: RetryPolicy::default(),
+            interceptors: Vec::new(),
+            ntlm_lock: Arc::default(),
+        })
+    }
+
+    /// Sets the `User-Agent` sent with each request, instead of
+    /// [`DEFAULT_USER_AGENT`].
+    pub fn with_user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = user_agent.to_string();
+        self
+    }
+
+    /// Sends the SMTP address of the mailbox being accessed in an
+    /// `X-AnchorMailbox` header with each request.
+    ///
+    /// Office365 uses it to route requests straight to the server holding the
+    /// mailbox, and requires it for some operations on shared mailboxes or
+    /// with [`SoapHeader::ExchangeImpersonation`](crate::types::SoapHeader).
+    pub fn with_anchor_mailbox(mut self, smtp_address: &str) -> Self {
+        self.anchor_mailbox = Some(smtp_address.to_string());
+        self
+    }
+
+    /// Sets the version of the EWS schema requests are written against, sent
+    /// in a `RequestServerVersion` header, instead of
+    /// [`ExchangeVersion::default`].
+    pub fn with_server_version(mut self, version: ExchangeVersion) -> Self {
+        self.server_version = version;
+        self
+    }
+
+    /// Sets SOAP headers to send with each request, e.g. to impersonate
+    /// another user with [`SoapHeader::ExchangeImpersonation`].
+    ///
+    /// They're sent after the `RequestServerVersion` header, which is always
+    /// sent and so shouldn't be among them; see
+    /// [`EwsClient::with_server_version`].
+    pub fn with_headers(mut self, headers: Vec<RequestHeader>) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    /// Sets how long to wait for each request, including reading the response
+    /// body, before giving up on it, instead of [`DEFAULT_TIMEOUT`].
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets how to retry requests the server was temporarily unable to
+    /// handle, instead of [`RetryPolicy::default`]. A policy with no retries
+    /// means requests are only attempted once.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Adds an interceptor to run around each request. Interceptors run in
+    /// the order they were added.
+    pub fn with_interceptor<I: RequestInterceptor + 'static>(mut self, interceptor: I) -> Self {
+        self.interceptors.push(Arc::new(interceptor));
+        self
+    }
+
+    /// Sends an already-serialized SOAP envelope and returns the raw response
+    /// body.
+    ///
+    /// This is an escape hatch for operations we don't model yet; no
+    /// serialization or validation is done on the body.
+    ///
+    /// Responses with an error status are returned as
+    /// [`HttpError::Status`], except for the 500 status Exchange sends SOAP
+    /// faults with, whose body is returned for parsing.
+    ///
+    /// Interceptors run around each attempt if the request is retried. Bodies
+    /// which can't be cloned, i.e. streams, are never retried.
+    pub async fn send_raw<B: Into<Body>>(&self, body: B) -> Result<String, HttpError> {
+        let response = self.send_streaming(body).await?;
+
+        // Rather than `text()`, which would quietly replace invalid
+        // sequences, so that a mangled body doesn't reach the parser.
+        let body = response.bytes().await?;
+        String::from_utf8(Vec::from(body)).map_err(HttpError::InvalidBody)
+    }
+
+    /// Sends an already-serialized SOAP envelope and returns the response
+    /// without reading its body.
+    ///
+    /// [`send_raw`](Self::send_raw) holds the whole body in memory at once,
+    /// which adds up for e.g. a `GetItem` response carrying large attachments.
+    /// The body of the response returned here can instead be read a chunk at
+    /// a time with [`reqwest::Response::chunk`] and handed on as it arrives,
+    /// at the cost of decoding and validating it being left to the caller.
+    ///
+    /// Error statuses are handled, and requests retried, in the same way as
+    /// with `send_raw`.
+    pub async fn send_streaming<B: Into<Body>>(
+        &self,
+        body: B,
+    ) -> Result<reqwest::Response, HttpError> {
+        let mut builder = self
+            .auth
+            .apply(self.client.post(self.endpoint.clone()))
+            .header(USER_AGENT, &self.user_agent)
+            .header("Content-Type", "text/xml; charset=utf-8")
+            .body(body);
+
+        if let Some(anchor_mailbox) = &self.anchor_mailbox {
+            builder = builder.header("X-AnchorMailbox", anchor_mailbox);
+        }
+
+        let mut request = builder.timeout(self.timeout).build()?;
+        let mut retries = 0;
+        loop {
+            let retry_policy =
+                (retries < self.retry_policy.max_retries).then_some(self.retry_policy);
+
+            // Keep a pristine copy to send if this attempt needs retrying.
+            let next_request = retry_policy.and_then(|_| request.try_clone());
+
+            let ntlm_guard = match self.auth {
+                Auth::Ntlm { .. } => Some(self.ntlm_lock.lock().await),
+                _ => None,
+            };
+            self.authorize_ntlm(&mut request).await?;
+
+            for interceptor in &self.interceptors {
+                interceptor.before(&mut request);
+            }
+
+            let response = self.client.execute(request).await?;
+            drop(ntlm_guard);
+
+            for interceptor in &self.interceptors {
+                interceptor.after(&response);
+            }
+
+            let status = response.status();
+            if status.is_success() || status == StatusCode::INTERNAL_SERVER_ERROR {
+                return Ok(response);
+            }
+
+            if !is_transient(status) {
+                return Err(HttpError::Status(status));
+            }
+
+            let delay =
+                retry_policy.and_then(|retry_policy| retry_policy.backoff(retries, &response));
+            let (Some(delay), Some(next_request)) = (delay, next_request) else {
+                return Err(HttpError::Status(status));
+            };
+
+            tokio::time::sleep(delay).await;
+            request = next_request;
+            retries += 1;
+        }
+    }
+
+    /// Runs an NTLM handshake, if the client uses NTLM, and adds the resulting
+    /// authenticate message to a request.
+    ///
+    /// The handshake's negotiate request is sent without a body, and isn't
+    /// seen by interceptors.
+    async fn authorize_ntlm(&self, request: &mut reqwest::Request) -> Result<(), HttpError> {
+        let Auth::Ntlm {
+            username,
+            password,
+            domain,
+        } = &self.auth
+        else {
+            return Ok(());
+        };
+
+        let response = self
+            .client
+            .post(self.endpoint.clone())
+            .header(USER_AGENT, &self.user_agent)
+            .header(
+                AUTHORIZATION,
+                format!("NTLM {}", BASE64_STANDARD.encode(ntlm::negotiate_message())),
+            )
+            .timeout(self.timeout)
+            .send()
+            .await?;
+        let challenge = response
+            .headers()
+            .get_all(WWW_AUTHENTICATE)
+            .iter()
+            .filter_map(|value| value.to_str().ok()?.split_once(' '))
+            .find(|(scheme, _)| scheme.eq_ignore_ascii_case("NTLM"))
+            .and_then(|(_, message)| BASE64_STANDARD.decode(message.trim()).ok())
+            .and_then(|message| ntlm::Challenge::parse(&message));
+
+        // Reading the body releases the connection back to the pool, where the
+        // request carrying the answer to the challenge can pick it up again.
+        response.bytes().await?;
+
+        let challenge = challenge.ok_or(HttpError::NtlmChallenge)?;
+
+        let mut client_challenge = [0; 8];
+        getrandom::getrandom(&mut client_challenge).map_err(HttpError::Random)?;
+
+        let message = ntlm::authenticate_message(
+            &challenge,
+            username,
+            password,
+            domain,
+            client_challenge,
+            ntlm::current_timestamp(),
+        );
+        let value = format!("NTLM {}", BASE64_STANDARD.encode(message));
+        request.headers_mut().insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&value).expect("base64 should be a valid header value"),
+        );
+
+        Ok(())
+    }
+
+    /// Sends a request and parses the response.
+    ///
+    /// SOAP faults are returned as errors; the response itself still needs
+    /// checking with its `into_result()` method.
+    pub async fn send<X: for<'a> EwsWrite<&'a mut Vec<u8>>>(
+        &self,
+        body: X,
+    ) -> Result<Response, EwsError> {
+        let mut headers = vec![RequestHeader::new(SoapHeader::RequestServerVersion {
+            version: self.server_version,
+        })];
+        headers.extend(self.headers.iter().cloned());
+
+        let response = self
+            .send_raw(request_to_string_with_headers(&headers, body)?)
+            .await?;
+        Ok(read_response(&response)?.into_result()?)
+    }
+
+    /// Lists the identifiers of the unread messages in a folder.
+    ///
+    /// Only identifiers are requested, which makes this much cheaper than
+    /// fetching the messages when all that's needed is e.g. an unread count.
+    /// They're requested a page at a time, as servers cap the number of items
+    /// returned by a single `FindItem`.
+    pub async fn unread_item_ids(&self, folder: FolderId) -> Result<Vec<ItemId>, EwsError> {
+        let mut ids = Vec::new();
+        let mut offset = 0;
+        loop {
+            let request = FindItem::new(
+                Traversal::Shallow,
+                ItemShape {
+                    base_shape: BaseShape::IdOnly,
+                    additional_properties: None,
+                },
+                vec![folder.clone()],
+            )
+            .with_restriction(Restriction::IsEqualTo {
+                field: FieldUri::MessageIsRead,
+                value: "false".to_string(),
+            })
+            .with_view(IndexedPageItemView {
+                max_entries_returned: Some(UNREAD_ITEM_IDS_PAGE_SIZE),
+                offset,
+                base_point: BasePoint::Beginning,
+            });
+
+            let Response::FindItemResponse(response) = self.send(request).await? else {
+                return Err(EwsError::UnexpectedResponse);
+            };
+            let response = response.into_result()?;
+
+            let messages = response.messages();
+            ids.extend(
+                messages
+                    .iter()
+                    .filter_map(|message| message.item_id().cloned()),
+            );
+
+            let root_folders = response.root_folders();
+            if root_folders
+                .iter()
+                .all(|root_folder| root_folder.includes_last_item_in_range())
+            {
+                return Ok(ids);
+            }
+
+            let next_offset = root_folders
+                .iter()
+                .find_map(|root_folder| root_folder.indexed_paging_offset())
+                .unwrap_or_else(|| {
+                    offset.saturating_add(u32::try_from(messages.len()).unwrap_or(u32::MAX))
+                });
+
+            // Don't ask for the same page again if the server makes no
+            // progress.
+            if next_offset <= offset {
+                return Ok(ids);
+            }
+            offset = next_offset;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::DistinguishedFolderIdName;
+
+    #[tokio::test]
+    async fn send_raw_posts_body_unmodified() {
+        let body = concat!(
+            r#"<soap:Envelope xmlns:soap="http://schemas.xmlsoap.org/soap/envelope/" "#,
+            r#"xmlns:t="http://schemas.microsoft.com/exchange/services/2006/types">"#,
+            r#"<soap:Body><FindItem xmlns="http://schemas.microsoft.com/exchange/services/2006/messages" "#,
+            r#"Traversal="Shallow"><ItemShape><t:BaseShape>IdOnly</t:BaseShape></ItemShape>"#,
+            r#"<ParentFolderIds><t:DistinguishedFolderId Id="inbox" /></ParentFolderIds>"#,
+            r#"</FindItem></soap:Body></soap:Envelope>"#
+        );
+
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/EWS/Exchange.asmx")
+            // "user:pass" in base64.
+            .match_header("authorization", "Basic dXNlcjpwYXNz")
+            .match_header("content-type", "text/xml; charset=utf-8")
+            .match_body(body)
+            .with_body("<soap:Envelope />")
+            .create_async()
+            .await;
+
+        let client = EwsClient::new(
+            &format!("{}/EWS/Exchange.asmx", server.url()),
+            "user",
+            "pass",
+        )
+        .unwrap();
+        let response = client.send_raw(body).await.expect("request should succeed");
+
+        mock.assert_async().await;
+        assert_eq!(response, "<soap:Envelope />");
+    }
+
+    #[tokio::test]
+    async fn compressed_responses_are_decoded() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let body = r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/">
+            <s:Body>
+                <m:FindItemResponse
+                    xmlns:m="http://schemas.microsoft.com/exchange/services/2006/messages"
+                    xmlns:t="http://schemas.microsoft.com/exchange/services/2006/types">
+                    <m:ResponseMessages>
+                        <m:FindItemResponseMessage ResponseClass="Success">
+                            <m:ResponseCode>NoError</m:ResponseCode>
+                            <m:RootFolder TotalItemsInView="1" IncludesLastItemInRange="true">
+                                <t:Items>
+                                    <t:Message>
+                                        <t:ItemId Id="AAMkAD" ChangeKey="CQAAAB" />
+                                    </t:Message>
+                                </t:Items>
+                            </m:RootFolder>
+                        </m:FindItemResponseMessage>
+                    </m:ResponseMessages>
+                </m:FindItemResponse>
+            </s:Body>
+        </s:Envelope>"#;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(body.as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/EWS/Exchange.asmx")
+            .match_header(
+                "accept-encoding",
+                mockito::Matcher::Regex("gzip".to_string()),
+            )
+            .with_header("Content-Encoding", "gzip")
+            .with_body(compressed)
+            .create_async()
+            .await;
+
+        let client = EwsClient::new(
+            &format!("{}/EWS/Exchange.asmx", server.url()),
+            "user",
+            "pass",
+        )
+        .unwrap();
+        let ids = client
+            .unread_item_ids(FolderId::DistinguishedFolderId {
+                id: DistinguishedFolderIdName::Inbox,
+                change_key: None,
+                mailbox: None,
+            })
+            .await
+            .expect("compressed response should be parsed");
+
+        mock.assert_async().await;
+        let ids: Vec<_> = ids.iter().map(ItemId::id).collect();
+        assert_eq!(ids, ["AAMkAD"]);
+    }
+
+    #[tokio::test]
+    async fn invalid_utf8_is_rejected() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/EWS/Exchange.asmx")
+            .with_body(b"<soap:Envelope>\xff</soap:Envelope>")
+            .create_async()
+            .await;
+
+        let client = EwsClient::new(
+            &format!("{}/EWS/Exchange.asmx", server.url()),
+            "user",
+            "pass",
+        )
+        .unwrap();
+        let result = client.send_raw("<soap:Envelope />").await;
+
+        assert!(
+            matches!(result, Err(HttpError::InvalidBody(_))),
+            "invalid UTF-8 should be rejected"
+        );
+    }
+
+    #[tokio::test]
+    async fn response_can_be_streamed() {
+        let body =
+            "<soap:Envelope>".to_string() + &"<t:Item />".repeat(10_000) + "</soap:Envelope>";
+
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/EWS/Exchange.asmx")
+            .with_chunked_body({
+                let body = body.clone();
+                move |writer| {
+                    for chunk in body.as_bytes().chunks(4096) {
+                        writer.write_all(chunk)?;
+                    }
+                    Ok(())
+                }
+            })
+            .create_async()
+            .await;
+
+        let client = EwsClient::new(
+            &format!("{}/EWS/Exchange.asmx", server.url()),
+            "user",
+            "pass",
+        )
+        .unwrap();
+        let mut response = client
+            .send_streaming("<soap:Envelope />")
+            .await
+            .expect("request should succeed");
+
+        let mut received = Vec::new();
+        while let Some(chunk) = response.chunk().await.expect("chunk should be read") {
+            received.extend_from_slice(&chunk);
+        }
+
+        mock.assert_async().await;
+        assert_eq!(received, body.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn user_agent_is_sent() {
+        let mut server = mockito::Server::new_async().await;
+        let default = server
+            .mock("POST", "/EWS/Exchange.asmx")
+            .match_header(
+                "user-agent",
+                format!("thundercell-ews/{}", env!("CARGO_PKG_VERSION")).as_str(),
+            )
+            .create_async()
+            .await;
+
+        let endpoint = format!("{}/EWS/Exchange.asmx", server.url());
+        let client = EwsClient::new(&endpoint, "user", "pass").unwrap();
+        client.send_raw("<soap:Envelope />").await.unwrap();
+        default.assert_async().await;
+
+        let custom = server
+            .mock("POST", "/EWS/Exchange.asmx")
+            .match_header("user-agent", "Thunderbird/128.0")
+            .create_async()
+            .await;
+
+        let client = client.with_user_agent("Thunderbird/128.0");
+        client.send_raw("<soap:Envelope />").await.unwrap();
+        custom.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn soap_headers_are_sent() {
+        const RESPONSE: &str = r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/">
+            <s:Body><ExportItemsResponse>hello</ExportItemsResponse></s:Body>
+        </s:Envelope>"#;
+
+        let mut server = mockito::Server::new_async().await;
+        let default = server
+            .mock("POST", "/EWS/Exchange.asmx")
+            .match_body(mockito::Matcher::Regex(
+                r#"<soap:Header><t:RequestServerVersion Version="Exchange2013_SP1" /></soap:Header>"#
+                    .to_string(),
+            ))
+            .with_body(RESPONSE)
+            .create_async()
+            .await;
+
+        let endpoint = format!("{}/EWS/Exchange.asmx", server.url());
+        let client = EwsClient::new(&endpoint, "user", "pass").unwrap();
+        client.send(BaseShape::IdOnly).await.unwrap();
+        default.assert_async().await;
+
+        let custom = server
+            .mock("POST", "/EWS/Exchange.asmx")
+            .match_body(mockito::Matcher::Regex(
+                concat!(
+                    r#"<soap:Header><t:RequestServerVersion Version="Exchange2016" />"#,
+                    r#"<t:ExchangeImpersonation soap:mustUnderstand="1">"#
+                )
+                .to_string(),
+            ))
+            .with_body(RESPONSE)
+            .create_async()
+            .await;
+
+        let client = client
+            .with_server_version(ExchangeVersion::Exchange2016)
+            .with_headers(vec![RequestHeader::new(
+                SoapHeader::ExchangeImpersonation {
+                    primary_smtp_address: "alice@example.com".to_string(),
+                },
+            )
+            .must_understand(true)]);
+        client.send(BaseShape::IdOnly).await.unwrap();
+        custom.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn anchor_mailbox_is_sent_when_set() {
+        let mut server = mockito::Server::new_async().await;
+        let without = server
+            .mock("POST", "/EWS/Exchange.asmx")
+            .match_header("x-anchormailbox", mockito::Matcher::Missing)
+            .create_async()
+            .await;
+
+        let endpoint = format!("{}/EWS/Exchange.asmx", server.url());
+        let client = EwsClient::new(&endpoint, "user", "pass").unwrap();
+        client.send_raw("<soap:Envelope />").await.unwrap();
+        without.assert_async().await;
+
+        let with = server
+            .mock("POST", "/EWS/Exchange.asmx")
+            .match_header("x-anchormailbox", "shared@example.com")
+            .create_async()
+            .await;
+
+        let client = client.with_anchor_mailbox("shared@example.com");
+        client.send_raw("<soap:Envelope />").await.unwrap();
+        with.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn bearer_token_is_sent() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/EWS/Exchange.asmx")
+            .match_header("authorization", "Bearer eyJ0eXAi")
+            .with_body("<soap:Envelope />")
+            .create_async()
+            .await;
+
+        let client = EwsClient::new_with_auth(
+            &format!("{}/EWS/Exchange.asmx", server.url()),
+            Auth::Bearer {
+                token: "eyJ0eXAi".to_string(),
+            },
+        )
+        .unwrap();
+        client
+            .send_raw("<soap:Envelope />")
+            .await
+            .expect("request should succeed");
+
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn ntlm_handshake_precedes_request() {
+        // A challenge from MS-NLMP 4.2.4.3, with the NetBIOS domain name
+        // "Domain" and computer name "Server".
+        let challenge = concat!(
+            "TlRMTVNTUAACAAAADAAMADgAAAAzgoriASNFZ4mrze8AAAAAAAAAACQAJABEAAAABgBwFwAAAA9TAGUAcgB2",
+            "AGUAcgACAAwARABvAG0AYQBpAG4AAQAMAFMAZQByAHYAZQByAAAAAAA=",
+        );
+
+        let mut server = mockito::Server::new_async().await;
+        // "NTLMSSP\0" followed by the message type, in base64.
+        let negotiate = server
+            .mock("POST", "/EWS/Exchange.asmx")
+            .match_header(
+                "authorization",
+                mockito::Matcher::Regex("^NTLM TlRMTVNTUAABAAAA".to_string()),
+            )
+            .match_body("")
+            .with_status(401)
+            .with_header("WWW-Authenticate", &format!("NTLM {challenge}"))
+            .create_async()
+            .await;
+        let authenticate = server
+            .mock("POST", "/EWS/Exchange.asmx")
+            .match_header(
+                "authorization",
+                mockito::Matcher::Regex("^NTLM TlRMTVNTUAADAAAA".to_string()),
+            )
+            .match_body("<soap:Envelope />")
+            .with_body("<soap:Envelope />")
+            .create_async()
+            .await;
+
+        let client = EwsClient::new_with_auth(
+            &format!("{}/EWS/Exchange.asmx", server.url()),
+            Auth::Ntlm {
+                username: "User".to_string(),
+                password: "Password".to_string(),
+                domain: "Domain".to_string(),
+            },
+        )
+        .unwrap();
+        let response = client
+            .send_raw("<soap:Envelope />")
+            .await
+            .expect("request should succeed");
+
+        negotiate.assert_async().await;
+        authenticate.assert_async().await;
+        assert_eq!(response, "<soap:Envelope />");
+    }
+
+    #[tokio::test]
+    async fn ntlm_requests_are_sent_one_at_a_time() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        /// Tracks how many requests are awaiting a response at once.
+        #[derive(Default)]
+        struct InFlight {
+            current: AtomicUsize,
+            max: AtomicUsize,
+        }
+
+        struct InFlightInterceptor(Arc<InFlight>);
+
+        impl RequestInterceptor for InFlightInterceptor {
+            fn before(&self, _request: &mut reqwest::Request) {
+                let current = self.0.current.fetch_add(1, Ordering::SeqCst) + 1;
+                self.0.max.fetch_max(current, Ordering::SeqCst);
+            }
+
+            fn after(&self, _response: &reqwest::Response) {
+                self.0.current.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+
+        let challenge = concat!(
+            "TlRMTVNTUAACAAAADAAMADgAAAAzgoriASNFZ4mrze8AAAAAAAAAACQAJABEAAAABgBwFwAAAA9TAGUAcgB2",
+            "AGUAcgACAAwARABvAG0AYQBpAG4AAQAMAFMAZQByAHYAZQByAAAAAAA=",
+        );
+
+        let mut server = mockito::Server::new_async().await;
+        let negotiate = server
+            .mock("POST", "/EWS/Exchange.asmx")
+            .match_header(
+                "authorization",
+                mockito::Matcher::Regex("^NTLM TlRMTVNTUAABAAAA".to_string()),
+            )
+            .with_status(401)
+            .with_header("WWW-Authenticate", &format!("NTLM {challenge}"))
+            .expect(3)
+            .create_async()
+            .await;
+        let authenticate = server
+            .mock("POST", "/EWS/Exchange.asmx")
+            .match_header(
+                "authorization",
+                mockito::Matcher::Regex("^NTLM TlRMTVNTUAADAAAA".to_string()),
+            )
+            .with_body("<soap:Envelope />")
+            .expect(3)
+            .create_async()
+            .await;
+
+        let in_flight = Arc::new(InFlight::default());
+        let client = EwsClient::new_with_auth(
+            &format!("{}/EWS/Exchange.asmx", server.url()),
+            Auth::Ntlm {
+                username: "User".to_string(),
+                password: "Password".to_string(),
+                domain: "Domain".to_string(),
+            },
+        )
+        .unwrap()
+        .with_interceptor(InFlightInterceptor(in_flight.clone()));
+
+        let (first, second, third) = tokio::join!(
+            client.send_raw("<soap:Envelope />"),
+            client.send_raw("<soap:Envelope />"),
+            client.send_raw("<soap:Envelope />"),
+        );
+        for result in [first, second, third] {
+            result.expect("request should succeed");
+        }
+
+        negotiate.assert_async().await;
+        authenticate.assert_async().await;
+        assert_eq!(in_flight.max.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn missing_ntlm_challenge_is_an_error() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/EWS/Exchange.asmx")
+            .with_status(401)
+            .with_header("WWW-Authenticate", "Basic realm=\"Exchange\"")
+            .create_async()
+            .await;
+
+        let client = EwsClient::new_with_auth(
+            &format!("{}/EWS/Exchange.asmx", server.url()),
+            Auth::Ntlm {
+                username: "User".to_string(),
+                password: "Password".to_string(),
+                domain: "Domain".to_string(),
+            },
+        )
+        .unwrap();
+        let result = client.send_raw("<soap:Envelope />").await;
+
+        assert!(
+            matches!(result, Err(HttpError::NtlmChallenge)),
+            "handshake should fail without a challenge"
+        );
+    }
+
+    #[tokio::test]
+    async fn request_is_sent_to_given_endpoint() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/ews/exchange.asmx")
+            .match_header("authorization", "Bearer eyJ0eXAi")
+            .with_body("<soap:Envelope />")
+            .create_async()
+            .await;
+
+        let auth = Auth::Bearer {
+            token: "eyJ0eXAi".to_string(),
+        };
+        let response = request_to_endpoint(
+            &format!("{}/ews/exchange.asmx", server.url()),
+            &auth,
+            "<soap:Envelope />",
+        )
+        .await
+        .expect("request should succeed");
+
+        mock.assert_async().await;
+        assert_eq!(response, "<soap:Envelope />");
+    }
+
+    #[tokio::test]
+    async fn relative_endpoint_is_rejected() {
+        let auth = Auth::Bearer {
+            token: "eyJ0eXAi".to_string(),
+        };
+
+        for endpoint in [
+            "/EWS/Exchange.asmx",
+            "mail.example.com/EWS/Exchange.asmx",
+            "mailto:alice@example.com",
+        ] {
+            let result = request_to_endpoint(endpoint, &auth, "<soap:Envelope />").await;
+            assert!(
+                matches!(&result, Err(EwsError::InvalidEndpoint(e)) if e == endpoint),
+                "{endpoint} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn client_rejects_relative_endpoint() {
+        let result = EwsClient::new("not a url", "user", "pass");
+        assert!(matches!(
+            result,
+            Err(EwsError::InvalidEndpoint(e)) if e == "not a url"
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stalled_server_times_out() {
+        // The connection is accepted by the OS, but nothing ever answers.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!(
+            "http://{}/EWS/Exchange.asmx",
+            listener.local_addr().unwrap()
+        );
+
+        let auth = Auth::Bearer {
+            token: "eyJ0eXAi".to_string(),
+        };
+        let result = request_to_endpoint(&endpoint, &auth, "<soap:Envelope />").await;
+
+        assert!(
+            matches!(result, Err(EwsError::Transport(HttpError::TimedOut(_)))),
+            "request should time out, got {result:?}"
+        );
+    }
+
+    #[test]
+    fn client_can_be_shared_between_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<EwsClient>();
+    }
+
+    #[tokio::test]
+    async fn error_statuses_are_errors() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/EWS/Exchange.asmx")
+            .with_status(401)
+            .with_header("WWW-Authenticate", "Basic realm=\"Exchange\"")
+            .with_body("<html><body>Unauthorized</body></html>")
+            .create_async()
+            .await;
+
+        let client = EwsClient::new(
+            &format!("{}/EWS/Exchange.asmx", server.url()),
+            "user",
+            "pass",
+        )
+        .unwrap();
+        let result = client.send(BaseShape::IdOnly).await;
+
+        mock.assert_async().await;
+        assert!(
+            matches!(
+                result,
+                Err(EwsError::Transport(HttpError::Status(
+                    StatusCode::UNAUTHORIZED
+                )))
+            ),
+            "401 should be reported as such"
+        );
+    }
+
+    #[tokio::test]
+    async fn soap_faults_are_read_from_error_responses() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/EWS/Exchange.asmx")
+            .with_status(500)
+            .with_body(
+                r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/">
+                    <s:Body>
+                        <s:Fault>
+                            <faultcode>s:Client</faultcode>
+                            <faultstring>The request failed schema validation.</faultstring>
+                        </s:Fault>
+                    </s:Body>
+                </s:Envelope>"#,
+            )
+            .create_async()
+            .await;
+
+        let client = EwsClient::new(
+            &format!("{}/EWS/Exchange.asmx", server.url()),
+            "user",
+            "pass",
+        )
+        .unwrap();
+        let result = client.send(BaseShape::IdOnly).await;
+
+        assert!(
+            matches!(result, Err(EwsError::Fault(_))),
+            "fault should be parsed"
+        );
+    }
+
+    #[tokio::test]
+    async fn transient_failures_are_retried() {
+        let mut server = mockito::Server::new_async().await;
+        let unavailable = server
+            .mock("POST", "/EWS/Exchange.asmx")
+            .with_status(503)
+            .with_header("Retry-After", "0")
+            .expect(2)
+            .create_async()
+            .await;
+        let ok = server
+            .mock("POST", "/EWS/Exchange.asmx")
+            .with_body("<soap:Envelope />")
+            .create_async()
+            .await;
+
+        let client = EwsClient::new(
+            &format!("{}/EWS/Exchange.asmx", server.url()),
+            "user",
+            "pass",
+        )
+        .unwrap()
+        .with_retry_policy(RetryPolicy {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_secs(1),
+        });
+        let response = client
+            .send_raw("<soap:Envelope />")
+            .await
+            .expect("request should succeed after retrying");
+
+        unavailable.assert_async().await;
+        ok.assert_async().await;
+        assert_eq!(response, "<soap:Envelope />");
+    }
+
+    #[tokio::test]
+    async fn retries_are_limited() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/EWS/Exchange.asmx")
+            .with_status(429)
+            .expect(2)
+            .create_async()
+            .await;
+
+        let client = EwsClient::new(
+            &format!("{}/EWS/Exchange.asmx", server.url()),
+            "user",
+            "pass",
+        )
+        .unwrap()
+        .with_retry_policy(RetryPolicy {
+            max_retries: 1,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_secs(1),
+        });
+        let result = client.send_raw("<soap:Envelope />").await;
+
+        mock.assert_async().await;
+        assert!(matches!(
+            result,
+            Err(HttpError::Status(StatusCode::TOO_MANY_REQUESTS))
+        ));
+    }
+
+    #[tokio::test]
+    async fn long_retry_after_delays_are_not_waited_for() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/EWS/Exchange.asmx")
+            .with_status(503)
+            .with_header("Retry-After", "86400")
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = EwsClient::new(
+            &format!("{}/EWS/Exchange.asmx", server.url()),
+            "user",
+            "pass",
+        )
+        .unwrap()
+        .with_retry_policy(RetryPolicy {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_secs(1),
+        });
+        let result = client.send_raw("<soap:Envelope />").await;
+
+        mock.assert_async().await;
+        assert!(matches!(
+            result,
+            Err(HttpError::Status(StatusCode::SERVICE_UNAVAILABLE))
+        ));
+    }
+
+    #[test]
+    fn retry_after_is_parsed() {
+        assert_eq!(parse_retry_after(" 120 "), Some(Duration::from_secs(120)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+
+        let later = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(3600));
+        let delay = parse_retry_after(&later).unwrap();
+        assert!(delay > Duration::from_secs(3590) && delay <= Duration::from_secs(3600));
+
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[tokio::test]
+    async fn unread_item_ids_are_listed() {
+        fn page(offset: u32, ids: &[&str], includes_last: bool) -> String {
+            let messages: String = ids
+                .iter()
+                .map(|id| {
+                    format!(r#"<t:Message><t:ItemId Id="{id}" ChangeKey="CQAAAB" /></t:Message>"#)
+                })
+                .collect();
+            format!(
+                r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/">
+                    <s:Body>
+                        <m:FindItemResponse
+                            xmlns:m="http://schemas.microsoft.com/exchange/services/2006/messages"
+                            xmlns:t="http://schemas.microsoft.com/exchange/services/2006/types">
+                            <m:ResponseMessages>
+                                <m:FindItemResponseMessage ResponseClass="Success">
+                                    <m:ResponseCode>NoError</m:ResponseCode>
+                                    <m:RootFolder IndexedPagingOffset="{next}" TotalItemsInView="3"
+                                        IncludesLastItemInRange="{includes_last}">
+                                        <t:Items>{messages}</t:Items>
+                                    </m:RootFolder>
+                                </m:FindItemResponseMessage>
+                            </m:ResponseMessages>
+                        </m:FindItemResponse>
+                    </s:Body>
+                </s:Envelope>"#,
+                next = offset + ids.len() as u32,
+            )
+        }
+
+        fn request_for_page(offset: u32) -> mockito::Matcher {
+            mockito::Matcher::AllOf(vec![
+                mockito::Matcher::Regex("<t:BaseShape>IdOnly</t:BaseShape>".to_string()),
+                mockito::Matcher::Regex(
+                    r#"<Restriction><t:IsEqualTo><t:FieldURI FieldURI="message:IsRead" />"#
+                        .to_string(),
+                ),
+                mockito::Matcher::Regex(format!(
+                    r#"<IndexedPageItemView MaxEntriesReturned="1000" Offset="{offset}" BasePoint="Beginning" />"#
+                )),
+            ])
+        }
+
+        let mut server = mockito::Server::new_async().await;
+        let first = server
+            .mock("POST", "/EWS/Exchange.asmx")
+            .match_body(request_for_page(0))
+            .with_body(page(0, &["AAMkAD", "AAMkAE"], false))
+            .expect(1)
+            .create_async()
+            .await;
+        let second = server
+            .mock("POST", "/EWS/Exchange.asmx")
+            .match_body(request_for_page(2))
+            .with_body(page(2, &["AAMkAF"], true))
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = EwsClient::new(
+            &format!("{}/EWS/Exchange.asmx", server.url()),
+            "user",
+            "pass",
+        )
+        .unwrap();
+        let ids = client
+            .unread_item_ids(FolderId::DistinguishedFolderId {
+                id: DistinguishedFolderIdName::Inbox,
+                change_key: None,
+                mailbox: None,
+            })
+            .await
+            .expect("listing unread items should succeed");
+
+        first.assert_async().await;
+        second.assert_async().await;
+        let ids: Vec<_> = ids.iter().map(ItemId::id).collect();
+        assert_eq!(ids, ["AAMkAD", "AAMkAE", "AAMkAF"]);
+    }
+
+    #[tokio::test]
+    async fn interceptors_run_around_requests() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        #[derive(Default)]
+        struct Counter {
+            sent: AtomicUsize,
+            received: AtomicUsize,
+        }
+
+        struct CountingInterceptor(Arc<Counter>);
+
+        impl RequestInterceptor for CountingInterceptor {
+            fn before(&self, request: &mut reqwest::Request) {
+                let count = self.0.sent.fetch_add(1, Ordering::SeqCst) + 1;
+                request
+                    .headers_mut()
+                    .insert("X-Request-Count", count.to_string().parse().unwrap());
+            }
+
+            fn after(&self, response: &reqwest::Response) {
+                assert!(response.status().is_success());
+                self.0.received.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let mut server = mockito::Server::new_async().await;
+        let first = server
+            .mock("POST", "/EWS/Exchange.asmx")
+            .match_header("x-request-count", "1")
+            .create_async()
+            .await;
+        let second = server
+            .mock("POST", "/EWS/Exchange.asmx")
+            .match_header("x-request-count", "2")
+            .create_async()
+            .await;
+
+        let counter = Arc::new(Counter::default());
+        let client = EwsClient::new(
+            &format!("{}/EWS/Exchange.asmx", server.url()),
+            "user",
+            "pass",
+        )
+        .unwrap()
+        .with_interceptor(CountingInterceptor(counter.clone()));
+
+        client.send_raw("<soap:Envelope />").await.unwrap();
+        client.send_raw("<soap:Envelope />").await.unwrap();
+
+        first.assert_async().await;
+        second.assert_async().await;
+        assert_eq!(counter.sent.load(Ordering::SeqCst), 2);
+        assert_eq!(counter.received.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn bearer_challenge_is_parsed() {
+        let header = concat!(
+            r#"Basic Realm="", Bearer client_id="00000002-0000-0ff1-ce00-000000000000", "#,
+            r#"trusted_issuers="00000001-0000-0000-c000-000000000000@*", "#,
+            r#"token_types="app_asserted_user_v1 service_asserted_app_v1", "#,
+            r#"authorization_uri="https://login.microsoftonline.com/common/oauth2/authorize", "#,
+            r#"resource="https://outlook.office365.com", "#,
+            r#"scope="https://outlook.office365.com/EWS.AccessAsUser.All", "#,
+            r#"error="invalid_token""#
+        );
+
+        let challenge = BearerChallenge::parse(header).expect("challenge should be found");
+        assert_eq!(
+            challenge.authorization_uri(),
+            Some("https://login.microsoftonline.com/common/oauth2/authorize")
+        );
+        assert_eq!(challenge.resource(), Some("https://outlook.office365.com"));
+        assert_eq!(
+            challenge.scope(),
+            Some("https://outlook.office365.com/EWS.AccessAsUser.All")
+        );
+
+        assert_eq!(BearerChallenge::parse(r#"Basic realm="Exchange""#), None);
+    }
 }
@@ -0,0 +1,374 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! The messages of an NTLMv2 handshake, as described in
+//! [MS-NLMP](https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-nlmp/b38c36ed-2804-4868-a9ff-8dd3182128e4).
+//!
+//! Only what's needed to authenticate is implemented; no session key is
+//! exchanged, so messages can't be signed or sealed.
+
+use hmac::{Hmac, Mac};
+use md4::{Digest, Md4};
+use md5::Md5;
+
+const SIGNATURE: &[u8; 8] = b"NTLMSSP\0";
+
+const NEGOTIATE_UNICODE: u32 = 0x0000_0001;
+const REQUEST_TARGET: u32 = 0x0000_0004;
+const NEGOTIATE_NTLM: u32 = 0x0000_0200;
+const NEGOTIATE_ALWAYS_SIGN: u32 = 0x0000_8000;
+const NEGOTIATE_EXTENDED_SESSIONSECURITY: u32 = 0x0008_0000;
+const NEGOTIATE_128: u32 = 0x2000_0000;
+const NEGOTIATE_56: u32 = 0x8000_0000;
+
+/// The flags sent in the negotiate message, which the flags of the
+/// authenticate message are limited to.
+const NEGOTIATE_FLAGS: u32 = NEGOTIATE_UNICODE
+    | REQUEST_TARGET
+    | NEGOTIATE_NTLM
+    | NEGOTIATE_ALWAYS_SIGN
+    | NEGOTIATE_EXTENDED_SESSIONSECURITY
+    | NEGOTIATE_128
+    | NEGOTIATE_56;
+
+/// The `AvId` of the `AV_PAIR` terminating a list.
+const AV_EOL: u16 = 0;
+
+/// The `AvId` of the `AV_PAIR` holding the server's time.
+const AV_TIMESTAMP: u16 = 7;
+
+/// The offset of the payload in an authenticate message, i.e. its size
+/// without a version or MIC.
+const AUTHENTICATE_PAYLOAD_OFFSET: usize = 64;
+
+/// The longest target info a challenge may carry. It's copied into the NT
+/// response, which must fit in a 16-bit length along with the 16-byte proof
+/// and the 32 bytes of the client challenge structure around it.
+const MAX_TARGET_INFO_LEN: usize = u16::MAX as usize - 48;
+
+/// The number of 100ns intervals between 1601-01-01, the epoch of Windows
+/// timestamps, and the Unix epoch.
+const WINDOWS_EPOCH_OFFSET: u64 = 116_444_736_000_000_000;
+
+/// Creates the negotiate message starting a handshake.
+pub(crate) fn negotiate_message() -> Vec<u8> {
+    let mut message = Vec::with_capacity(32);
+    message.extend_from_slice(SIGNATURE);
+    message.extend_from_slice(&1u32.to_le_bytes());
+    message.extend_from_slice(&NEGOTIATE_FLAGS.to_le_bytes());
+
+    // Empty domain and workstation fields.
+    message.extend_from_slice(&[0; 16]);
+
+    message
+}
+
+/// The parts of a server's challenge message needed to answer it.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Challenge {
+    flags: u32,
+    server_challenge: [u8; 8],
+    target_info: Vec<u8>,
+}
+
+impl Challenge {
+    /// Parses a challenge message, returning `None` if it's malformed or its
+    /// target info is too long to be answered.
+    pub(crate) fn parse(message: &[u8]) -> Option<Self> {
+        if message.get(..8)? != SIGNATURE || read_u32(message, 8)? != 2 {
+            return None;
+        }
+
+        let flags = read_u32(message, 20)?;
+        let server_challenge = message.get(24..32)?.try_into().ok()?;
+        let target_info = read_field(message, 40)?;
+        if target_info.len() > MAX_TARGET_INFO_LEN {
+            return None;
+        }
+        let target_info = target_info.to_vec();
+
+        Some(Self {
+            flags,
+            server_challenge,
+            target_info,
+        })
+    }
+
+    /// The server's time, if it sent one.
+    fn timestamp(&self) -> Option<[u8; 8]> {
+        let mut pairs = self.target_info.as_slice();
+        loop {
+            let id = u16::from_le_bytes(pairs.get(..2)?.try_into().ok()?);
+            let len = usize::from(u16::from_le_bytes(pairs.get(2..4)?.try_into().ok()?));
+            let value = pairs.get(4..4 + len)?;
+
+            match id {
+                AV_EOL => return None,
+                AV_TIMESTAMP => return value.try_into().ok(),
+                _ => pairs = &pairs[4 + len..],
+            }
+        }
+    }
+}
+
+/// Converts the current time to a Windows timestamp.
+pub(crate) fn current_timestamp() -> u64 {
+    let since_epoch = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+
+    let intervals = u64::try_from(since_epoch.as_nanos() / 100).unwrap_or(u64::MAX);
+    intervals.saturating_add(WINDOWS_EPOCH_OFFSET)
+}
+
+/// Creates the authenticate message answering a challenge with an NTLMv2
+/// response.
+///
+/// `timestamp` is only used if the server didn't send its own time in the
+/// challenge.
+pub(crate) fn authenticate_message(
+    challenge: &Challenge,
+    username: &str,
+    password: &str,
+    domain: &str,
+    client_challenge: [u8; 8],
+    timestamp: u64,
+) -> Vec<u8> {
+    let key = ntowfv2(password, username, domain);
+
+    let server_timestamp = challenge.timestamp();
+    let timestamp = server_timestamp.unwrap_or(timestamp.to_le_bytes());
+
+    // The `NTLMv2_CLIENT_CHALLENGE` structure (MS-NLMP 2.2.2.7), called
+    // `temp` by the spec.
+    let mut client_blob = vec![1, 1, 0, 0, 0, 0, 0, 0];
+    client_blob.extend_from_slice(&timestamp);
+    client_blob.extend_from_slice(&client_challenge);
+    client_blob.extend_from_slice(&[0; 4]);
+    client_blob.extend_from_slice(&challenge.target_info);
+    client_blob.extend_from_slice(&[0; 4]);
+
+    let mut nt_response = hmac_md5(&key, &[&challenge.server_challenge, &client_blob]).to_vec();
+    nt_response.extend_from_slice(&client_blob);
+
+    // Clients must not send an LMv2 response when the server sent its time.
+    let lm_response = if server_timestamp.is_some() {
+        vec![0; 24]
+    } else {
+        let mut response =
+            hmac_md5(&key, &[&challenge.server_challenge, &client_challenge]).to_vec();
+        response.extend_from_slice(&client_challenge);
+        response
+    };
+
+    let mut message = vec![0; AUTHENTICATE_PAYLOAD_OFFSET];
+    message[..8].copy_from_slice(SIGNATURE);
+    message[8..12].copy_from_slice(&3u32.to_le_bytes());
+    message[60..64].copy_from_slice(&(challenge.flags & NEGOTIATE_FLAGS).to_le_bytes());
+
+    write_field(&mut message, 28, &utf16le(domain));
+    write_field(&mut message, 36, &utf16le(username));
+    // No workstation name or session key.
+    write_field(&mut message, 44, &[]);
+    write_field(&mut message, 12, &lm_response);
+    write_field(&mut message, 20, &nt_response);
+    write_field(&mut message, 52, &[]);
+
+    message
+}
+
+/// Computes the NTLMv2 key for a user (`NTOWFv2` in MS-NLMP 3.3.2).
+fn ntowfv2(password: &str, username: &str, domain: &str) -> [u8; 16] {
+    let password_hash = Md4::digest(utf16le(password));
+    let identity = utf16le(&(username.to_uppercase() + domain));
+
+    hmac_md5(&password_hash, &[&identity])
+}
+
+/// Computes the HMAC-MD5 of the concatenation of `parts`.
+fn hmac_md5(key: &[u8], parts: &[&[u8]]) -> [u8; 16] {
+    let mut mac = Hmac::<Md5>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    for part in parts {
+        mac.update(part);
+    }
+
+    mac.finalize().into_bytes().into()
+}
+
+/// Encodes a string as UTF-16LE, the encoding of strings in NTLM messages
+/// once Unicode is negotiated.
+fn utf16le(value: &str) -> Vec<u8> {
+    value.encode_utf16().flat_map(u16::to_le_bytes).collect()
+}
+
+fn read_u32(message: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        message.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+/// Reads the value of a field described by the length and offset at
+/// `header_offset`.
+fn read_field(message: &[u8], header_offset: usize) -> Option<&[u8]> {
+    let len = u16::from_le_bytes(
+        message
+            .get(header_offset..header_offset + 2)?
+            .try_into()
+            .ok()?,
+    );
+    let offset = read_u32(message, header_offset + 4)?;
+
+    let start = usize::try_from(offset).ok()?;
+    message.get(start..start + usize::from(len))
+}
+
+/// Appends a value to the payload of a message, and describes it in the
+/// field at `header_offset`.
+fn write_field(message: &mut Vec<u8>, header_offset: usize, value: &[u8]) {
+    let len = u16::try_from(value.len()).expect("NTLM fields should fit in 64KiB");
+    let offset = u32::try_from(message.len()).expect("NTLM messages should fit in 4GiB");
+
+    message[header_offset..header_offset + 2].copy_from_slice(&len.to_le_bytes());
+    message[header_offset + 2..header_offset + 4].copy_from_slice(&len.to_le_bytes());
+    message[header_offset + 4..header_offset + 8].copy_from_slice(&offset.to_le_bytes());
+    message.extend_from_slice(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The values used by the NTLMv2 examples in MS-NLMP 4.2.4.
+    const USER: &str = "User";
+    const DOMAIN: &str = "Domain";
+    const PASSWORD: &str = "Password";
+    const SERVER_CHALLENGE: [u8; 8] = [0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef];
+    const CLIENT_CHALLENGE: [u8; 8] = [0xaa; 8];
+
+    /// The `AV_PAIR`s of the examples' challenge: the NetBIOS domain name
+    /// "Domain" and computer name "Server".
+    fn target_info() -> Vec<u8> {
+        let mut target_info = vec![0x02, 0x00, 0x0c, 0x00];
+        target_info.extend(utf16le("Domain"));
+        target_info.extend([0x01, 0x00, 0x0c, 0x00]);
+        target_info.extend(utf16le("Server"));
+        target_info.extend([0x00, 0x00, 0x00, 0x00]);
+        target_info
+    }
+
+    /// Builds a challenge message with the given target info.
+    fn challenge_message(target_info: &[u8]) -> Vec<u8> {
+        let mut message = vec![0; 48];
+        message[..8].copy_from_slice(SIGNATURE);
+        message[8..12].copy_from_slice(&2u32.to_le_bytes());
+        message[20..24].copy_from_slice(&0xe28a_8233u32.to_le_bytes());
+        message[24..32].copy_from_slice(&SERVER_CHALLENGE);
+        write_field(&mut message, 40, target_info);
+        message
+    }
+
+    #[test]
+    fn negotiate_message_is_encoded() {
+        let message = negotiate_message();
+
+        assert_eq!(&message[..8], b"NTLMSSP\0");
+        assert_eq!(read_u32(&message, 8), Some(1));
+        assert_eq!(read_u32(&message, 12), Some(NEGOTIATE_FLAGS));
+        assert_eq!(message.len(), 32);
+    }
+
+    #[test]
+    fn challenge_message_is_parsed() {
+        let challenge = Challenge::parse(&challenge_message(&target_info()))
+            .expect("challenge should be parsed");
+
+        assert_eq!(challenge.flags, 0xe28a_8233);
+        assert_eq!(challenge.server_challenge, SERVER_CHALLENGE);
+        assert_eq!(challenge.target_info, target_info());
+        assert_eq!(challenge.timestamp(), None);
+
+        // A negotiate message, and a challenge cut short.
+        assert_eq!(Challenge::parse(&negotiate_message()), None);
+        assert_eq!(
+            Challenge::parse(&challenge_message(&target_info())[..40]),
+            None
+        );
+    }
+
+    #[test]
+    fn oversized_target_info_is_rejected() {
+        let target_info = vec![0; MAX_TARGET_INFO_LEN];
+        let challenge =
+            Challenge::parse(&challenge_message(&target_info)).expect("challenge should be parsed");
+        let message = authenticate_message(&challenge, USER, PASSWORD, DOMAIN, CLIENT_CHALLENGE, 0);
+        assert_eq!(
+            read_field(&message, 20).unwrap().len(),
+            usize::from(u16::MAX)
+        );
+
+        let target_info = vec![0; MAX_TARGET_INFO_LEN + 1];
+        assert_eq!(Challenge::parse(&challenge_message(&target_info)), None);
+    }
+
+    #[test]
+    fn key_matches_spec() {
+        assert_eq!(
+            ntowfv2(PASSWORD, USER, DOMAIN),
+            [
+                0x0c, 0x86, 0x8a, 0x40, 0x3b, 0xfd, 0x7a, 0x93, 0xa3, 0x00, 0x1e, 0xf2, 0x2e, 0xf0,
+                0x2e, 0x3f
+            ]
+        );
+    }
+
+    #[test]
+    fn authenticate_message_matches_spec() {
+        let challenge = Challenge::parse(&challenge_message(&target_info())).unwrap();
+        let message = authenticate_message(&challenge, USER, PASSWORD, DOMAIN, CLIENT_CHALLENGE, 0);
+
+        assert_eq!(&message[..8], b"NTLMSSP\0");
+        assert_eq!(read_u32(&message, 8), Some(3));
+        assert_eq!(read_field(&message, 28), Some(utf16le(DOMAIN).as_slice()));
+        assert_eq!(read_field(&message, 36), Some(utf16le(USER).as_slice()));
+        assert_eq!(read_field(&message, 44), Some([].as_slice()));
+
+        let lm_response = read_field(&message, 12).unwrap();
+        assert_eq!(
+            lm_response,
+            [
+                0x86, 0xc3, 0x50, 0x97, 0xac, 0x9c, 0xec, 0x10, 0x25, 0x54, 0x76, 0x4a, 0x57, 0xcc,
+                0xcc, 0x19, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa
+            ]
+        );
+
+        let nt_response = read_field(&message, 20).unwrap();
+        assert_eq!(
+            nt_response[..16],
+            [
+                0x68, 0xcd, 0x0a, 0xb8, 0x51, 0xe5, 0x1c, 0x96, 0xaa, 0xbc, 0x92, 0x7b, 0xeb, 0xef,
+                0x6a, 0x1c
+            ]
+        );
+        assert_eq!(nt_response[16..24], [1, 1, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(nt_response[32..40], CLIENT_CHALLENGE);
+        assert_eq!(nt_response[44..44 + target_info().len()], target_info());
+    }
+
+    #[test]
+    fn server_timestamp_is_used() {
+        let server_time = 0x01d9_0000_1234_5678u64.to_le_bytes();
+
+        let mut target_info = vec![0x07, 0x00, 0x08, 0x00];
+        target_info.extend(server_time);
+        target_info.extend(self::target_info());
+
+        let challenge = Challenge::parse(&challenge_message(&target_info)).unwrap();
+        assert_eq!(challenge.timestamp(), Some(server_time));
+
+        let message =
+            authenticate_message(&challenge, USER, PASSWORD, DOMAIN, CLIENT_CHALLENGE, 42);
+        assert_eq!(read_field(&message, 12), Some([0; 24].as_slice()));
+        assert_eq!(read_field(&message, 20).unwrap()[24..32], server_time);
+    }
+}
@@ -2,9 +2,13 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use base64::prelude::*;
+use email_address::EmailAddress;
 use serde::{Deserialize, Serialize};
 use xml::writer::XmlEvent;
 
+use crate::xml::write_text_element;
+
 pub const MESSAGES_NS_URI: &str = "http://schemas.microsoft.com/exchange/services/2006/messages";
 pub const SOAP_NS_URI: &str = "http://schemas.xmlsoap.org/soap/envelope/";
 pub const TYPES_NS_URI: &str = "http://schemas.microsoft.com/exchange/services/2006/types";
@@ -12,6 +16,39 @@ pub const TYPES_NS_URI: &str = "http://schemas.microsoft.com/exchange/services/2
 pub trait EwsWrite<W> {
     /// Writes the struct as XML using the provided writer.
     fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error>;
+
+    /// An estimate of the number of bytes the struct takes up once written,
+    /// used to preallocate buffers. It doesn't need to be exact.
+    fn size_hint(&self) -> usize {
+        512
+    }
+}
+
+/// An absent value is written as nothing at all, so that optional elements
+/// can be written without checking for them first.
+impl<W, T: EwsWrite<W>> EwsWrite<W> for Option<T> {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        match self {
+            Some(value) => value.write(writer),
+            None => Ok(()),
+        }
+    }
+
+    fn size_hint(&self) -> usize {
+        self.as_ref().map_or(0, EwsWrite::size_hint)
+    }
+}
+
+/// A sequence of values is written as each of its elements in turn, with no
+/// wrapping element; callers write any wrapper the schema calls for.
+impl<W, T: EwsWrite<W>> EwsWrite<W> for Vec<T> {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        self.iter().try_for_each(|value| value.write(writer))
+    }
+
+    fn size_hint(&self) -> usize {
+        self.iter().map(EwsWrite::size_hint).sum()
+    }
 }
 
 #[derive(Deserialize)]
@@ -20,25 +57,321 @@ pub struct SoapEnvelope {
     pub body: SoapBody,
 }
 
+impl SoapEnvelope {
+    /// Returns the contents of the body, or an error if the server responded
+    /// with a SOAP fault.
+    pub fn into_result(self) -> Result<Response, Fault> {
+        match self.body.contents {
+            Response::Fault(fault) => Err(fault),
+            response => Ok(response),
+        }
+    }
+}
+
 #[derive(Deserialize)]
 pub struct SoapBody {
     #[serde(rename = "$value")]
     pub contents: Response,
 }
 
+/// The contents of a response envelope's body.
+///
+/// The variant is picked by the name of the body's element, without its
+/// namespace prefix, so each variant is named after the response element of
+/// its operation, e.g. `GetItemResponse`. Supporting a new operation's
+/// response takes a variant here, plus one in [`ResponseMessageContents`] for
+/// its response messages.
 #[derive(Deserialize)]
 pub enum Response {
     // Placeholder to demonstrate matching.
     ExportItemsResponse(String),
 
+    GetAttachmentResponse(GetAttachmentResponse),
+
     FindItemResponse(FindItemResponse),
+
+    FindConversationResponse(FindConversationResponse),
+
+    FindFolderResponse(FindFolderResponse),
+
+    GetConversationItemsResponse(GetConversationItemsResponse),
+
+    GetEventsResponse(GetEventsResponse),
+
+    GetItemResponse(GetItemResponse),
+
+    CreateItemResponse(CreateItemResponse),
+
+    UpdateItemResponse(UpdateItemResponse),
+
+    DeleteItemResponse(DeleteItemResponse),
+
+    MoveItemResponse(MoveItemResponse),
+
+    CopyItemResponse(CopyItemResponse),
+
+    SyncFolderItemsResponse(SyncFolderItemsResponse),
+
+    SyncFolderHierarchyResponse(SyncFolderHierarchyResponse),
+
+    GetFolderResponse(GetFolderResponse),
+
+    /// An error preventing the server from processing the request at all,
+    /// e.g. a malformed request.
+    Fault(Fault),
 }
 
-#[derive(Deserialize)]
+/// A SOAP fault, sent by the server in place of a response when it couldn't
+/// process a request.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/exchange-web-services/ews-error-handling>.
+#[derive(Debug, Deserialize)]
+pub struct Fault {
+    #[serde(rename = "faultcode")]
+    fault_code: String,
+
+    #[serde(rename = "faultstring")]
+    fault_string: String,
+
+    detail: Option<FaultDetail>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct FaultDetail {
+    response_code: Option<ResponseCode>,
+    message: Option<String>,
+}
+
+impl Fault {
+    /// The SOAP fault code, e.g. `a:ErrorSchemaValidation`.
+    pub fn fault_code(&self) -> &str {
+        &self.fault_code
+    }
+
+    /// A human-readable description of the fault.
+    pub fn fault_string(&self) -> &str {
+        &self.fault_string
+    }
+
+    /// The EWS response code describing the fault, if the server sent one.
+    pub fn response_code(&self) -> Option<&ResponseCode> {
+        self.detail.as_ref()?.response_code.as_ref()
+    }
+
+    /// A more detailed description of the fault, if the server sent one.
+    pub fn message(&self) -> Option<&str> {
+        self.detail.as_ref()?.message.as_deref()
+    }
+}
+
+impl std::fmt::Display for Fault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SOAP fault {}: {}", self.fault_code, self.fault_string)
+    }
+}
+
+impl std::error::Error for Fault {}
+
+/// A header block to include in the SOAP envelope of a request.
+#[derive(Clone, Debug)]
+pub enum SoapHeader {
+    /// Sends the request on behalf of another user, which the authenticated
+    /// account must be allowed to impersonate.
+    ///
+    /// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/exchangeimpersonation>.
+    ExchangeImpersonation { primary_smtp_address: String },
+
+    /// The version of the EWS schema the request is written against, which
+    /// determines the elements the server accepts and sends back.
+    ///
+    /// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/requestserverversion>.
+    RequestServerVersion { version: ExchangeVersion },
+}
+
+impl SoapHeader {
+    fn name(&self) -> &'static str {
+        match self {
+            SoapHeader::ExchangeImpersonation { .. } => "t:ExchangeImpersonation",
+            SoapHeader::RequestServerVersion { .. } => "t:RequestServerVersion",
+        }
+    }
+}
+
+/// A version of the EWS schema.
+///
+/// Defaults to Exchange 2013 SP1, which is supported by Office365 and all
+/// on-premises servers still receiving updates.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExchangeVersion {
+    Exchange2007Sp1,
+    Exchange2010,
+    Exchange2010Sp1,
+    Exchange2010Sp2,
+    Exchange2013,
+    #[default]
+    Exchange2013Sp1,
+    Exchange2016,
+}
+
+impl From<ExchangeVersion> for &str {
+    fn from(value: ExchangeVersion) -> Self {
+        match value {
+            ExchangeVersion::Exchange2007Sp1 => "Exchange2007_SP1",
+            ExchangeVersion::Exchange2010 => "Exchange2010",
+            ExchangeVersion::Exchange2010Sp1 => "Exchange2010_SP1",
+            ExchangeVersion::Exchange2010Sp2 => "Exchange2010_SP2",
+            ExchangeVersion::Exchange2013 => "Exchange2013",
+            ExchangeVersion::Exchange2013Sp1 => "Exchange2013_SP1",
+            ExchangeVersion::Exchange2016 => "Exchange2016",
+        }
+    }
+}
+
+/// A [`SoapHeader`] along with how the server should treat it.
+#[derive(Clone, Debug)]
+pub struct RequestHeader {
+    header: SoapHeader,
+    must_understand: bool,
+}
+
+impl RequestHeader {
+    pub fn new(header: SoapHeader) -> Self {
+        Self {
+            header,
+            must_understand: false,
+        }
+    }
+
+    /// Sets whether the server must reject the request if it can't process
+    /// the header, via the `soap:mustUnderstand` attribute. The attribute is
+    /// omitted unless this is set.
+    pub fn must_understand(mut self, must_understand: bool) -> Self {
+        self.must_understand = must_understand;
+        self
+    }
+}
+
+impl<W: std::io::Write> EwsWrite<W> for RequestHeader {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        let mut start = XmlEvent::start_element(self.header.name());
+        if self.must_understand {
+            start = start.attr("soap:mustUnderstand", "1");
+        }
+        if let SoapHeader::RequestServerVersion { version } = &self.header {
+            start = start.attr("Version", (*version).into());
+        }
+        writer.write(start)?;
+
+        match &self.header {
+            SoapHeader::ExchangeImpersonation {
+                primary_smtp_address,
+            } => {
+                writer.write(XmlEvent::start_element("t:ConnectingSID"))?;
+                write_text_element(writer, "t:PrimarySmtpAddress", primary_smtp_address)?;
+                writer.write(XmlEvent::end_element())?;
+            }
+
+            // The version is written as an attribute.
+            SoapHeader::RequestServerVersion { .. } => (),
+        }
+
+        writer.write(XmlEvent::end_element())
+    }
+}
+
+/// A mailbox, e.g. the sender or a recipient of a message.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/mailbox>.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Mailbox {
+    /// The display name of the mailbox's owner.
+    name: Option<String>,
+    email_address: String,
+
+    /// The kind of address in `email_address`, e.g. `SMTP`, or `EX` for an
+    /// Exchange legacy DN.
+    routing_type: Option<String>,
+}
+
+impl Mailbox {
+    /// Creates a mailbox for an SMTP address.
+    ///
+    /// The address is kept as a string once created, as the server can also
+    /// send other kinds of addresses, e.g. Exchange legacy DNs.
+    pub fn new(email_address: &EmailAddress, name: Option<&str>) -> Self {
+        Self {
+            name: name.map(str::to_string),
+            email_address: email_address.to_string(),
+            routing_type: None,
+        }
+    }
+
+    pub fn with_routing_type(mut self, routing_type: &str) -> Self {
+        self.routing_type = Some(routing_type.to_string());
+        self
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn email_address(&self) -> &str {
+        &self.email_address
+    }
+
+    pub fn routing_type(&self) -> Option<&str> {
+        self.routing_type.as_deref()
+    }
+}
+
+impl<W: std::io::Write> EwsWrite<W> for Mailbox {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        writer.write(XmlEvent::start_element("t:Mailbox"))?;
+        if let Some(name) = &self.name {
+            write_text_element(writer, "t:Name", name)?;
+        }
+        write_text_element(writer, "t:EmailAddress", &self.email_address)?;
+        if let Some(routing_type) = &self.routing_type {
+            write_text_element(writer, "t:RoutingType", routing_type)?;
+        }
+        writer.write(XmlEvent::end_element())
+    }
+}
+
+/// A list of mailboxes, e.g. the recipients of a message.
+///
+/// The same type backs each of `ToRecipients`, `CcRecipients` and
+/// `BccRecipients`, so the name of the element is provided when writing.
+/// An empty list is written as an empty element.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ArrayOfRecipients {
+    #[serde(rename = "Mailbox", default)]
+    mailboxes: Vec<Mailbox>,
+}
+
+impl ArrayOfRecipients {
+    fn write<W: std::io::Write>(
+        &self,
+        writer: &mut xml::EventWriter<W>,
+        name: &str,
+    ) -> Result<(), xml::writer::Error> {
+        writer.write(XmlEvent::start_element(name))?;
+        self.mailboxes.write(writer)?;
+        writer.write(XmlEvent::end_element())
+    }
+}
+
+/// A single mailbox, e.g. the sender of a message.
+#[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "PascalCase")]
-pub struct Mailbox;
+pub struct SingleRecipient {
+    mailbox: Mailbox,
+}
 
 /// An identifier for a remote folder.
+#[derive(Clone, Debug)]
 pub enum FolderId {
     /// An identifier for an arbitrary folder.
     ///
@@ -53,9 +386,7 @@ pub enum FolderId {
     ///
     /// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/distinguishedfolderid>.
     DistinguishedFolderId {
-        // This should probably be an enum, but this is a proof of concept and
-        // I'm not writing all of those out right now.
-        id: String,
+        id: DistinguishedFolderIdName,
         change_key: Option<String>,
         mailbox: Option<Mailbox>,
     },
@@ -64,9 +395,19 @@ pub enum FolderId {
 impl<W: std::io::Write> EwsWrite<W> for FolderId {
     fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
         match self {
-            FolderId::FolderId { .. } => todo!(),
+            FolderId::FolderId { id, change_key } => {
+                let mut builder = XmlEvent::start_element("t:FolderId").attr("Id", id);
+
+                if let Some(change_key) = change_key {
+                    builder = builder.attr("ChangeKey", change_key);
+                }
+
+                writer.write(builder)?;
+                writer.write(XmlEvent::end_element())
+            }
             FolderId::DistinguishedFolderId { id, change_key, .. } => {
-                let mut builder = XmlEvent::start_element("t:DistinguishedFolderId").attr("Id", id);
+                let mut builder =
+                    XmlEvent::start_element("t:DistinguishedFolderId").attr("Id", id.as_str());
 
                 if let Some(change_key) = change_key {
                     builder = builder.attr("ChangeKey", change_key);
@@ -79,6 +420,70 @@ impl<W: std::io::Write> EwsWrite<W> for FolderId {
     }
 }
 
+/// The name of a well-known folder, which can be referenced without knowing
+/// its identifier.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/distinguishedfolderid>.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DistinguishedFolderIdName {
+    Inbox,
+    SentItems,
+    DeletedItems,
+    Drafts,
+    JunkEmail,
+    Outbox,
+    Calendar,
+    Contacts,
+
+    /// The root of the mailbox, including folders not visible to the user.
+    Root,
+
+    /// The root of the folders visible to the user, i.e. the parent of the
+    /// inbox.
+    MsgFolderRoot,
+
+    /// Any other name, as it's sent to the server.
+    Other(String),
+}
+
+impl DistinguishedFolderIdName {
+    /// The name as it's sent to the server.
+    pub fn as_str(&self) -> &str {
+        match self {
+            DistinguishedFolderIdName::Inbox => "inbox",
+            DistinguishedFolderIdName::SentItems => "sentitems",
+            DistinguishedFolderIdName::DeletedItems => "deleteditems",
+            DistinguishedFolderIdName::Drafts => "drafts",
+            DistinguishedFolderIdName::JunkEmail => "junkemail",
+            DistinguishedFolderIdName::Outbox => "outbox",
+            DistinguishedFolderIdName::Calendar => "calendar",
+            DistinguishedFolderIdName::Contacts => "contacts",
+            DistinguishedFolderIdName::Root => "root",
+            DistinguishedFolderIdName::MsgFolderRoot => "msgfolderroot",
+            DistinguishedFolderIdName::Other(name) => name,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for FolderId {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        // The server only ever sends concrete identifiers, even for
+        // distinguished folders.
+        #[derive(Deserialize)]
+        #[serde(rename_all = "PascalCase")]
+        struct ConcreteFolderId {
+            id: String,
+            change_key: Option<String>,
+        }
+
+        let ConcreteFolderId { id, change_key } = ConcreteFolderId::deserialize(deserializer)?;
+        Ok(FolderId::FolderId { id, change_key })
+    }
+}
+
 /// The base set of properties to be returned in response to our request, which
 /// can be modified by the parent.
 ///
@@ -91,16 +496,13 @@ pub enum BaseShape {
 
 impl<W: std::io::Write> EwsWrite<W> for BaseShape {
     fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
-        writer.write(XmlEvent::start_element("t:BaseShape"))?;
-
         let value = match self {
             BaseShape::IdOnly => "IdOnly",
             BaseShape::Default => "Default",
             BaseShape::AllProperties => "AllProperties",
         };
 
-        writer.write(XmlEvent::characters(value))?;
-        writer.write(XmlEvent::end_element())
+        write_text_element(writer, "t:BaseShape", value)
     }
 }
 
@@ -121,8 +523,241 @@ impl<W: std::io::Write> EwsWrite<W> for FolderShape {
     }
 }
 
+/// An identifier for a property of an item or folder.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/fielduri>.
+#[derive(Clone, Copy)]
+pub enum FieldUri {
+    ItemConversationId,
+    FolderEffectiveRights,
+    ItemAttachments,
+    ItemBody,
+    ItemDateTimeReceived,
+    ItemDateTimeSent,
+    ItemEffectiveRights,
+    ItemFlag,
+    ItemHasAttachments,
+    ItemImportance,
+    ItemInternetMessageHeaders,
+    ItemSize,
+    ItemSubject,
+    MessageFrom,
+    MessageIsRead,
+}
+
+impl From<FieldUri> for &str {
+    fn from(value: FieldUri) -> Self {
+        match value {
+            FieldUri::ItemConversationId => "item:ConversationId",
+            FieldUri::FolderEffectiveRights => "folder:EffectiveRights",
+            FieldUri::ItemAttachments => "item:Attachments",
+            FieldUri::ItemBody => "item:Body",
+            FieldUri::ItemDateTimeReceived => "item:DateTimeReceived",
+            FieldUri::ItemDateTimeSent => "item:DateTimeSent",
+            FieldUri::ItemEffectiveRights => "item:EffectiveRights",
+            FieldUri::ItemFlag => "item:Flag",
+            FieldUri::ItemHasAttachments => "item:HasAttachments",
+            FieldUri::ItemImportance => "item:Importance",
+            FieldUri::ItemInternetMessageHeaders => "item:InternetMessageHeaders",
+            FieldUri::ItemSize => "item:Size",
+            FieldUri::ItemSubject => "item:Subject",
+            FieldUri::MessageFrom => "message:From",
+            FieldUri::MessageIsRead => "message:IsRead",
+        }
+    }
+}
+
+impl<W: std::io::Write> EwsWrite<W> for FieldUri {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        writer.write(XmlEvent::start_element("t:FieldURI").attr("FieldURI", (*self).into()))?;
+        writer.write(XmlEvent::end_element())
+    }
+}
+
+/// A filter on the items or folders to include in the response.
+///
+/// Restrictions can be combined with [`Restriction::And`],
+/// [`Restriction::Or`] and [`Restriction::Not`] to build more complex
+/// filters.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/restriction>.
+pub enum Restriction {
+    /// Matches if the property is equal to the constant value.
+    IsEqualTo { field: FieldUri, value: String },
+
+    /// Matches if the property contains the constant value, ignoring case.
+    Contains { field: FieldUri, value: String },
+
+    /// Matches if all of the restrictions match.
+    And(Vec<Restriction>),
+
+    /// Matches if any of the restrictions matches.
+    Or(Vec<Restriction>),
+
+    /// Matches if the restriction doesn't match.
+    Not(Box<Restriction>),
+}
+
+impl Restriction {
+    /// Writes the restriction as a search expression, i.e. without the
+    /// `Restriction` element which only wraps the outermost one.
+    fn write_expression<W: std::io::Write>(
+        &self,
+        writer: &mut xml::EventWriter<W>,
+    ) -> Result<(), xml::writer::Error> {
+        match self {
+            Restriction::IsEqualTo { field, value } => {
+                writer.write(XmlEvent::start_element("t:IsEqualTo"))?;
+                field.write(writer)?;
+                writer.write(XmlEvent::start_element("t:FieldURIOrConstant"))?;
+                writer.write(XmlEvent::start_element("t:Constant").attr("Value", value))?;
+                writer.write(XmlEvent::end_element())?;
+                writer.write(XmlEvent::end_element())?;
+            }
+            Restriction::Contains { field, value } => {
+                writer.write(
+                    XmlEvent::start_element("t:Contains")
+                        .attr("ContainmentMode", "Substring")
+                        .attr("ContainmentComparison", "IgnoreCase"),
+                )?;
+                field.write(writer)?;
+                writer.write(XmlEvent::start_element("t:Constant").attr("Value", value))?;
+                writer.write(XmlEvent::end_element())?;
+            }
+            Restriction::And(restrictions) | Restriction::Or(restrictions) => {
+                let name = match self {
+                    Restriction::And(_) => "t:And",
+                    _ => "t:Or",
+                };
+
+                writer.write(XmlEvent::start_element(name))?;
+                for restriction in restrictions {
+                    restriction.write_expression(writer)?;
+                }
+            }
+            Restriction::Not(restriction) => {
+                writer.write(XmlEvent::start_element("t:Not"))?;
+                restriction.write_expression(writer)?;
+            }
+        }
+
+        writer.write(XmlEvent::end_element())
+    }
+}
+
+impl<W: std::io::Write> EwsWrite<W> for Restriction {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        writer.write(XmlEvent::start_element("Restriction"))?;
+        self.write_expression(writer)?;
+        writer.write(XmlEvent::end_element())
+    }
+}
+
+/// The direction in which to order results.
+#[derive(Clone, Copy)]
+pub enum SortDirection {
+    Ascending,
+    Descending,
+}
+
+impl From<SortDirection> for &str {
+    fn from(value: SortDirection) -> Self {
+        match value {
+            SortDirection::Ascending => "Ascending",
+            SortDirection::Descending => "Descending",
+        }
+    }
+}
+
+/// The order in which to return results, as a list of properties to sort by
+/// in order of precedence.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/sortorder>.
+pub struct SortOrder(pub Vec<FieldOrder>);
+
+impl<W: std::io::Write> EwsWrite<W> for SortOrder {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        writer.write(XmlEvent::start_element("SortOrder"))?;
+        self.0.write(writer)?;
+        writer.write(XmlEvent::end_element())
+    }
+}
+
+/// A property to sort results by.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/fieldorder>.
+pub struct FieldOrder {
+    pub field: FieldUri,
+    pub order: SortDirection,
+}
+
+impl<W: std::io::Write> EwsWrite<W> for FieldOrder {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        writer.write(XmlEvent::start_element("t:FieldOrder").attr("Order", self.order.into()))?;
+        self.field.write(writer)?;
+        writer.write(XmlEvent::end_element())
+    }
+}
+
+/// The function used to reduce the items in a group to the single value by
+/// which groups are ordered.
+#[derive(Clone, Copy)]
+pub enum Aggregate {
+    Minimum,
+    Maximum,
+}
+
+impl From<Aggregate> for &str {
+    fn from(value: Aggregate) -> Self {
+        match value {
+            Aggregate::Minimum => "Minimum",
+            Aggregate::Maximum => "Maximum",
+        }
+    }
+}
+
+/// A grouping of the items in a [`FindItem`] response by the value of a
+/// property, e.g. by sender.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/groupby>.
+pub struct GroupBy {
+    /// The order of the groups relative to each other.
+    pub order: SortDirection,
+
+    /// The property whose value items are grouped by.
+    pub field: FieldUri,
+
+    /// The property used to order groups, and how to aggregate it over the
+    /// items of each group.
+    pub aggregate: Aggregate,
+    pub aggregate_on: FieldUri,
+}
+
+impl<W: std::io::Write> EwsWrite<W> for GroupBy {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        writer.write(XmlEvent::start_element("GroupBy").attr("Order", self.order.into()))?;
+        self.field.write(writer)?;
+
+        writer.write(
+            XmlEvent::start_element("t:AggregateOn").attr("Aggregate", self.aggregate.into()),
+        )?;
+        self.aggregate_on.write(writer)?;
+        writer.write(XmlEvent::end_element())?;
+
+        writer.write(XmlEvent::end_element())
+    }
+}
+
+/// The item properties to include in the response.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/itemshape>.
 pub struct ItemShape {
     pub base_shape: BaseShape,
+
+    /// Properties to include on top of those selected by the base shape.
+    ///
+    /// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/additionalproperties>.
+    pub additional_properties: Option<Vec<FieldUri>>,
 }
 
 impl<W: std::io::Write> EwsWrite<W> for ItemShape {
@@ -131,6 +766,12 @@ impl<W: std::io::Write> EwsWrite<W> for ItemShape {
 
         self.base_shape.write(writer)?;
 
+        if let Some(additional_properties) = &self.additional_properties {
+            writer.write(XmlEvent::start_element("t:AdditionalProperties"))?;
+            additional_properties.write(writer)?;
+            writer.write(XmlEvent::end_element())?;
+        }
+
         writer.write(XmlEvent::end_element())
     }
 }
@@ -138,6 +779,11 @@ impl<W: std::io::Write> EwsWrite<W> for ItemShape {
 #[derive(Clone, Copy)]
 pub enum Traversal {
     Shallow,
+
+    /// Searches all descendants of the parent folders. Only valid when
+    /// finding folders.
+    Deep,
+
     SoftDeleted,
     Associated,
 }
@@ -146,14 +792,14 @@ impl From<Traversal> for &str {
     fn from(value: Traversal) -> Self {
         match value {
             Traversal::Shallow => "Shallow",
+            Traversal::Deep => "Deep",
             Traversal::SoftDeleted => "SoftDeleted",
             Traversal::Associated => "Associated",
         }
     }
 }
 
-/// A request to list any items matching provided filters. I didn't add support
-/// for filters.
+/// A request to list any items matching provided filters.
 ///
 /// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/finditem>.
 pub struct FindItem {
@@ -163,9 +809,21 @@ pub struct FindItem {
     /// The desired properties to include in the response.
     item_shape: ItemShape,
 
-    /// Identifiers for the folders in which to locate items.
-    parent_folder_ids: Vec<FolderId>,
-}
+    /// An optional view selecting a page of the results.
+    view: Option<IndexedPageItemView>,
+
+    /// An optional grouping of the items in the response.
+    group_by: Option<GroupBy>,
+
+    /// An optional filter on the items to include in the response.
+    restriction: Option<Restriction>,
+
+    /// An optional order in which to return the items.
+    sort_order: Option<SortOrder>,
+
+    /// Identifiers for the folders in which to locate items.
+    parent_folder_ids: Vec<FolderId>,
+}
 
 impl FindItem {
     /// Creates a new FindItem request object.
@@ -177,9 +835,39 @@ impl FindItem {
         Self {
             traversal,
             item_shape,
+            view: None,
+            group_by: None,
+            restriction: None,
+            sort_order: None,
             parent_folder_ids,
         }
     }
+
+    /// Only includes a page of the items in the response.
+    pub fn with_view(mut self, view: IndexedPageItemView) -> Self {
+        self.view = Some(view);
+        self
+    }
+
+    /// Groups the items in the response, which are then available from
+    /// [`CheckedResponse<FindItemResponse>::groups`].
+    pub fn with_group_by(mut self, group_by: GroupBy) -> Self {
+        self.group_by = Some(group_by);
+        self
+    }
+
+    /// Only includes the items matching the given restriction in the
+    /// response.
+    pub fn with_restriction(mut self, restriction: Restriction) -> Self {
+        self.restriction = Some(restriction);
+        self
+    }
+
+    /// Has the server sort the items in the response.
+    pub fn with_sort_order(mut self, sort_order: SortOrder) -> Self {
+        self.sort_order = Some(sort_order);
+        self
+    }
 }
 
 impl<W: std::io::Write> EwsWrite<W> for FindItem {
@@ -193,17 +881,29 @@ impl<W: std::io::Write> EwsWrite<W> for FindItem {
 
         self.item_shape.write(writer)?;
 
+        self.view.write(writer)?;
+
+        self.group_by.write(writer)?;
+
+        self.restriction.write(writer)?;
+
+        self.sort_order.write(writer)?;
+
         writer.write(XmlEvent::start_element("ParentFolderIds"))?;
-        for id in self.parent_folder_ids.iter() {
-            id.write(writer)?;
-        }
+        self.parent_folder_ids.write(writer)?;
         writer.write(XmlEvent::end_element())?;
 
         writer.write(XmlEvent::end_element())
     }
+
+    fn size_hint(&self) -> usize {
+        // Roughly the size of the shape, plus a distinguished folder ID per
+        // parent folder.
+        256 + 64 * self.parent_folder_ids.len()
+    }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct ItemId {
     id: String,
@@ -211,6 +911,13 @@ pub struct ItemId {
 }
 
 impl ItemId {
+    pub fn new(id: &str, change_key: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            change_key: change_key.to_string(),
+        }
+    }
+
     pub fn id(&self) -> &str {
         &self.id
     }
@@ -220,96 +927,5241 @@ impl ItemId {
     }
 }
 
-/// An email message.
+impl<W: std::io::Write> EwsWrite<W> for ItemId {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        writer.write(
+            XmlEvent::start_element("t:ItemId")
+                .attr("Id", &self.id)
+                .attr("ChangeKey", &self.change_key),
+        )?;
+        writer.write(XmlEvent::end_element())
+    }
+}
+
+/// A list of identifiers for the items an operation applies to.
 ///
-/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/message-ex15websvcsotherref>.
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/itemids>.
+struct ItemIds(Vec<ItemId>);
+
+impl<W: std::io::Write> EwsWrite<W> for ItemIds {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        writer.write(XmlEvent::start_element("ItemIds"))?;
+        self.0.write(writer)?;
+        writer.write(XmlEvent::end_element())
+    }
+
+    fn size_hint(&self) -> usize {
+        // Item IDs are long base64 strings, with change keys about a third
+        // of their size.
+        256 * self.0.len()
+    }
+}
+
+/// An identifier for a conversation, i.e. a thread of related items.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/conversationid>.
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "PascalCase")]
-pub struct Message {
-    item_id: ItemId,
-    subject: String,
+pub struct ConversationId {
+    id: String,
+    change_key: Option<String>,
 }
 
-impl Message {
-    pub fn item_id(&self) -> &ItemId {
-        &self.item_id
+impl ConversationId {
+    pub fn id(&self) -> &str {
+        &self.id
     }
 
-    pub fn subject(&self) -> &str {
-        &self.subject
+    pub fn change_key(&self) -> Option<&str> {
+        self.change_key.as_deref()
     }
 }
 
-/// The response to a [`FindItem`] request.
+impl<W: std::io::Write> EwsWrite<W> for ConversationId {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        let mut builder = XmlEvent::start_element("t:ConversationId").attr("Id", &self.id);
+
+        if let Some(change_key) = &self.change_key {
+            builder = builder.attr("ChangeKey", change_key);
+        }
+
+        writer.write(builder)?;
+        writer.write(XmlEvent::end_element())
+    }
+}
+
+/// The raw MIME representation of an item, e.g. a full RFC 822 message.
 ///
-/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/finditemresponse>.
-#[derive(Deserialize, Serialize)]
-#[serde(rename_all = "PascalCase")]
-pub struct FindItemResponse {
-    response_messages: ResponseMessages,
+/// The content is carried base64-encoded on the wire, so arbitrary bytes
+/// survive the round trip unmodified, which allows resending or forwarding an
+/// exact copy of an original message.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/mimecontent>.
+#[derive(Debug)]
+pub struct MimeContent {
+    /// The character set of the content once decoded, e.g. `UTF-8`.
+    character_set: Option<String>,
+    content: Vec<u8>,
 }
 
-#[derive(Deserialize, Serialize)]
-pub struct ResponseMessages {
-    #[serde(rename = "$value")]
-    contents: Vec<ResponseMessageContents>,
+impl MimeContent {
+    pub fn new(content: Vec<u8>, character_set: Option<&str>) -> Self {
+        Self {
+            character_set: character_set.map(str::to_string),
+            content,
+        }
+    }
+
+    pub fn character_set(&self) -> Option<&str> {
+        self.character_set.as_deref()
+    }
+
+    /// The decoded content.
+    pub fn content(&self) -> &[u8] {
+        &self.content
+    }
 }
 
-#[derive(Deserialize, Serialize)]
-pub enum ResponseMessageContents {
-    FindItemResponseMessage(FindItemResponseMessage),
+impl<W: std::io::Write> EwsWrite<W> for MimeContent {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        let start = XmlEvent::start_element("t:MimeContent");
+        let start = match &self.character_set {
+            Some(character_set) => start.attr("CharacterSet", character_set),
+            None => start,
+        };
+        writer.write(start)?;
+        writer.write(XmlEvent::characters(&BASE64_STANDARD.encode(&self.content)))?;
+        writer.write(XmlEvent::end_element())
+    }
 
-    // Placeholder just to demonstrate matching.
-    GetRemindersResponse(String),
+    fn size_hint(&self) -> usize {
+        // Base64 takes four bytes for every three bytes of input.
+        64 + self.content.len().div_ceil(3) * 4
+    }
 }
 
-#[derive(Deserialize, Serialize)]
-#[serde(rename_all = "PascalCase")]
-pub struct FindItemResponseMessage {
-    root_folder: RootFolder,
+impl<'de> Deserialize<'de> for MimeContent {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "PascalCase")]
+        struct EncodedMimeContent {
+            character_set: Option<String>,
+            #[serde(rename = "$value", default)]
+            content: String,
+        }
+
+        let encoded = EncodedMimeContent::deserialize(deserializer)?;
+        let content = BASE64_STANDARD
+            .decode(encoded.content.trim())
+            .map_err(serde::de::Error::custom)?;
+
+        Ok(Self {
+            character_set: encoded.character_set,
+            content,
+        })
+    }
 }
 
-#[derive(Deserialize, Serialize)]
-#[serde(rename_all = "PascalCase")]
-pub struct RootFolder {
-    items: Items,
+/// A point in time, as sent by the server in ISO 8601 form, e.g.
+/// `2023-05-16T09:30:00Z` or `2023-05-16T11:30:00+02:00`.
+///
+/// The time is held in UTC, at up to nanosecond precision, and written back
+/// in RFC 3339 form with a trailing `Z`.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/datetimereceived>.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DateTime {
+    /// Seconds since the Unix epoch.
+    seconds: i64,
+    nanos: u32,
 }
 
-#[derive(Deserialize, Serialize)]
-pub struct Items {
-    #[serde(rename = "$value")]
-    items: Vec<EwsItem>,
+impl DateTime {
+    pub fn from_unix_timestamp(seconds: i64) -> Self {
+        Self { seconds, nanos: 0 }
+    }
+
+    /// The number of whole seconds since the Unix epoch.
+    pub fn unix_timestamp(&self) -> i64 {
+        self.seconds
+    }
+
+    /// The fraction of a second past [`DateTime::unix_timestamp`].
+    pub fn subsec_nanos(&self) -> u32 {
+        self.nanos
+    }
 }
 
-#[derive(Deserialize, Serialize)]
+/// Two-digit decimal number at the start of `bytes`.
+fn parse_two_digits(bytes: &[u8]) -> Option<u32> {
+    match bytes {
+        [tens @ b'0'..=b'9', units @ b'0'..=b'9', ..] => {
+            Some(u32::from(tens - b'0') * 10 + u32::from(units - b'0'))
+        }
+        _ => None,
+    }
+}
+
+/// The number of days between the Unix epoch and the given date in the
+/// proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = i64::from(month);
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The inverse of [`days_from_civil`], giving the year, month and day.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+/// An error parsing a [`DateTime`].
+#[derive(Debug)]
+pub struct DateTimeParseError(String);
+
+impl std::fmt::Display for DateTimeParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid date and time {:?}", self.0)
+    }
+}
+
+impl std::error::Error for DateTimeParseError {}
+
+impl std::str::FromStr for DateTime {
+    type Err = DateTimeParseError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || DateTimeParseError(value.to_string());
+
+        // `YYYY-MM-DDTHH:MM:SS`, followed by an optional fraction of a second
+        // and either `Z` or an offset from UTC.
+        let bytes = value.as_bytes();
+        if bytes.len() < 20
+            || !bytes[..4].iter().all(u8::is_ascii_digit)
+            || bytes[4] != b'-'
+            || bytes[7] != b'-'
+            || !matches!(bytes[10], b'T' | b't')
+            || bytes[13] != b':'
+            || bytes[16] != b':'
+        {
+            return Err(invalid());
+        }
+
+        let year: i64 = value[..4].parse().map_err(|_| invalid())?;
+        let (month, day, hour, minute, second) = (
+            parse_two_digits(&bytes[5..]).ok_or_else(invalid)?,
+            parse_two_digits(&bytes[8..]).ok_or_else(invalid)?,
+            parse_two_digits(&bytes[11..]).ok_or_else(invalid)?,
+            parse_two_digits(&bytes[14..]).ok_or_else(invalid)?,
+            parse_two_digits(&bytes[17..]).ok_or_else(invalid)?,
+        );
+
+        let days = days_from_civil(year, month, day);
+        if !(1..=12).contains(&month)
+            || civil_from_days(days) != (year, month, day)
+            || hour > 23
+            || minute > 59
+            || second > 59
+        {
+            return Err(invalid());
+        }
+
+        let mut rest = &bytes[19..];
+
+        let mut nanos = 0;
+        if let [b'.', fraction @ ..] = rest {
+            let digits = fraction.iter().take_while(|b| b.is_ascii_digit()).count();
+            if digits == 0 {
+                return Err(invalid());
+            }
+
+            // Anything beyond nanosecond precision is dropped.
+            nanos = fraction[..digits]
+                .iter()
+                .chain(std::iter::repeat(&b'0'))
+                .take(9)
+                .fold(0, |nanos, digit| nanos * 10 + u32::from(digit - b'0'));
+            rest = &fraction[digits..];
+        }
+
+        let offset_seconds = match rest {
+            [b'Z' | b'z'] => 0,
+            [sign @ (b'+' | b'-'), _, _, b':', _, _] => {
+                let hours = parse_two_digits(&rest[1..]).ok_or_else(invalid)?;
+                let minutes = parse_two_digits(&rest[4..]).ok_or_else(invalid)?;
+                if hours > 23 || minutes > 59 {
+                    return Err(invalid());
+                }
+
+                let offset = i64::from(hours * 3600 + minutes * 60);
+                if *sign == b'-' {
+                    -offset
+                } else {
+                    offset
+                }
+            }
+            _ => return Err(invalid()),
+        };
+
+        let seconds = days * 86_400 + i64::from(hour * 3600 + minute * 60 + second);
+
+        Ok(Self {
+            seconds: seconds - offset_seconds,
+            nanos,
+        })
+    }
+}
+
+impl std::fmt::Display for DateTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (year, month, day) = civil_from_days(self.seconds.div_euclid(86_400));
+        let time = self.seconds.rem_euclid(86_400);
+        write!(
+            f,
+            "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}",
+            time / 3600,
+            time / 60 % 60,
+            time % 60
+        )?;
+
+        if self.nanos != 0 {
+            let fraction = format!("{:09}", self.nanos);
+            write!(f, ".{}", fraction.trim_end_matches('0'))?;
+        }
+
+        write!(f, "Z")
+    }
+}
+
+impl Serialize for DateTime {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for DateTime {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        value.trim().parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// An email message.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/message-ex15websvcsotherref>.
+#[derive(Debug, Default, Deserialize, Serialize)]
 #[serde(rename_all = "PascalCase")]
-pub enum EwsItem {
-    Message(Message),
+pub struct Message {
+    #[serde(skip_serializing)]
+    mime_content: Option<MimeContent>,
+
+    /// The identifier of the message, which is assigned by the server and so
+    /// absent from messages being created.
+    item_id: Option<ItemId>,
+
+    subject: Option<String>,
+    body: Option<Body>,
+    attachments: Option<Attachments>,
+    date_time_received: Option<DateTime>,
+    size: Option<u64>,
+    importance: Option<Importance>,
+    internet_message_headers: Option<InternetMessageHeaders>,
+    date_time_sent: Option<DateTime>,
+    has_attachments: Option<bool>,
+    conversation_id: Option<ConversationId>,
+    effective_rights: Option<EffectiveRights>,
+    flag: Option<Flag>,
+    to_recipients: Option<ArrayOfRecipients>,
+    cc_recipients: Option<ArrayOfRecipients>,
+    bcc_recipients: Option<ArrayOfRecipients>,
+    from: Option<SingleRecipient>,
+    is_read: Option<bool>,
 }
 
-impl FindItemResponse {
-    pub fn messages(&self) -> Vec<&Message> {
-        self.response_messages
-            .contents
-            .iter()
-            .filter_map(|message| match message {
-                ResponseMessageContents::FindItemResponseMessage(message) => Some(message),
-                _ => None,
-            })
-            .next()
-            .unwrap()
-            .root_folder
-            .items
-            .items
-            .iter()
-            .filter_map(|item| match item {
-                EwsItem::Message(message) => Some(message),
-            })
-            .collect()
+impl Message {
+    pub fn with_mime_content(mut self, mime_content: MimeContent) -> Self {
+        self.mime_content = Some(mime_content);
+        self
+    }
+
+    pub fn with_subject(mut self, subject: &str) -> Self {
+        self.subject = Some(subject.to_string());
+        self
+    }
+
+    pub fn with_body(mut self, body: Body) -> Self {
+        self.body = Some(body);
+        self
+    }
+
+    pub fn with_importance(mut self, importance: Importance) -> Self {
+        self.importance = Some(importance);
+        self
+    }
+
+    pub fn with_to_recipients(mut self, to_recipients: Vec<Mailbox>) -> Self {
+        self.to_recipients = Some(ArrayOfRecipients {
+            mailboxes: to_recipients,
+        });
+        self
+    }
+
+    pub fn with_cc_recipients(mut self, cc_recipients: Vec<Mailbox>) -> Self {
+        self.cc_recipients = Some(ArrayOfRecipients {
+            mailboxes: cc_recipients,
+        });
+        self
+    }
+
+    pub fn with_bcc_recipients(mut self, bcc_recipients: Vec<Mailbox>) -> Self {
+        self.bcc_recipients = Some(ArrayOfRecipients {
+            mailboxes: bcc_recipients,
+        });
+        self
+    }
+
+    pub fn with_from(mut self, from: Mailbox) -> Self {
+        self.from = Some(SingleRecipient { mailbox: from });
+        self
+    }
+
+    pub fn with_is_read(mut self, is_read: bool) -> Self {
+        self.is_read = Some(is_read);
+        self
+    }
+
+    /// The MIME representation of the message, if it was requested.
+    pub fn mime_content(&self) -> Option<&MimeContent> {
+        self.mime_content.as_ref()
+    }
+
+    pub fn item_id(&self) -> Option<&ItemId> {
+        self.item_id.as_ref()
+    }
+
+    /// The subject of the message, unless the requested shape left it out.
+    pub fn subject(&self) -> Option<&str> {
+        self.subject.as_deref()
+    }
+
+    /// The attachments on the message, if they were requested.
+    ///
+    /// Only their metadata is included; the content of each has to be
+    /// fetched with [`GetAttachment`].
+    pub fn attachments(&self) -> &[Attachment] {
+        self.attachments
+            .as_ref()
+            .map_or(&[], |attachments| &attachments.attachments)
+    }
+
+    /// When the message arrived in the mailbox, if it was requested.
+    pub fn date_time_received(&self) -> Option<DateTime> {
+        self.date_time_received
+    }
+
+    /// When the message was sent, if it was requested.
+    pub fn date_time_sent(&self) -> Option<DateTime> {
+        self.date_time_sent
+    }
+
+    /// The size of the message in bytes, if it was requested.
+    pub fn size(&self) -> Option<u64> {
+        self.size
+    }
+
+    /// Whether the message has attachments, if it was requested.
+    ///
+    /// Inline attachments, e.g. images in an HTML body, are not counted.
+    pub fn has_attachments(&self) -> Option<bool> {
+        self.has_attachments
+    }
+
+    /// Whether the message has been read, if it was requested.
+    pub fn is_read(&self) -> Option<bool> {
+        self.is_read
+    }
+
+    /// The importance the sender gave the message, if it was requested.
+    pub fn importance(&self) -> Option<Importance> {
+        self.importance
+    }
+
+    /// The follow-up flag on the message, if it was requested.
+    ///
+    /// Servers older than Exchange 2013 never send one.
+    pub fn flag(&self) -> Option<&Flag> {
+        self.flag.as_ref()
+    }
+
+    /// The RFC 822 headers of the message, if they were requested.
+    pub fn internet_message_headers(&self) -> &[InternetMessageHeader] {
+        self.internet_message_headers
+            .as_ref()
+            .map_or(&[], |headers| &headers.headers)
+    }
+
+    /// The identifier of the conversation (thread) this message belongs to,
+    /// if it was requested.
+    pub fn conversation_id(&self) -> Option<&ConversationId> {
+        self.conversation_id.as_ref()
+    }
+
+    /// The body of the message, if it was requested.
+    pub fn body(&self) -> Option<&Body> {
+        self.body.as_ref()
+    }
+
+    pub fn to_recipients(&self) -> &[Mailbox] {
+        self.to_recipients
+            .as_ref()
+            .map_or(&[], |recipients| &recipients.mailboxes)
+    }
+
+    pub fn cc_recipients(&self) -> &[Mailbox] {
+        self.cc_recipients
+            .as_ref()
+            .map_or(&[], |recipients| &recipients.mailboxes)
+    }
+
+    /// The blind carbon copy recipients, which the server only knows about
+    /// for messages sent by the user.
+    pub fn bcc_recipients(&self) -> &[Mailbox] {
+        self.bcc_recipients
+            .as_ref()
+            .map_or(&[], |recipients| &recipients.mailboxes)
+    }
+
+    pub fn from(&self) -> Option<&Mailbox> {
+        self.from.as_ref().map(|from| &from.mailbox)
+    }
+
+    /// What the user is allowed to do with the message, if it was requested
+    /// with [`FieldUri::ItemEffectiveRights`].
+    pub fn effective_rights(&self) -> Option<&EffectiveRights> {
+        self.effective_rights.as_ref()
     }
 }
 
-pub struct GetFolder {
-    pub folder_ids: Vec<FolderId>,
-    pub folder_shape: FolderShape,
+/// How important the sender considers an item to be.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/importance>.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub enum Importance {
+    Low,
+    Normal,
+    High,
+}
+
+impl From<Importance> for &str {
+    fn from(value: Importance) -> Self {
+        match value {
+            Importance::Low => "Low",
+            Importance::Normal => "Normal",
+            Importance::High => "High",
+        }
+    }
+}
+
+/// Whether an item is flagged for follow-up.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/flagstatus>.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub enum FlagStatus {
+    NotFlagged,
+    Flagged,
+    Complete,
+}
+
+/// The follow-up flag on an item.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/flag>.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Flag {
+    pub flag_status: FlagStatus,
+
+    /// When work on the item should start, if the flag has dates.
+    pub start_date: Option<DateTime>,
+
+    /// When the item is due, if the flag has dates.
+    pub due_date: Option<DateTime>,
+
+    /// When the flag was marked complete.
+    pub complete_date: Option<DateTime>,
+}
+
+/// The actions the authenticated user is allowed to perform on an item or
+/// folder.
+///
+/// Rights the server leaves out are treated as not granted.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/effectiverights>.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct EffectiveRights {
+    /// Whether associated (hidden) items can be created in the folder.
+    pub create_associated: bool,
+
+    /// Whether items can be created in the folder.
+    pub create_contents: bool,
+
+    /// Whether subfolders can be created in the folder.
+    pub create_hierarchy: bool,
+
+    pub delete: bool,
+    pub modify: bool,
+    pub read: bool,
+
+    /// Whether private items in the folder can be viewed.
+    pub view_private_items: bool,
+}
+
+/// An identifier for an attachment, as assigned by the server.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/attachmentid>.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct AttachmentId {
+    id: String,
+}
+
+impl AttachmentId {
+    pub fn new(id: &str) -> Self {
+        Self { id: id.to_string() }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl<W: std::io::Write> EwsWrite<W> for AttachmentId {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        writer.write(XmlEvent::start_element("t:AttachmentId").attr("Id", &self.id))?;
+        writer.write(XmlEvent::end_element())
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Attachments {
+    #[serde(rename = "$value", default)]
+    attachments: Vec<Attachment>,
+}
+
+/// A file or item attached to an item.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/attachments-ex15websvcsotherref>.
+#[derive(Debug, Deserialize, Serialize)]
+pub enum Attachment {
+    FileAttachment(FileAttachment),
+    ItemAttachment(ItemAttachment),
+}
+
+impl Attachment {
+    pub fn attachment_id(&self) -> &AttachmentId {
+        match self {
+            Attachment::FileAttachment(attachment) => &attachment.attachment_id,
+            Attachment::ItemAttachment(attachment) => &attachment.attachment_id,
+        }
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Attachment::FileAttachment(attachment) => attachment.name.as_deref(),
+            Attachment::ItemAttachment(attachment) => attachment.name.as_deref(),
+        }
+    }
+
+    /// The MIME type of the attachment, e.g. `application/pdf`.
+    pub fn content_type(&self) -> Option<&str> {
+        match self {
+            Attachment::FileAttachment(attachment) => attachment.content_type.as_deref(),
+            Attachment::ItemAttachment(attachment) => attachment.content_type.as_deref(),
+        }
+    }
+
+    /// The size of the attachment in bytes.
+    pub fn size(&self) -> Option<u64> {
+        match self {
+            Attachment::FileAttachment(attachment) => attachment.size,
+            Attachment::ItemAttachment(attachment) => attachment.size,
+        }
+    }
+}
+
+/// A file attached to an item.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/fileattachment>.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct FileAttachment {
+    attachment_id: AttachmentId,
+    name: Option<String>,
+    content_type: Option<String>,
+    size: Option<u64>,
+
+    /// Whether the attachment is displayed within the body, e.g. an image in
+    /// an HTML body.
+    is_inline: Option<bool>,
+
+    /// The base64-encoded content of the file, only present in the response
+    /// to a [`GetAttachment`] request.
+    content: Option<String>,
+}
+
+impl FileAttachment {
+    pub fn is_inline(&self) -> bool {
+        self.is_inline.unwrap_or(false)
+    }
+
+    /// The base64-encoded content of the file, if it was fetched.
+    pub fn content(&self) -> Option<&str> {
+        self.content.as_deref()
+    }
+
+    /// The content of the file, if it was fetched, decoded from base64.
+    pub fn decoded_content(&self) -> Result<Option<Vec<u8>>, base64::DecodeError> {
+        self.content
+            .as_ref()
+            .map(|content| BASE64_STANDARD.decode(content.trim()))
+            .transpose()
+    }
+}
+
+/// An item, such as a forwarded message, attached to another item.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/itemattachment>.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ItemAttachment {
+    attachment_id: AttachmentId,
+    name: Option<String>,
+    content_type: Option<String>,
+    size: Option<u64>,
+    is_inline: Option<bool>,
+
+    /// The attached message, only present in the response to a
+    /// [`GetAttachment`] request.
+    message: Option<Box<Message>>,
+}
+
+impl ItemAttachment {
+    pub fn is_inline(&self) -> bool {
+        self.is_inline.unwrap_or(false)
+    }
+
+    /// The attached message, if it was fetched.
+    pub fn message(&self) -> Option<&Message> {
+        self.message.as_deref()
+    }
+}
+
+impl<W: std::io::Write> EwsWrite<W> for Message {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        writer.write(XmlEvent::start_element("t:Message"))?;
+
+        self.mime_content.write(writer)?;
+
+        // The conversation ID, effective rights, timestamps, size and whether
+        // there are attachments are all determined by the server, so we never
+        // send them. Elements must be written in the order of the
+        // schema.
+        self.item_id.write(writer)?;
+
+        if let Some(subject) = &self.subject {
+            write_text_element(writer, "t:Subject", subject)?;
+        }
+
+        self.body.write(writer)?;
+
+        if let Some(importance) = self.importance {
+            write_text_element(writer, "t:Importance", <&str>::from(importance))?;
+        }
+
+        self.internet_message_headers.write(writer)?;
+
+        if let Some(to_recipients) = &self.to_recipients {
+            to_recipients.write(writer, "t:ToRecipients")?;
+        }
+
+        if let Some(cc_recipients) = &self.cc_recipients {
+            cc_recipients.write(writer, "t:CcRecipients")?;
+        }
+
+        if let Some(bcc_recipients) = &self.bcc_recipients {
+            bcc_recipients.write(writer, "t:BccRecipients")?;
+        }
+
+        if let Some(from) = &self.from {
+            writer.write(XmlEvent::start_element("t:From"))?;
+            from.mailbox.write(writer)?;
+            writer.write(XmlEvent::end_element())?;
+        }
+
+        if let Some(is_read) = self.is_read {
+            write_text_element(writer, "t:IsRead", is_read)?;
+        }
+
+        writer.write(XmlEvent::end_element())
+    }
+}
+
+/// The format of the body of an item.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/body>.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BodyType {
+    Html,
+    Text,
+}
+
+impl From<BodyType> for &str {
+    fn from(value: BodyType) -> Self {
+        match value {
+            BodyType::Html => "HTML",
+            BodyType::Text => "Text",
+        }
+    }
+}
+
+impl Serialize for BodyType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str((*self).into())
+    }
+}
+
+impl<'de> Deserialize<'de> for BodyType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        // Some servers don't use the documented casing, e.g. sending `html`.
+        let value = String::deserialize(deserializer)?;
+        if value.eq_ignore_ascii_case("HTML") {
+            Ok(BodyType::Html)
+        } else if value.eq_ignore_ascii_case("Text") {
+            Ok(BodyType::Text)
+        } else {
+            Err(serde::de::Error::custom(format!(
+                "unsupported BodyType {value}"
+            )))
+        }
+    }
+}
+
+/// The body of an item.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/body>.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Body {
+    #[serde(rename = "BodyType")]
+    body_type: BodyType,
+
+    #[serde(rename = "$value", default)]
+    content: String,
+}
+
+impl Body {
+    pub fn new(body_type: BodyType, content: &str) -> Self {
+        Self {
+            body_type,
+            content: content.to_string(),
+        }
+    }
+
+    pub fn body_type(&self) -> BodyType {
+        self.body_type
+    }
+
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+}
+
+/// HTML bodies are written in a CDATA section, which keeps their markup
+/// readable rather than escaping every tag.
+impl<W: std::io::Write> EwsWrite<W> for Body {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        writer.write(XmlEvent::start_element("t:Body").attr("BodyType", self.body_type.into()))?;
+
+        match self.body_type {
+            BodyType::Html => {
+                // A CDATA section can't contain its own terminator, so split
+                // the content between the `]]` and `>` of any occurrence.
+                let mut rest = self.content.as_str();
+                while let Some(index) = rest.find("]]>") {
+                    writer.write(XmlEvent::cdata(&rest[..index + 2]))?;
+                    rest = &rest[index + 2..];
+                }
+                writer.write(XmlEvent::cdata(rest))?;
+            }
+            BodyType::Text => writer.write(XmlEvent::characters(&self.content))?,
+        }
+
+        writer.write(XmlEvent::end_element())
+    }
+}
+
+/// The RFC 822 headers of a message.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/internetmessageheaders>.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct InternetMessageHeaders {
+    #[serde(rename = "InternetMessageHeader", default)]
+    headers: Vec<InternetMessageHeader>,
+}
+
+impl<W: std::io::Write> EwsWrite<W> for InternetMessageHeaders {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        writer.write(XmlEvent::start_element("t:InternetMessageHeaders"))?;
+        self.headers.write(writer)?;
+        writer.write(XmlEvent::end_element())
+    }
+}
+
+/// A single RFC 822 header, e.g. `X-Mailer` or `References`.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/internetmessageheader>.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct InternetMessageHeader {
+    #[serde(rename = "HeaderName")]
+    name: String,
+
+    #[serde(rename = "$value", default)]
+    value: String,
+}
+
+impl InternetMessageHeader {
+    pub fn new(name: &str, value: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
+
+impl<W: std::io::Write> EwsWrite<W> for InternetMessageHeader {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        writer.write(
+            XmlEvent::start_element("t:InternetMessageHeader").attr("HeaderName", &self.name),
+        )?;
+        writer.write(XmlEvent::characters(&self.value))?;
+        writer.write(XmlEvent::end_element())
+    }
+}
+
+/// The outcome of processing a single response message.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/responseclass>.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub enum ResponseClass {
+    Success,
+    Warning,
+    Error,
+}
+
+/// The status code of a single response message.
+///
+/// Only the codes callers are likely to handle specifically are modelled;
+/// any other code is kept as-is in [`ResponseCode::Other`].
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/responsecode>.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ResponseCode {
+    NoError,
+    ErrorAccessDenied,
+    ErrorFolderNotFound,
+    ErrorInvalidIdMalformed,
+    ErrorItemNotFound,
+    ErrorMailboxStoreUnavailable,
+    ErrorServerBusy,
+    ErrorTimeoutExpired,
+    Other(String),
+}
+
+impl ResponseCode {
+    /// Converts the code into a `Result`, so that server errors can be
+    /// propagated with `?`.
+    pub fn to_result(self) -> Result<(), EwsError> {
+        match self {
+            ResponseCode::NoError => Ok(()),
+            ResponseCode::ErrorAccessDenied => Err(EwsError::AccessDenied),
+            ResponseCode::ErrorFolderNotFound => Err(EwsError::FolderNotFound),
+            ResponseCode::ErrorInvalidIdMalformed => Err(EwsError::InvalidId),
+            ResponseCode::ErrorItemNotFound => Err(EwsError::ItemNotFound),
+            ResponseCode::ErrorMailboxStoreUnavailable | ResponseCode::ErrorServerBusy => {
+                Err(EwsError::ServerUnavailable)
+            }
+            ResponseCode::ErrorTimeoutExpired => Err(EwsError::TimedOut),
+            ResponseCode::Other(code) => Err(EwsError::Other(code)),
+        }
+    }
+}
+
+impl ResponseCode {
+    /// The code as it appears in responses from the server.
+    pub fn as_str(&self) -> &str {
+        match self {
+            ResponseCode::NoError => "NoError",
+            ResponseCode::ErrorAccessDenied => "ErrorAccessDenied",
+            ResponseCode::ErrorFolderNotFound => "ErrorFolderNotFound",
+            ResponseCode::ErrorInvalidIdMalformed => "ErrorInvalidIdMalformed",
+            ResponseCode::ErrorItemNotFound => "ErrorItemNotFound",
+            ResponseCode::ErrorMailboxStoreUnavailable => "ErrorMailboxStoreUnavailable",
+            ResponseCode::ErrorServerBusy => "ErrorServerBusy",
+            ResponseCode::ErrorTimeoutExpired => "ErrorTimeoutExpired",
+            ResponseCode::Other(code) => code,
+        }
+    }
+}
+
+impl From<&str> for ResponseCode {
+    fn from(code: &str) -> Self {
+        match code {
+            "NoError" => ResponseCode::NoError,
+            "ErrorAccessDenied" => ResponseCode::ErrorAccessDenied,
+            "ErrorFolderNotFound" => ResponseCode::ErrorFolderNotFound,
+            "ErrorInvalidIdMalformed" => ResponseCode::ErrorInvalidIdMalformed,
+            "ErrorItemNotFound" => ResponseCode::ErrorItemNotFound,
+            "ErrorMailboxStoreUnavailable" => ResponseCode::ErrorMailboxStoreUnavailable,
+            "ErrorServerBusy" => ResponseCode::ErrorServerBusy,
+            "ErrorTimeoutExpired" => ResponseCode::ErrorTimeoutExpired,
+            code => ResponseCode::Other(code.to_string()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ResponseCode {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let code = String::deserialize(deserializer)?;
+        Ok(code.trim().into())
+    }
+}
+
+impl Serialize for ResponseCode {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+/// An error occurring while performing an EWS operation.
+///
+/// Most variants correspond to errors reported by the server through the
+/// response code of a response message. The others wrap failures to write a
+/// request, send it or parse the response, so that every public function in
+/// the crate can be used with `?` against this one type, which is also
+/// exported as [`crate::Error`].
+#[derive(Debug)]
+pub enum EwsError {
+    AccessDenied,
+    FolderNotFound,
+    InvalidId,
+    ItemNotFound,
+
+    /// The server or mailbox is temporarily unable to process requests, and
+    /// the request may be retried later.
+    ServerUnavailable,
+
+    TimedOut,
+
+    /// Any other error, with the response code returned by the server.
+    Other(String),
+
+    /// The server did not fully succeed in processing the request, without
+    /// giving a more specific response code.
+    Response(EwsResponseError),
+
+    /// The endpoint requests were to be sent to isn't an absolute HTTP(S)
+    /// URL.
+    InvalidEndpoint(String),
+
+    /// The request could not be sent, or no response was received.
+    Transport(crate::net::HttpError),
+
+    /// The request could not be serialized.
+    Serialize(xml::writer::Error),
+
+    /// The response could not be parsed.
+    Deserialize(serde_xml_rs::Error),
+
+    /// The server couldn't process the request at all.
+    Fault(Fault),
+
+    /// The response was not the one expected for the request.
+    UnexpectedResponse,
+}
+
+impl std::fmt::Display for EwsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EwsError::AccessDenied => write!(f, "access denied"),
+            EwsError::FolderNotFound => write!(f, "folder not found"),
+            EwsError::InvalidId => write!(f, "malformed identifier"),
+            EwsError::ItemNotFound => write!(f, "item not found"),
+            EwsError::ServerUnavailable => write!(f, "server temporarily unavailable"),
+            EwsError::TimedOut => write!(f, "operation timed out"),
+            EwsError::Other(code) => write!(f, "request failed with response code {code}"),
+            EwsError::Response(err) => err.fmt(f),
+            EwsError::InvalidEndpoint(endpoint) => {
+                write!(
+                    f,
+                    "invalid EWS endpoint {endpoint:?}, expected an absolute URL"
+                )
+            }
+            EwsError::Transport(err) => write!(f, "HTTP request failed: {err}"),
+            EwsError::Serialize(err) => write!(f, "failed to write request: {err}"),
+            EwsError::Deserialize(err) => write!(f, "failed to parse response: {err}"),
+            EwsError::Fault(fault) => fault.fmt(f),
+            EwsError::UnexpectedResponse => write!(f, "unexpected response to request"),
+        }
+    }
+}
+
+impl std::error::Error for EwsError {}
+
+impl From<EwsResponseError> for EwsError {
+    /// Maps the error to the variant for its response code, if it has one
+    /// other than `NoError`, e.g. with warnings.
+    fn from(err: EwsResponseError) -> Self {
+        match err.response_code.clone().map(ResponseCode::to_result) {
+            Some(Err(typed)) => typed,
+            _ => EwsError::Response(err),
+        }
+    }
+}
+
+impl From<Fault> for EwsError {
+    fn from(fault: Fault) -> Self {
+        EwsError::Fault(fault)
+    }
+}
+
+impl From<crate::net::HttpError> for EwsError {
+    fn from(err: crate::net::HttpError) -> Self {
+        EwsError::Transport(err)
+    }
+}
+
+impl From<reqwest::Error> for EwsError {
+    fn from(err: reqwest::Error) -> Self {
+        EwsError::Transport(err.into())
+    }
+}
+
+impl From<xml::writer::Error> for EwsError {
+    fn from(err: xml::writer::Error) -> Self {
+        EwsError::Serialize(err)
+    }
+}
+
+impl From<serde_xml_rs::Error> for EwsError {
+    fn from(err: serde_xml_rs::Error) -> Self {
+        EwsError::Deserialize(err)
+    }
+}
+
+/// An error indicating that the server did not fully succeed in processing a
+/// request.
+#[derive(Debug)]
+pub struct EwsResponseError {
+    response_class: ResponseClass,
+    response_code: Option<ResponseCode>,
+    message_text: Option<String>,
+}
+
+impl EwsResponseError {
+    pub fn response_class(&self) -> ResponseClass {
+        self.response_class
+    }
+
+    /// The code the server gave for the failure, if any.
+    pub fn response_code(&self) -> Option<&ResponseCode> {
+        self.response_code.as_ref()
+    }
+
+    /// The server's description of the failure, if any.
+    pub fn message_text(&self) -> Option<&str> {
+        self.message_text.as_deref()
+    }
+}
+
+impl std::fmt::Display for EwsResponseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "request failed with response class {:?}",
+            self.response_class
+        )?;
+
+        if let Some(response_code) = &self.response_code {
+            write!(f, " ({})", response_code.as_str())?;
+        }
+
+        if let Some(message_text) = &self.message_text {
+            write!(f, ": {message_text}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for EwsResponseError {}
+
+/// The outcome of processing a single response message, which every
+/// operation's response messages start with.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/responsemessage>.
+struct ResponseMessageHeader<'a> {
+    response_class: ResponseClass,
+    response_code: Option<&'a ResponseCode>,
+    message_text: Option<&'a str>,
+}
+
+impl ResponseMessageHeader<'_> {
+    /// Returns an error unless the response class indicates success, carrying
+    /// the code and text the server gave alongside it.
+    fn check(&self) -> Result<(), EwsResponseError> {
+        match self.response_class {
+            ResponseClass::Success => Ok(()),
+            response_class => Err(EwsResponseError {
+                response_class,
+                response_code: self.response_code.cloned(),
+                message_text: self.message_text.map(str::to_string),
+            }),
+        }
+    }
+}
+
+/// A response message, i.e. the part of a response for one of the things a
+/// request asked for.
+trait ResponseMessage {
+    fn header(&self) -> ResponseMessageHeader<'_>;
+}
+
+/// Defines a response message, adding the fields for the response class, code
+/// and text every response message starts with to the given ones, and
+/// implements [`ResponseMessage`] for it.
+///
+/// serde-xml-rs can't flatten a struct holding them into each message, so the
+/// fields are repeated by this instead.
+macro_rules! response_message {
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident {
+            $($(#[$field_attr:meta])* $field:ident: $ty:ty,)*
+        }
+    ) => {
+        $(#[$attr])*
+        $vis struct $name {
+            response_class: ResponseClass,
+
+            /// The code the server gave for the outcome of processing the
+            /// message.
+            response_code: Option<ResponseCode>,
+
+            /// A description of the outcome, generally only present on
+            /// failure.
+            message_text: Option<String>,
+
+            $($(#[$field_attr])* $field: $ty,)*
+        }
+
+        impl ResponseMessage for $name {
+            fn header(&self) -> ResponseMessageHeader<'_> {
+                ResponseMessageHeader {
+                    response_class: self.response_class,
+                    response_code: self.response_code.as_ref(),
+                    message_text: self.message_text.as_deref(),
+                }
+            }
+        }
+    };
+}
+
+/// A response made up of response messages, one for each of the things the
+/// request asked for, e.g. each item to fetch.
+///
+/// This provides `into_result()` for the responses of most operations.
+pub trait HasResponseMessages: Sized {
+    fn response_messages(&self) -> &ResponseMessages;
+
+    /// Checks that the server successfully processed the request.
+    fn into_result(self) -> Result<CheckedResponse<Self>, EwsResponseError> {
+        self.response_messages().check()?;
+        Ok(CheckedResponse(self))
+    }
+}
+
+/// A response which has been checked to be successful.
+///
+/// Accessors for the data in a response are only available once it's been
+/// checked with its `into_result()` method, so that data can't be read from a
+/// response the server failed to produce.
+pub struct CheckedResponse<T>(T);
+
+/// The response to a [`FindItem`] request.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/finditemresponse>.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct FindItemResponse {
+    response_messages: ResponseMessages,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ResponseMessages {
+    #[serde(rename = "$value", default)]
+    contents: Vec<ResponseMessageContents>,
+}
+
+impl ResponseMessages {
+    /// Returns an error if any of the response messages wasn't successful.
+    fn check(&self) -> Result<(), EwsResponseError> {
+        self.contents
+            .iter()
+            .try_for_each(ResponseMessageContents::check)
+    }
+
+    /// The identifiers of the items in response messages which carry some.
+    fn item_ids(&self) -> Vec<&ItemId> {
+        self.items()
+            .flat_map(Items::messages)
+            .filter_map(Message::item_id)
+            .collect()
+    }
+
+    /// The items in response messages which carry some.
+    fn items(&self) -> impl Iterator<Item = &Items> {
+        self.contents.iter().filter_map(|message| match message {
+            ResponseMessageContents::CopyItemResponseMessage(message)
+            | ResponseMessageContents::CreateItemResponseMessage(message)
+            | ResponseMessageContents::GetItemResponseMessage(message)
+            | ResponseMessageContents::MoveItemResponseMessage(message)
+            | ResponseMessageContents::UpdateItemResponseMessage(message) => message.items.as_ref(),
+            _ => None,
+        })
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub enum ResponseMessageContents {
+    CopyItemResponseMessage(ItemInfoResponseMessage),
+
+    CreateItemResponseMessage(ItemInfoResponseMessage),
+
+    DeleteItemResponseMessage(DeleteItemResponseMessage),
+
+    FindFolderResponseMessage(FindFolderResponseMessage),
+
+    FindItemResponseMessage(FindItemResponseMessage),
+
+    GetAttachmentResponseMessage(GetAttachmentResponseMessage),
+
+    GetConversationItemsResponseMessage(GetConversationItemsResponseMessage),
+
+    GetEventsResponseMessage(GetEventsResponseMessage),
+
+    GetFolderResponseMessage(GetFolderResponseMessage),
+
+    GetItemResponseMessage(ItemInfoResponseMessage),
+
+    MoveItemResponseMessage(ItemInfoResponseMessage),
+
+    SyncFolderItemsResponseMessage(SyncFolderItemsResponseMessage),
+
+    SyncFolderHierarchyResponseMessage(SyncFolderHierarchyResponseMessage),
+
+    UpdateItemResponseMessage(ItemInfoResponseMessage),
+}
+
+impl ResponseMessageContents {
+    /// Returns an error if the message reports that the server failed to
+    /// process its part of the request.
+    fn check(&self) -> Result<(), EwsResponseError> {
+        self.message().header().check()
+    }
+
+    fn message(&self) -> &dyn ResponseMessage {
+        match self {
+            ResponseMessageContents::CopyItemResponseMessage(message)
+            | ResponseMessageContents::CreateItemResponseMessage(message)
+            | ResponseMessageContents::GetItemResponseMessage(message)
+            | ResponseMessageContents::MoveItemResponseMessage(message)
+            | ResponseMessageContents::UpdateItemResponseMessage(message) => message,
+            ResponseMessageContents::DeleteItemResponseMessage(message) => message,
+            ResponseMessageContents::FindFolderResponseMessage(message) => message,
+            ResponseMessageContents::FindItemResponseMessage(message) => message,
+            ResponseMessageContents::GetAttachmentResponseMessage(message) => message,
+            ResponseMessageContents::GetConversationItemsResponseMessage(message) => message,
+            ResponseMessageContents::GetEventsResponseMessage(message) => message,
+            ResponseMessageContents::GetFolderResponseMessage(message) => message,
+            ResponseMessageContents::SyncFolderHierarchyResponseMessage(message) => message,
+            ResponseMessageContents::SyncFolderItemsResponseMessage(message) => message,
+        }
+    }
+}
+
+response_message! {
+    #[derive(Deserialize, Serialize)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct FindItemResponseMessage {
+        /// The items found, only present if the response was successful.
+        root_folder: Option<RootFolder>,
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct RootFolder {
+    /// The total number of items matching the request, across all pages.
+    total_items_in_view: Option<u32>,
+
+    /// The offset to request the next page from when paging with an
+    /// [`IndexedPageItemView`].
+    indexed_paging_offset: Option<u32>,
+
+    /// Whether this page includes the last matching item.
+    includes_last_item_in_range: Option<bool>,
+
+    /// The items found, unless they were grouped.
+    items: Option<Items>,
+
+    /// The items found, if they were grouped.
+    groups: Option<Groups>,
+
+    /// The folders found, in response to a [`FindFolder`] request.
+    folders: Option<Folders>,
+}
+
+impl RootFolder {
+    pub fn total_items_in_view(&self) -> Option<u32> {
+        self.total_items_in_view
+    }
+
+    pub fn indexed_paging_offset(&self) -> Option<u32> {
+        self.indexed_paging_offset
+    }
+
+    /// Whether this is the last page of results. Servers leaving the
+    /// attribute out are assumed to have returned everything.
+    pub fn includes_last_item_in_range(&self) -> bool {
+        self.includes_last_item_in_range.unwrap_or(true)
+    }
+
+    /// The folders found by a [`FindFolder`] request.
+    pub fn folders(&self) -> Vec<&Folder> {
+        self.folders
+            .iter()
+            .flat_map(|folders| folders.folders.iter().map(EwsFolder::folder))
+            .collect()
+    }
+
+    fn grouped_items(&self) -> impl Iterator<Item = &GroupedItems> {
+        self.groups.iter().flat_map(|groups| groups.groups.iter())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct Groups {
+    #[serde(rename = "GroupedItems", default)]
+    groups: Vec<GroupedItems>,
+}
+
+/// A group of items sharing the same value for the property they were grouped
+/// by.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/groupeditems>.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct GroupedItems {
+    group_index: String,
+    items: Items,
+}
+
+impl GroupedItems {
+    /// The value shared by the items in the group.
+    pub fn group_index(&self) -> &str {
+        &self.group_index
+    }
+
+    pub fn messages(&self) -> Vec<&Message> {
+        self.items.messages().collect()
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct Items {
+    #[serde(rename = "$value", default)]
+    items: Vec<EwsItem>,
+}
+
+impl Items {
+    fn messages(&self) -> impl Iterator<Item = &Message> {
+        self.items.iter().map(|item| match item {
+            EwsItem::Message(message) => message,
+        })
+    }
+}
+
+/// An item in a mailbox.
+///
+/// Like other enums mapping to a choice of EWS elements, variants are tagged by
+/// element name in both directions: a variant is written as an element named
+/// after it, and the local name of an incoming element selects the variant to
+/// deserialize.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub enum EwsItem {
+    Message(Message),
+}
+
+impl<W: std::io::Write> EwsWrite<W> for EwsItem {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        match self {
+            EwsItem::Message(message) => message.write(writer),
+        }
+    }
+}
+
+impl HasResponseMessages for FindItemResponse {
+    fn response_messages(&self) -> &ResponseMessages {
+        &self.response_messages
+    }
+}
+
+impl CheckedResponse<FindItemResponse> {
+    /// The results for each of the requested folders, along with the
+    /// information needed to request the next page.
+    pub fn root_folders(&self) -> Vec<&RootFolder> {
+        self.iter_root_folders().collect()
+    }
+
+    fn iter_root_folders(&self) -> impl Iterator<Item = &RootFolder> {
+        self.0
+            .response_messages
+            .contents
+            .iter()
+            .filter_map(|message| match message {
+                ResponseMessageContents::FindItemResponseMessage(message) => {
+                    message.root_folder.as_ref()
+                }
+                _ => None,
+            })
+    }
+
+    /// All messages found, including those in groups if the items were
+    /// grouped.
+    pub fn messages(&self) -> Vec<&Message> {
+        self.iter_root_folders()
+            .flat_map(|root_folder| {
+                let ungrouped = root_folder.items.iter().flat_map(Items::messages);
+                let grouped = root_folder
+                    .grouped_items()
+                    .flat_map(|group| group.items.messages());
+
+                ungrouped.chain(grouped)
+            })
+            .collect()
+    }
+
+    /// The groups of items found, if a [`GroupBy`] was requested.
+    pub fn groups(&self) -> Vec<&GroupedItems> {
+        self.iter_root_folders()
+            .flat_map(RootFolder::grouped_items)
+            .collect()
+    }
+}
+
+/// A request for the properties of one or more items.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/getitem>.
+pub struct GetItem {
+    /// The desired properties to include in the response.
+    item_shape: ItemShape,
+
+    /// Identifiers for the items to get.
+    item_ids: ItemIds,
+}
+
+impl GetItem {
+    /// Creates a new GetItem request object.
+    pub fn new(item_shape: ItemShape, item_ids: Vec<ItemId>) -> Self {
+        Self {
+            item_shape,
+            item_ids: ItemIds(item_ids),
+        }
+    }
+}
+
+impl<W: std::io::Write> EwsWrite<W> for GetItem {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        writer.write(
+            XmlEvent::start_element("GetItem")
+                .default_ns(MESSAGES_NS_URI)
+                .ns("t", TYPES_NS_URI),
+        )?;
+
+        self.item_shape.write(writer)?;
+
+        self.item_ids.write(writer)?;
+
+        writer.write(XmlEvent::end_element())
+    }
+
+    fn size_hint(&self) -> usize {
+        256 + EwsWrite::<W>::size_hint(&self.item_ids)
+    }
+}
+
+/// The response to a [`GetItem`] request.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/getitemresponse>.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct GetItemResponse {
+    response_messages: ResponseMessages,
+}
+
+impl HasResponseMessages for GetItemResponse {
+    fn response_messages(&self) -> &ResponseMessages {
+        &self.response_messages
+    }
+}
+
+impl CheckedResponse<GetItemResponse> {
+    /// The messages returned, in the order they were requested.
+    pub fn messages(&self) -> Vec<&Message> {
+        self.0
+            .response_messages
+            .items()
+            .flat_map(Items::messages)
+            .collect()
+    }
+}
+
+response_message! {
+    /// The response message for a single item in the response to an operation
+    /// on items, such as [`GetItem`] or [`MoveItem`].
+    ///
+    /// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/getitemresponsemessage>.
+    #[derive(Deserialize, Serialize)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct ItemInfoResponseMessage {
+        /// The item, only present if the response was successful.
+        items: Option<Items>,
+    }
+}
+
+/// A request for the content of one or more attachments.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/getattachment>.
+pub struct GetAttachment {
+    /// Identifiers for the attachments to get.
+    attachment_ids: Vec<AttachmentId>,
+}
+
+impl GetAttachment {
+    /// Creates a new GetAttachment request object.
+    pub fn new(attachment_ids: Vec<AttachmentId>) -> Self {
+        Self { attachment_ids }
+    }
+}
+
+impl<W: std::io::Write> EwsWrite<W> for GetAttachment {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        writer.write(
+            XmlEvent::start_element("GetAttachment")
+                .default_ns(MESSAGES_NS_URI)
+                .ns("t", TYPES_NS_URI),
+        )?;
+
+        writer.write(XmlEvent::start_element("AttachmentIds"))?;
+        self.attachment_ids.write(writer)?;
+        writer.write(XmlEvent::end_element())?;
+
+        writer.write(XmlEvent::end_element())
+    }
+
+    fn size_hint(&self) -> usize {
+        // Attachment IDs are long base64 strings, like item IDs.
+        256 + 256 * self.attachment_ids.len()
+    }
+}
+
+/// The response to a [`GetAttachment`] request.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/getattachmentresponse>.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct GetAttachmentResponse {
+    response_messages: ResponseMessages,
+}
+
+impl HasResponseMessages for GetAttachmentResponse {
+    fn response_messages(&self) -> &ResponseMessages {
+        &self.response_messages
+    }
+}
+
+impl CheckedResponse<GetAttachmentResponse> {
+    /// The attachments returned, with their content, in the order they were
+    /// requested.
+    pub fn attachments(&self) -> Vec<&Attachment> {
+        self.0
+            .response_messages
+            .contents
+            .iter()
+            .filter_map(|message| match message {
+                ResponseMessageContents::GetAttachmentResponseMessage(message) => {
+                    message.attachments.as_ref()
+                }
+                _ => None,
+            })
+            .flat_map(|attachments| attachments.attachments.iter())
+            .collect()
+    }
+}
+
+response_message! {
+    /// The response message for a single attachment in a
+    /// [`GetAttachmentResponse`].
+    ///
+    /// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/getattachmentresponsemessage>.
+    #[derive(Deserialize, Serialize)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct GetAttachmentResponseMessage {
+        /// The attachment, only present if the response was successful.
+        attachments: Option<Attachments>,
+    }
+}
+
+/// How items should be deleted.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/deleteitem#attributes>.
+#[derive(Clone, Copy)]
+pub enum DeleteType {
+    /// Permanently deletes the items.
+    HardDelete,
+
+    /// Moves the items to the dumpster, from which they can be recovered.
+    SoftDelete,
+
+    /// Moves the items to the Deleted Items folder.
+    MoveToDeletedItems,
+}
+
+impl From<DeleteType> for &str {
+    fn from(value: DeleteType) -> Self {
+        match value {
+            DeleteType::HardDelete => "HardDelete",
+            DeleteType::SoftDelete => "SoftDelete",
+            DeleteType::MoveToDeletedItems => "MoveToDeletedItems",
+        }
+    }
+}
+
+/// A request to delete items.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/deleteitem>.
+pub struct DeleteItem {
+    delete_type: DeleteType,
+
+    /// Identifiers for the items to delete.
+    item_ids: ItemIds,
+}
+
+impl DeleteItem {
+    /// Creates a new DeleteItem request object.
+    pub fn new(delete_type: DeleteType, item_ids: Vec<ItemId>) -> Self {
+        Self {
+            delete_type,
+            item_ids: ItemIds(item_ids),
+        }
+    }
+}
+
+impl<W: std::io::Write> EwsWrite<W> for DeleteItem {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        writer.write(
+            XmlEvent::start_element("DeleteItem")
+                .default_ns(MESSAGES_NS_URI)
+                .ns("t", TYPES_NS_URI)
+                .attr("DeleteType", self.delete_type.into()),
+        )?;
+
+        self.item_ids.write(writer)?;
+
+        writer.write(XmlEvent::end_element())
+    }
+
+    fn size_hint(&self) -> usize {
+        128 + EwsWrite::<W>::size_hint(&self.item_ids)
+    }
+}
+
+/// The response to a [`DeleteItem`] request.
+///
+/// There is a response message for each item, so checking the response tells
+/// whether all of them were deleted.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/deleteitemresponse>.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DeleteItemResponse {
+    response_messages: ResponseMessages,
+}
+
+impl HasResponseMessages for DeleteItemResponse {
+    fn response_messages(&self) -> &ResponseMessages {
+        &self.response_messages
+    }
+}
+
+response_message! {
+    /// The response message for a single item in a [`DeleteItemResponse`].
+    #[derive(Deserialize, Serialize)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct DeleteItemResponseMessage {}
+}
+
+/// A request to move items to another folder.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/moveitem>.
+pub struct MoveItem {
+    to_folder_id: FolderId,
+    item_ids: ItemIds,
+}
+
+impl MoveItem {
+    /// Creates a new MoveItem request object.
+    pub fn new(to_folder_id: FolderId, item_ids: Vec<ItemId>) -> Self {
+        Self {
+            to_folder_id,
+            item_ids: ItemIds(item_ids),
+        }
+    }
+}
+
+impl<W: std::io::Write> EwsWrite<W> for MoveItem {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        write_item_transfer(writer, "MoveItem", &self.to_folder_id, &self.item_ids)
+    }
+
+    fn size_hint(&self) -> usize {
+        256 + EwsWrite::<W>::size_hint(&self.item_ids)
+    }
+}
+
+/// A request to copy items to another folder.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/copyitem>.
+pub struct CopyItem {
+    to_folder_id: FolderId,
+    item_ids: ItemIds,
+}
+
+impl CopyItem {
+    /// Creates a new CopyItem request object.
+    pub fn new(to_folder_id: FolderId, item_ids: Vec<ItemId>) -> Self {
+        Self {
+            to_folder_id,
+            item_ids: ItemIds(item_ids),
+        }
+    }
+}
+
+impl<W: std::io::Write> EwsWrite<W> for CopyItem {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        write_item_transfer(writer, "CopyItem", &self.to_folder_id, &self.item_ids)
+    }
+
+    fn size_hint(&self) -> usize {
+        256 + EwsWrite::<W>::size_hint(&self.item_ids)
+    }
+}
+
+/// Writes a [`MoveItem`] or [`CopyItem`] request, which only differ by name.
+fn write_item_transfer<W: std::io::Write>(
+    writer: &mut xml::EventWriter<W>,
+    name: &str,
+    to_folder_id: &FolderId,
+    item_ids: &ItemIds,
+) -> Result<(), xml::writer::Error> {
+    writer.write(
+        XmlEvent::start_element(name)
+            .default_ns(MESSAGES_NS_URI)
+            .ns("t", TYPES_NS_URI),
+    )?;
+
+    writer.write(XmlEvent::start_element("ToFolderId"))?;
+    to_folder_id.write(writer)?;
+    writer.write(XmlEvent::end_element())?;
+
+    item_ids.write(writer)?;
+
+    writer.write(XmlEvent::end_element())
+}
+
+/// The response to a [`MoveItem`] request.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/moveitemresponse>.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct MoveItemResponse {
+    response_messages: ResponseMessages,
+}
+
+impl HasResponseMessages for MoveItemResponse {
+    fn response_messages(&self) -> &ResponseMessages {
+        &self.response_messages
+    }
+}
+
+impl CheckedResponse<MoveItemResponse> {
+    /// The new identifiers of the moved items, in the order they were
+    /// requested.
+    ///
+    /// Items moved to another mailbox don't have one.
+    pub fn item_ids(&self) -> Vec<&ItemId> {
+        self.0.response_messages.item_ids()
+    }
+}
+
+/// The response to a [`CopyItem`] request.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/copyitemresponse>.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CopyItemResponse {
+    response_messages: ResponseMessages,
+}
+
+impl HasResponseMessages for CopyItemResponse {
+    fn response_messages(&self) -> &ResponseMessages {
+        &self.response_messages
+    }
+}
+
+impl CheckedResponse<CopyItemResponse> {
+    /// The identifiers of the copies, in the order they were requested.
+    ///
+    /// Items copied to another mailbox don't have one.
+    pub fn item_ids(&self) -> Vec<&ItemId> {
+        self.0.response_messages.item_ids()
+    }
+}
+
+/// What to do with messages once they're created.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/createitem#attributes>.
+#[derive(Clone, Copy)]
+pub enum MessageDisposition {
+    /// Saves the message without sending it, e.g. as a draft.
+    SaveOnly,
+
+    /// Sends the message without keeping a copy.
+    SendOnly,
+
+    /// Sends the message and saves a copy, by default in the Sent Items
+    /// folder.
+    SendAndSaveCopy,
+}
+
+impl From<MessageDisposition> for &str {
+    fn from(value: MessageDisposition) -> Self {
+        match value {
+            MessageDisposition::SaveOnly => "SaveOnly",
+            MessageDisposition::SendOnly => "SendOnly",
+            MessageDisposition::SendAndSaveCopy => "SendAndSaveCopy",
+        }
+    }
+}
+
+/// A request to create items, e.g. to send messages.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/createitem>.
+pub struct CreateItem {
+    message_disposition: MessageDisposition,
+
+    /// The folder in which to save the items, if not the default for the
+    /// disposition.
+    saved_item_folder_id: Option<FolderId>,
+
+    items: Vec<EwsItem>,
+}
+
+impl CreateItem {
+    /// Creates a new CreateItem request object.
+    pub fn new(
+        message_disposition: MessageDisposition,
+        saved_item_folder_id: Option<FolderId>,
+        items: Vec<EwsItem>,
+    ) -> Self {
+        Self {
+            message_disposition,
+            saved_item_folder_id,
+            items,
+        }
+    }
+}
+
+impl<W: std::io::Write> EwsWrite<W> for CreateItem {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        writer.write(
+            XmlEvent::start_element("CreateItem")
+                .default_ns(MESSAGES_NS_URI)
+                .ns("t", TYPES_NS_URI)
+                .attr("MessageDisposition", self.message_disposition.into()),
+        )?;
+
+        if let Some(folder_id) = &self.saved_item_folder_id {
+            writer.write(XmlEvent::start_element("SavedItemFolderId"))?;
+            folder_id.write(writer)?;
+            writer.write(XmlEvent::end_element())?;
+        }
+
+        writer.write(XmlEvent::start_element("Items"))?;
+        self.items.write(writer)?;
+        writer.write(XmlEvent::end_element())?;
+
+        writer.write(XmlEvent::end_element())
+    }
+}
+
+/// The response to a [`CreateItem`] request.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/createitemresponse>.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CreateItemResponse {
+    response_messages: ResponseMessages,
+}
+
+impl HasResponseMessages for CreateItemResponse {
+    fn response_messages(&self) -> &ResponseMessages {
+        &self.response_messages
+    }
+}
+
+impl CheckedResponse<CreateItemResponse> {
+    /// The identifiers of the created items.
+    ///
+    /// Messages which were sent without saving a copy don't have one.
+    pub fn item_ids(&self) -> Vec<&ItemId> {
+        self.0.response_messages.item_ids()
+    }
+}
+
+/// What the server should do when an item has changed since its change key
+/// was obtained.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/updateitem#attributes>.
+#[derive(Clone, Copy)]
+pub enum ConflictResolution {
+    /// Fails the update if the item has changed.
+    NeverOverwrite,
+
+    /// Applies the update unless it conflicts with the changes made since.
+    AutoResolve,
+
+    /// Applies the update regardless of any changes made since.
+    AlwaysOverwrite,
+}
+
+impl From<ConflictResolution> for &str {
+    fn from(value: ConflictResolution) -> Self {
+        match value {
+            ConflictResolution::NeverOverwrite => "NeverOverwrite",
+            ConflictResolution::AutoResolve => "AutoResolve",
+            ConflictResolution::AlwaysOverwrite => "AlwaysOverwrite",
+        }
+    }
+}
+
+/// A change to a single property of an item.
+pub enum ItemUpdate {
+    /// Sets the property to its value in `message`, which should have no
+    /// other properties set.
+    ///
+    /// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/setitemfield>.
+    SetItemField {
+        field: FieldUri,
+        message: Box<Message>,
+    },
+
+    /// Removes the property from the item.
+    ///
+    /// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/deleteitemfield>.
+    DeleteItemField(FieldUri),
+}
+
+impl<W: std::io::Write> EwsWrite<W> for ItemUpdate {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        match self {
+            ItemUpdate::SetItemField { field, message } => {
+                writer.write(XmlEvent::start_element("t:SetItemField"))?;
+                field.write(writer)?;
+                message.write(writer)?;
+            }
+            ItemUpdate::DeleteItemField(field) => {
+                writer.write(XmlEvent::start_element("t:DeleteItemField"))?;
+                field.write(writer)?;
+            }
+        }
+
+        writer.write(XmlEvent::end_element())
+    }
+}
+
+/// The updates to make to one item, written as an `ItemChange` element.
+///
+/// Not to be confused with [`ItemChange`], a change reported by
+/// [`SyncFolderItems`].
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/itemchange>.
+pub struct UpdateItemChange {
+    item_id: ItemId,
+    updates: Vec<ItemUpdate>,
+}
+
+impl UpdateItemChange {
+    pub fn new(item_id: ItemId, updates: Vec<ItemUpdate>) -> Self {
+        Self { item_id, updates }
+    }
+}
+
+impl<W: std::io::Write> EwsWrite<W> for UpdateItemChange {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        writer.write(XmlEvent::start_element("t:ItemChange"))?;
+        self.item_id.write(writer)?;
+
+        writer.write(XmlEvent::start_element("t:Updates"))?;
+        self.updates.write(writer)?;
+        writer.write(XmlEvent::end_element())?;
+
+        writer.write(XmlEvent::end_element())
+    }
+}
+
+/// A request to change the properties of existing items.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/updateitem>.
+pub struct UpdateItem {
+    conflict_resolution: ConflictResolution,
+
+    /// What to do with messages once they are updated. The server requires
+    /// this when any of the items is a message.
+    message_disposition: Option<MessageDisposition>,
+
+    item_changes: Vec<UpdateItemChange>,
+}
+
+impl UpdateItem {
+    /// Creates a new UpdateItem request object.
+    pub fn new(
+        conflict_resolution: ConflictResolution,
+        item_changes: Vec<UpdateItemChange>,
+    ) -> Self {
+        Self {
+            conflict_resolution,
+            message_disposition: None,
+            item_changes,
+        }
+    }
+
+    /// Creates a request marking a message as read or unread.
+    pub fn mark_read(item_id: ItemId, read: bool) -> Self {
+        Self::new(
+            ConflictResolution::AutoResolve,
+            vec![UpdateItemChange::new(
+                item_id,
+                vec![ItemUpdate::SetItemField {
+                    field: FieldUri::MessageIsRead,
+                    message: Box::new(Message::default().with_is_read(read)),
+                }],
+            )],
+        )
+        .with_message_disposition(MessageDisposition::SaveOnly)
+    }
+
+    pub fn with_message_disposition(mut self, message_disposition: MessageDisposition) -> Self {
+        self.message_disposition = Some(message_disposition);
+        self
+    }
+}
+
+impl<W: std::io::Write> EwsWrite<W> for UpdateItem {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        let mut builder = XmlEvent::start_element("UpdateItem")
+            .default_ns(MESSAGES_NS_URI)
+            .ns("t", TYPES_NS_URI)
+            .attr("ConflictResolution", self.conflict_resolution.into());
+        if let Some(message_disposition) = self.message_disposition {
+            builder = builder.attr("MessageDisposition", message_disposition.into());
+        }
+        writer.write(builder)?;
+
+        writer.write(XmlEvent::start_element("ItemChanges"))?;
+        self.item_changes.write(writer)?;
+        writer.write(XmlEvent::end_element())?;
+
+        writer.write(XmlEvent::end_element())
+    }
+}
+
+/// The response to an [`UpdateItem`] request.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/updateitemresponse>.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct UpdateItemResponse {
+    response_messages: ResponseMessages,
+}
+
+impl HasResponseMessages for UpdateItemResponse {
+    fn response_messages(&self) -> &ResponseMessages {
+        &self.response_messages
+    }
+}
+
+impl CheckedResponse<UpdateItemResponse> {
+    /// The identifiers of the updated items, with their new change keys.
+    pub fn item_ids(&self) -> Vec<&ItemId> {
+        self.0.response_messages.item_ids()
+    }
+}
+
+/// The point from which the offset of a paged view is counted.
+#[derive(Clone, Copy)]
+pub enum BasePoint {
+    Beginning,
+    End,
+}
+
+impl From<BasePoint> for &str {
+    fn from(value: BasePoint) -> Self {
+        match value {
+            BasePoint::Beginning => "Beginning",
+            BasePoint::End => "End",
+        }
+    }
+}
+
+/// A view describing which page of results to return.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/indexedpageitemview>.
+pub struct IndexedPageItemView {
+    /// The maximum number of results in the page, or all of them if `None`.
+    pub max_entries_returned: Option<u32>,
+
+    /// The number of results between the base point and the start of the page.
+    pub offset: u32,
+
+    pub base_point: BasePoint,
+}
+
+impl<W: std::io::Write> EwsWrite<W> for IndexedPageItemView {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        let max_entries_returned = self.max_entries_returned.map(|max| max.to_string());
+        let offset = self.offset.to_string();
+
+        let mut builder = XmlEvent::start_element("IndexedPageItemView");
+        if let Some(max_entries_returned) = &max_entries_returned {
+            builder = builder.attr("MaxEntriesReturned", max_entries_returned);
+        }
+        builder = builder
+            .attr("Offset", &offset)
+            .attr("BasePoint", self.base_point.into());
+
+        writer.write(builder)?;
+        writer.write(XmlEvent::end_element())
+    }
+}
+
+/// A request to list the conversations in a folder.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/findconversation>.
+pub struct FindConversation {
+    /// The page of conversations to return.
+    view: Option<IndexedPageItemView>,
+
+    /// Identifier for the folder in which to locate conversations.
+    parent_folder_id: FolderId,
+}
+
+impl FindConversation {
+    /// Creates a new FindConversation request object.
+    pub fn new(view: Option<IndexedPageItemView>, parent_folder_id: FolderId) -> Self {
+        Self {
+            view,
+            parent_folder_id,
+        }
+    }
+}
+
+impl<W: std::io::Write> EwsWrite<W> for FindConversation {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        writer.write(
+            XmlEvent::start_element("FindConversation")
+                .default_ns(MESSAGES_NS_URI)
+                .ns("t", TYPES_NS_URI),
+        )?;
+
+        self.view.write(writer)?;
+
+        writer.write(XmlEvent::start_element("ParentFolderId"))?;
+        self.parent_folder_id.write(writer)?;
+        writer.write(XmlEvent::end_element())?;
+
+        writer.write(XmlEvent::end_element())
+    }
+}
+
+response_message! {
+    /// The response to a [`FindConversation`] request.
+    ///
+    /// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/findconversationresponse>.
+    #[derive(Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct FindConversationResponse {
+        /// The conversations found, only present if the response was successful.
+        conversations: Option<Conversations>,
+    }
+}
+
+impl FindConversationResponse {
+    /// Checks that the server successfully processed the request.
+    pub fn into_result(self) -> Result<CheckedResponse<Self>, EwsResponseError> {
+        self.header().check()?;
+        Ok(CheckedResponse(self))
+    }
+}
+
+impl CheckedResponse<FindConversationResponse> {
+    pub fn conversations(&self) -> &[Conversation] {
+        self.0
+            .conversations
+            .as_ref()
+            .map_or(&[], |conversations| &conversations.conversations)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct Conversations {
+    #[serde(rename = "Conversation", default)]
+    conversations: Vec<Conversation>,
+}
+
+/// A summary of a conversation.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/conversation-ex15websvcsotherref>.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Conversation {
+    conversation_id: ConversationId,
+    conversation_topic: Option<String>,
+    message_count: Option<u32>,
+    unread_count: Option<u32>,
+}
+
+impl Conversation {
+    pub fn conversation_id(&self) -> &ConversationId {
+        &self.conversation_id
+    }
+
+    pub fn topic(&self) -> Option<&str> {
+        self.conversation_topic.as_deref()
+    }
+
+    pub fn message_count(&self) -> Option<u32> {
+        self.message_count
+    }
+
+    pub fn unread_count(&self) -> Option<u32> {
+        self.unread_count
+    }
+}
+
+/// A request to fetch the items making up one or more conversations.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/getconversationitems>.
+pub struct GetConversationItems {
+    /// The desired properties to include in the response.
+    item_shape: ItemShape,
+
+    /// Identifiers for the conversations to fetch.
+    conversation_ids: Vec<ConversationId>,
+}
+
+impl GetConversationItems {
+    /// Creates a new GetConversationItems request object.
+    pub fn new(item_shape: ItemShape, conversation_ids: Vec<ConversationId>) -> Self {
+        Self {
+            item_shape,
+            conversation_ids,
+        }
+    }
+}
+
+impl<W: std::io::Write> EwsWrite<W> for GetConversationItems {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        writer.write(
+            XmlEvent::start_element("GetConversationItems")
+                .default_ns(MESSAGES_NS_URI)
+                .ns("t", TYPES_NS_URI),
+        )?;
+
+        self.item_shape.write(writer)?;
+
+        writer.write(XmlEvent::start_element("Conversations"))?;
+        for id in self.conversation_ids.iter() {
+            writer.write(XmlEvent::start_element("t:Conversation"))?;
+            id.write(writer)?;
+            writer.write(XmlEvent::end_element())?;
+        }
+        writer.write(XmlEvent::end_element())?;
+
+        writer.write(XmlEvent::end_element())
+    }
+}
+
+/// The response to a [`GetConversationItems`] request.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/getconversationitemsresponse>.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct GetConversationItemsResponse {
+    response_messages: ResponseMessages,
+}
+
+impl HasResponseMessages for GetConversationItemsResponse {
+    fn response_messages(&self) -> &ResponseMessages {
+        &self.response_messages
+    }
+}
+
+impl CheckedResponse<GetConversationItemsResponse> {
+    pub fn conversations(&self) -> Vec<&ConversationItems> {
+        self.0
+            .response_messages
+            .contents
+            .iter()
+            .filter_map(|message| match message {
+                ResponseMessageContents::GetConversationItemsResponseMessage(message) => {
+                    message.conversation.as_ref()
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+response_message! {
+    #[derive(Deserialize, Serialize)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct GetConversationItemsResponseMessage {
+        /// The conversation's items, only present if the response was successful.
+        conversation: Option<ConversationItems>,
+    }
+}
+
+/// The items in a conversation, arranged as nodes in the conversation tree.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/conversation-conversationresponsetype>.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ConversationItems {
+    conversation_id: ConversationId,
+    sync_state: Option<String>,
+    conversation_nodes: ConversationNodes,
+}
+
+impl ConversationItems {
+    pub fn conversation_id(&self) -> &ConversationId {
+        &self.conversation_id
+    }
+
+    pub fn sync_state(&self) -> Option<&str> {
+        self.sync_state.as_deref()
+    }
+
+    pub fn nodes(&self) -> &[ConversationNode] {
+        &self.conversation_nodes.nodes
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ConversationNodes {
+    #[serde(rename = "ConversationNode", default)]
+    nodes: Vec<ConversationNode>,
+}
+
+/// A single node in a conversation tree, holding the items for one message.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/conversationnode>.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ConversationNode {
+    internet_message_id: Option<String>,
+    parent_internet_message_id: Option<String>,
+    items: Items,
+}
+
+impl ConversationNode {
+    pub fn internet_message_id(&self) -> Option<&str> {
+        self.internet_message_id.as_deref()
+    }
+
+    pub fn parent_internet_message_id(&self) -> Option<&str> {
+        self.parent_internet_message_id.as_deref()
+    }
+
+    pub fn messages(&self) -> Vec<&Message> {
+        self.items
+            .items
+            .iter()
+            .map(|item| match item {
+                EwsItem::Message(message) => message,
+            })
+            .collect()
+    }
+}
+
+/// A request for the events which occurred on a pull subscription since the
+/// given watermark.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/getevents>.
+pub struct GetEvents {
+    /// The identifier of the pull subscription, as returned by the server
+    /// when subscribing.
+    subscription_id: String,
+
+    /// The watermark of the last event already processed.
+    watermark: String,
+}
+
+impl GetEvents {
+    /// Creates a new GetEvents request object.
+    pub fn new(subscription_id: &str, watermark: &str) -> Self {
+        Self {
+            subscription_id: subscription_id.to_string(),
+            watermark: watermark.to_string(),
+        }
+    }
+}
+
+impl<W: std::io::Write> EwsWrite<W> for GetEvents {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        writer.write(
+            XmlEvent::start_element("GetEvents")
+                .default_ns(MESSAGES_NS_URI)
+                .ns("t", TYPES_NS_URI),
+        )?;
+
+        write_text_element(writer, "SubscriptionId", &self.subscription_id)?;
+        write_text_element(writer, "Watermark", &self.watermark)?;
+
+        writer.write(XmlEvent::end_element())
+    }
+}
+
+/// The response to a [`GetEvents`] request.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/geteventsresponse>.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct GetEventsResponse {
+    response_messages: ResponseMessages,
+}
+
+impl HasResponseMessages for GetEventsResponse {
+    fn response_messages(&self) -> &ResponseMessages {
+        &self.response_messages
+    }
+}
+
+impl CheckedResponse<GetEventsResponse> {
+    pub fn notifications(&self) -> Vec<&Notification> {
+        self.0
+            .response_messages
+            .contents
+            .iter()
+            .filter_map(|message| match message {
+                ResponseMessageContents::GetEventsResponseMessage(message) => {
+                    message.notification.as_ref()
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+response_message! {
+    #[derive(Deserialize, Serialize)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct GetEventsResponseMessage {
+        /// The batch of events, only present if the response was successful.
+        notification: Option<Notification>,
+    }
+}
+
+/// A batch of events from a subscription.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/notification-ex15websvcsotherref>.
+#[derive(Deserialize, Serialize)]
+pub struct Notification {
+    #[serde(rename = "$value", default)]
+    contents: Vec<NotificationContents>,
+}
+
+impl Notification {
+    pub fn subscription_id(&self) -> Option<&str> {
+        self.contents.iter().find_map(|contents| match contents {
+            NotificationContents::SubscriptionId(id) => Some(id.as_str()),
+            _ => None,
+        })
+    }
+
+    /// The watermark the batch starts from.
+    pub fn previous_watermark(&self) -> Option<&str> {
+        self.contents.iter().find_map(|contents| match contents {
+            NotificationContents::PreviousWatermark(watermark) => Some(watermark.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Whether more events are waiting on the server, in which case another
+    /// request should be sent right away.
+    pub fn more_events(&self) -> bool {
+        self.contents.iter().any(|contents| match contents {
+            NotificationContents::MoreEvents(more_events) => *more_events,
+            _ => false,
+        })
+    }
+
+    /// The events in the batch, in the order they occurred.
+    pub fn events(&self) -> Vec<(EventKind, &Event)> {
+        self.contents
+            .iter()
+            .filter_map(|contents| {
+                let (kind, event) = match contents {
+                    NotificationContents::CopiedEvent(event) => (EventKind::Copied, event),
+                    NotificationContents::CreatedEvent(event) => (EventKind::Created, event),
+                    NotificationContents::DeletedEvent(event) => (EventKind::Deleted, event),
+                    NotificationContents::ModifiedEvent(event) => (EventKind::Modified, event),
+                    NotificationContents::MovedEvent(event) => (EventKind::Moved, event),
+                    NotificationContents::NewMailEvent(event) => (EventKind::NewMail, event),
+                    NotificationContents::StatusEvent(event) => (EventKind::Status, event),
+                    NotificationContents::FreeBusyChangedEvent(event) => {
+                        (EventKind::FreeBusyChanged, event)
+                    }
+                    NotificationContents::SubscriptionId(_)
+                    | NotificationContents::PreviousWatermark(_)
+                    | NotificationContents::MoreEvents(_) => return None,
+                };
+
+                Some((kind, event))
+            })
+            .collect()
+    }
+
+    /// The watermark to send in the next [`GetEvents`] request, i.e. the one
+    /// of the last event in the batch.
+    pub fn watermark(&self) -> Option<&str> {
+        self.events()
+            .last()
+            .map(|(_, event)| event.watermark.as_str())
+            .or(self.previous_watermark())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub enum NotificationContents {
+    SubscriptionId(String),
+    PreviousWatermark(String),
+    MoreEvents(bool),
+    CopiedEvent(Event),
+    CreatedEvent(Event),
+    DeletedEvent(Event),
+    ModifiedEvent(Event),
+    MovedEvent(Event),
+    NewMailEvent(Event),
+    StatusEvent(Event),
+    FreeBusyChangedEvent(Event),
+}
+
+/// The kind of change an [`Event`] reports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventKind {
+    Copied,
+    Created,
+    Deleted,
+    Modified,
+    Moved,
+    NewMail,
+
+    /// A keep-alive event, which doesn't relate to any item.
+    Status,
+
+    FreeBusyChanged,
+}
+
+/// A change which occurred in the mailbox.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/createdevent>.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Event {
+    watermark: String,
+    time_stamp: Option<DateTime>,
+
+    /// The item the event relates to, if it isn't about a folder.
+    item_id: Option<ItemId>,
+}
+
+impl Event {
+    pub fn watermark(&self) -> &str {
+        &self.watermark
+    }
+
+    pub fn time_stamp(&self) -> Option<&DateTime> {
+        self.time_stamp.as_ref()
+    }
+
+    pub fn item_id(&self) -> Option<&ItemId> {
+        self.item_id.as_ref()
+    }
+}
+
+/// A request for the changes to the items in a folder since a previous sync.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/syncfolderitems>.
+pub struct SyncFolderItems {
+    item_shape: ItemShape,
+
+    /// The folder to sync.
+    sync_folder_id: FolderId,
+
+    /// The state returned by the previous sync, if any. Without one, every
+    /// item in the folder is reported as created.
+    sync_state: Option<String>,
+
+    /// The maximum number of changes to return, between 1 and 512.
+    max_changes_returned: u32,
+}
+
+impl SyncFolderItems {
+    /// Creates a new SyncFolderItems request object.
+    pub fn new(item_shape: ItemShape, sync_folder_id: FolderId, max_changes_returned: u32) -> Self {
+        Self {
+            item_shape,
+            sync_folder_id,
+            sync_state: None,
+            max_changes_returned,
+        }
+    }
+
+    /// Requests only the changes since the sync which returned the given
+    /// state.
+    pub fn with_sync_state(mut self, sync_state: &str) -> Self {
+        self.sync_state = Some(sync_state.to_string());
+        self
+    }
+}
+
+impl<W: std::io::Write> EwsWrite<W> for SyncFolderItems {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        writer.write(
+            XmlEvent::start_element("SyncFolderItems")
+                .default_ns(MESSAGES_NS_URI)
+                .ns("t", TYPES_NS_URI),
+        )?;
+
+        self.item_shape.write(writer)?;
+
+        writer.write(XmlEvent::start_element("SyncFolderId"))?;
+        self.sync_folder_id.write(writer)?;
+        writer.write(XmlEvent::end_element())?;
+
+        if let Some(sync_state) = &self.sync_state {
+            write_text_element(writer, "SyncState", sync_state)?;
+        }
+
+        write_text_element(writer, "MaxChangesReturned", self.max_changes_returned)?;
+
+        writer.write(XmlEvent::end_element())
+    }
+}
+
+/// The response to a [`SyncFolderItems`] request.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/syncfolderitemsresponse>.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SyncFolderItemsResponse {
+    response_messages: ResponseMessages,
+}
+
+impl HasResponseMessages for SyncFolderItemsResponse {
+    fn response_messages(&self) -> &ResponseMessages {
+        &self.response_messages
+    }
+}
+
+impl CheckedResponse<SyncFolderItemsResponse> {
+    /// The results of the sync, along with the state to resume it from.
+    pub fn results(&self) -> Vec<&SyncFolderItemsResponseMessage> {
+        self.0
+            .response_messages
+            .contents
+            .iter()
+            .filter_map(|message| match message {
+                ResponseMessageContents::SyncFolderItemsResponseMessage(message) => Some(message),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+response_message! {
+    #[derive(Deserialize, Serialize)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct SyncFolderItemsResponseMessage {
+        sync_state: Option<String>,
+        includes_last_item_in_range: Option<bool>,
+        changes: Option<ItemChanges>,
+    }
+}
+
+impl SyncFolderItemsResponseMessage {
+    /// The state to send in the next [`SyncFolderItems`] request to get only
+    /// the changes since this one.
+    pub fn sync_state(&self) -> Option<&str> {
+        self.sync_state.as_deref()
+    }
+
+    /// Whether all changes have been returned. If not, another request should
+    /// be sent with the new sync state to get the rest.
+    pub fn includes_last_item_in_range(&self) -> bool {
+        self.includes_last_item_in_range.unwrap_or(true)
+    }
+
+    /// The changes to the folder's items, in the order they occurred.
+    pub fn changes(&self) -> &[ItemChange] {
+        self.changes
+            .as_ref()
+            .map(|changes| changes.changes.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ItemChanges {
+    #[serde(rename = "$value", default)]
+    changes: Vec<ItemChange>,
+}
+
+/// A change to an item reported by [`SyncFolderItems`].
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/changes-items>.
+#[derive(Deserialize, Serialize)]
+pub enum ItemChange {
+    Create(ChangedItem),
+    Update(ChangedItem),
+    Delete(DeletedItem),
+    ReadFlagChange(ReadFlagChange),
+}
+
+/// An item which was created or updated, with the properties requested in
+/// the [`ItemShape`].
+#[derive(Deserialize, Serialize)]
+pub struct ChangedItem {
+    #[serde(rename = "$value")]
+    item: EwsItem,
+}
+
+impl ChangedItem {
+    pub fn item(&self) -> &EwsItem {
+        &self.item
+    }
+}
+
+/// An item which was deleted, of which only the identifier remains.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DeletedItem {
+    item_id: ItemId,
+}
+
+impl DeletedItem {
+    pub fn item_id(&self) -> &ItemId {
+        &self.item_id
+    }
+}
+
+/// An item which was marked as read or unread.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ReadFlagChange {
+    item_id: ItemId,
+    is_read: bool,
+}
+
+impl ReadFlagChange {
+    pub fn item_id(&self) -> &ItemId {
+        &self.item_id
+    }
+
+    pub fn is_read(&self) -> bool {
+        self.is_read
+    }
+}
+
+/// A folder in a mailbox.
+///
+/// Only the properties requested in the [`FolderShape`] are present.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/folder>.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Folder {
+    #[serde(skip_serializing)]
+    folder_id: Option<FolderId>,
+
+    #[serde(skip_serializing)]
+    parent_folder_id: Option<FolderId>,
+
+    /// The kind of items the folder holds, e.g. "IPF.Note" for messages.
+    folder_class: Option<String>,
+
+    display_name: Option<String>,
+
+    /// The number of items in the folder.
+    total_count: Option<u32>,
+
+    child_folder_count: Option<u32>,
+
+    /// The number of unread items in the folder. Only sent for folders
+    /// holding messages.
+    unread_count: Option<u32>,
+}
+
+impl Folder {
+    pub fn folder_id(&self) -> Option<&FolderId> {
+        self.folder_id.as_ref()
+    }
+
+    pub fn parent_folder_id(&self) -> Option<&FolderId> {
+        self.parent_folder_id.as_ref()
+    }
+
+    pub fn folder_class(&self) -> Option<&str> {
+        self.folder_class.as_deref()
+    }
+
+    pub fn display_name(&self) -> Option<&str> {
+        self.display_name.as_deref()
+    }
+
+    pub fn total_count(&self) -> Option<u32> {
+        self.total_count
+    }
+
+    pub fn child_folder_count(&self) -> Option<u32> {
+        self.child_folder_count
+    }
+
+    pub fn unread_count(&self) -> Option<u32> {
+        self.unread_count
+    }
+}
+
+/// A folder of any kind.
+///
+/// Variants are tagged by element name, like [`EwsItem`]'s. The specialized
+/// folder kinds share the properties we model, so they all hold a [`Folder`].
+#[derive(Deserialize, Serialize)]
+pub enum EwsFolder {
+    Folder(Folder),
+    CalendarFolder(Folder),
+    ContactsFolder(Folder),
+    SearchFolder(Folder),
+    TasksFolder(Folder),
+}
+
+impl EwsFolder {
+    pub fn folder(&self) -> &Folder {
+        match self {
+            EwsFolder::Folder(folder)
+            | EwsFolder::CalendarFolder(folder)
+            | EwsFolder::ContactsFolder(folder)
+            | EwsFolder::SearchFolder(folder)
+            | EwsFolder::TasksFolder(folder) => folder,
+        }
+    }
+}
+
+/// A request for the changes to the folder tree of a mailbox since a previous
+/// sync.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/syncfolderhierarchy>.
+pub struct SyncFolderHierarchy {
+    folder_shape: FolderShape,
+
+    /// The folder whose subfolders to sync. Without one, the whole mailbox is
+    /// synced.
+    sync_folder_id: Option<FolderId>,
+
+    /// The state returned by the previous sync, if any. Without one, every
+    /// folder is reported as created.
+    sync_state: Option<String>,
+}
+
+impl SyncFolderHierarchy {
+    /// Creates a new SyncFolderHierarchy request object.
+    pub fn new(folder_shape: FolderShape) -> Self {
+        Self {
+            folder_shape,
+            sync_folder_id: None,
+            sync_state: None,
+        }
+    }
+
+    /// Limits the sync to the subfolders of the given folder.
+    pub fn with_sync_folder_id(mut self, sync_folder_id: FolderId) -> Self {
+        self.sync_folder_id = Some(sync_folder_id);
+        self
+    }
+
+    /// Requests only the changes since the sync which returned the given
+    /// state.
+    pub fn with_sync_state(mut self, sync_state: &str) -> Self {
+        self.sync_state = Some(sync_state.to_string());
+        self
+    }
+}
+
+impl<W: std::io::Write> EwsWrite<W> for SyncFolderHierarchy {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        writer.write(
+            XmlEvent::start_element("SyncFolderHierarchy")
+                .default_ns(MESSAGES_NS_URI)
+                .ns("t", TYPES_NS_URI),
+        )?;
+
+        self.folder_shape.write(writer)?;
+
+        if let Some(sync_folder_id) = &self.sync_folder_id {
+            writer.write(XmlEvent::start_element("SyncFolderId"))?;
+            sync_folder_id.write(writer)?;
+            writer.write(XmlEvent::end_element())?;
+        }
+
+        if let Some(sync_state) = &self.sync_state {
+            write_text_element(writer, "SyncState", sync_state)?;
+        }
+
+        writer.write(XmlEvent::end_element())
+    }
+}
+
+/// The response to a [`SyncFolderHierarchy`] request.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/syncfolderhierarchyresponse>.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SyncFolderHierarchyResponse {
+    response_messages: ResponseMessages,
+}
+
+impl HasResponseMessages for SyncFolderHierarchyResponse {
+    fn response_messages(&self) -> &ResponseMessages {
+        &self.response_messages
+    }
+}
+
+impl CheckedResponse<SyncFolderHierarchyResponse> {
+    /// The results of the sync, along with the state to resume it from.
+    pub fn results(&self) -> Vec<&SyncFolderHierarchyResponseMessage> {
+        self.0
+            .response_messages
+            .contents
+            .iter()
+            .filter_map(|message| match message {
+                ResponseMessageContents::SyncFolderHierarchyResponseMessage(message) => {
+                    Some(message)
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+response_message! {
+    #[derive(Deserialize, Serialize)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct SyncFolderHierarchyResponseMessage {
+        sync_state: Option<String>,
+        includes_last_folder_in_range: Option<bool>,
+        changes: Option<FolderChanges>,
+    }
+}
+
+impl SyncFolderHierarchyResponseMessage {
+    /// The state to send in the next [`SyncFolderHierarchy`] request to get
+    /// only the changes since this one.
+    pub fn sync_state(&self) -> Option<&str> {
+        self.sync_state.as_deref()
+    }
+
+    /// Whether all changes have been returned. If not, another request should
+    /// be sent with the new sync state to get the rest.
+    pub fn includes_last_folder_in_range(&self) -> bool {
+        self.includes_last_folder_in_range.unwrap_or(true)
+    }
+
+    /// The changes to the folder tree, in the order they occurred.
+    pub fn changes(&self) -> &[FolderChange] {
+        self.changes
+            .as_ref()
+            .map(|changes| changes.changes.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct FolderChanges {
+    #[serde(rename = "$value", default)]
+    changes: Vec<FolderChange>,
+}
+
+/// A change to a folder reported by [`SyncFolderHierarchy`].
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/changes-hierarchy>.
+#[derive(Deserialize, Serialize)]
+pub enum FolderChange {
+    Create(ChangedFolder),
+    Update(ChangedFolder),
+    Delete(DeletedFolder),
+}
+
+/// A folder which was created or updated, with the properties requested in
+/// the [`FolderShape`].
+#[derive(Deserialize, Serialize)]
+pub struct ChangedFolder {
+    #[serde(rename = "$value")]
+    folder: EwsFolder,
+}
+
+impl ChangedFolder {
+    pub fn folder(&self) -> &EwsFolder {
+        &self.folder
+    }
+}
+
+/// A folder which was deleted, of which only the identifier remains.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DeletedFolder {
+    #[serde(skip_serializing)]
+    folder_id: FolderId,
+}
+
+impl DeletedFolder {
+    pub fn folder_id(&self) -> &FolderId {
+        &self.folder_id
+    }
+}
+
+/// A request to list the subfolders of one or more folders.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/findfolder>.
+pub struct FindFolder {
+    /// Whether to list only direct subfolders, or all descendants.
+    traversal: Traversal,
+
+    folder_shape: FolderShape,
+
+    /// The folders whose subfolders to list.
+    parent_folder_ids: Vec<FolderId>,
+}
+
+impl FindFolder {
+    /// Creates a new FindFolder request object.
+    pub fn new(
+        traversal: Traversal,
+        folder_shape: FolderShape,
+        parent_folder_ids: Vec<FolderId>,
+    ) -> Self {
+        Self {
+            traversal,
+            folder_shape,
+            parent_folder_ids,
+        }
+    }
+}
+
+impl<W: std::io::Write> EwsWrite<W> for FindFolder {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        writer.write(
+            XmlEvent::start_element("FindFolder")
+                .default_ns(MESSAGES_NS_URI)
+                .ns("t", TYPES_NS_URI)
+                .attr("Traversal", self.traversal.into()),
+        )?;
+
+        self.folder_shape.write(writer)?;
+
+        writer.write(XmlEvent::start_element("ParentFolderIds"))?;
+        self.parent_folder_ids.write(writer)?;
+        writer.write(XmlEvent::end_element())?;
+
+        writer.write(XmlEvent::end_element())
+    }
+}
+
+/// The response to a [`FindFolder`] request.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/findfolderresponse>.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct FindFolderResponse {
+    response_messages: ResponseMessages,
+}
+
+impl HasResponseMessages for FindFolderResponse {
+    fn response_messages(&self) -> &ResponseMessages {
+        &self.response_messages
+    }
+}
+
+impl CheckedResponse<FindFolderResponse> {
+    /// The results for each of the parent folders.
+    pub fn root_folders(&self) -> Vec<&RootFolder> {
+        self.0
+            .response_messages
+            .contents
+            .iter()
+            .filter_map(|message| match message {
+                ResponseMessageContents::FindFolderResponseMessage(message) => {
+                    message.root_folder.as_ref()
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The folders found under all of the parent folders.
+    pub fn folders(&self) -> Vec<&Folder> {
+        self.root_folders()
+            .into_iter()
+            .flat_map(RootFolder::folders)
+            .collect()
+    }
+}
+
+response_message! {
+    #[derive(Deserialize, Serialize)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct FindFolderResponseMessage {
+        /// The folders found, only present if the response was successful.
+        root_folder: Option<RootFolder>,
+    }
+}
+
+/// A request for the properties of one or more folders.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/getfolder>.
+pub struct GetFolder {
+    folder_shape: FolderShape,
+
+    /// The folders to get the properties of.
+    folder_ids: Vec<FolderId>,
+}
+
+impl GetFolder {
+    /// Creates a new GetFolder request object.
+    pub fn new(folder_shape: FolderShape, folder_ids: Vec<FolderId>) -> Self {
+        Self {
+            folder_ids,
+            folder_shape,
+        }
+    }
+}
+
+impl<W: std::io::Write> EwsWrite<W> for GetFolder {
+    fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), xml::writer::Error> {
+        writer.write(
+            XmlEvent::start_element("GetFolder")
+                .default_ns(MESSAGES_NS_URI)
+                .ns("t", TYPES_NS_URI),
+        )?;
+
+        self.folder_shape.write(writer)?;
+
+        writer.write(XmlEvent::start_element("FolderIds"))?;
+        self.folder_ids.write(writer)?;
+        writer.write(XmlEvent::end_element())?;
+
+        writer.write(XmlEvent::end_element())
+    }
+}
+
+/// The response to a [`GetFolder`] request.
+///
+/// See <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/getfolderresponse>.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct GetFolderResponse {
+    response_messages: ResponseMessages,
+}
+
+impl HasResponseMessages for GetFolderResponse {
+    fn response_messages(&self) -> &ResponseMessages {
+        &self.response_messages
+    }
+}
+
+impl CheckedResponse<GetFolderResponse> {
+    /// The requested folders, in the order they were requested.
+    pub fn folders(&self) -> Vec<&Folder> {
+        self.0
+            .response_messages
+            .contents
+            .iter()
+            .filter_map(|message| match message {
+                ResponseMessageContents::GetFolderResponseMessage(message) => {
+                    message.folders.as_ref()
+                }
+                _ => None,
+            })
+            .flat_map(|folders| folders.folders.iter().map(EwsFolder::folder))
+            .collect()
+    }
+}
+
+response_message! {
+    #[derive(Deserialize, Serialize)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct GetFolderResponseMessage {
+        /// The requested folder, only present if the response was successful.
+        folders: Option<Folders>,
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct Folders {
+    #[serde(rename = "$value", default)]
+    folders: Vec<EwsFolder>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes `value` inside a wrapper element declaring the namespaces it
+    /// expects to be able to use.
+    fn write_to_string<X: EwsWrite<Vec<u8>>>(value: &X) -> String {
+        let mut writer = xml::EventWriter::new(Vec::new());
+        writer
+            .write(
+                XmlEvent::start_element("Wrapper")
+                    .default_ns(MESSAGES_NS_URI)
+                    .ns("t", TYPES_NS_URI),
+            )
+            .unwrap();
+        value.write(&mut writer).expect("writing should succeed");
+        writer.write(XmlEvent::end_element()).unwrap();
+
+        String::from_utf8(writer.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn item_round_trips_through_element_name_tag() {
+        let item = EwsItem::Message(Message {
+            item_id: Some(ItemId {
+                id: "AAMkAD".to_string(),
+                change_key: "CQAAAB".to_string(),
+            }),
+            subject: Some("Quarterly report".to_string()),
+            ..Default::default()
+        });
+
+        let items: Items =
+            serde_xml_rs::from_str(&write_to_string(&item)).expect("parsing should succeed");
+        assert_eq!(items.items.len(), 1);
+
+        let EwsItem::Message(message) = &items.items[0];
+        let item_id = message.item_id().unwrap();
+        assert_eq!(item_id.id(), "AAMkAD");
+        assert_eq!(item_id.change_key(), "CQAAAB");
+        assert_eq!(message.subject(), Some("Quarterly report"));
+    }
+
+    #[test]
+    fn message_exposes_conversation_id() {
+        let message: Message = serde_xml_rs::from_str(
+            r#"<t:Message xmlns:t="http://schemas.microsoft.com/exchange/services/2006/types">
+                <t:ItemId Id="AAMkAD" ChangeKey="CQAAAB" />
+                <t:Subject>Re: Quarterly report</t:Subject>
+                <t:ConversationId Id="AAQkAD" />
+            </t:Message>"#,
+        )
+        .expect("parsing should succeed");
+
+        let conversation_id = message
+            .conversation_id()
+            .expect("conversation ID should be present");
+        assert_eq!(conversation_id.id(), "AAQkAD");
+        assert_eq!(conversation_id.change_key(), None);
+    }
+
+    #[test]
+    fn item_shape_requests_additional_properties() {
+        let shape = ItemShape {
+            base_shape: BaseShape::IdOnly,
+            additional_properties: Some(vec![
+                FieldUri::ItemBody,
+                FieldUri::ItemInternetMessageHeaders,
+            ]),
+        };
+
+        let xml = write_to_string(&shape);
+        assert!(
+            xml.contains(concat!(
+                "<ItemShape><t:BaseShape>IdOnly</t:BaseShape><t:AdditionalProperties>",
+                r#"<t:FieldURI FieldURI="item:Body" />"#,
+                r#"<t:FieldURI FieldURI="item:InternetMessageHeaders" />"#,
+                "</t:AdditionalProperties></ItemShape>"
+            )),
+            "unexpected output: {xml}"
+        );
+    }
+
+    #[test]
+    fn find_conversation_is_paged() {
+        let request = FindConversation::new(
+            Some(IndexedPageItemView {
+                max_entries_returned: Some(20),
+                offset: 0,
+                base_point: BasePoint::Beginning,
+            }),
+            FolderId::DistinguishedFolderId {
+                id: DistinguishedFolderIdName::Inbox,
+                change_key: None,
+                mailbox: None,
+            },
+        );
+
+        let xml = write_to_string(&request);
+        assert!(
+            xml.contains(concat!(
+                r#"<IndexedPageItemView MaxEntriesReturned="20" Offset="0" BasePoint="Beginning" />"#,
+                r#"<ParentFolderId><t:DistinguishedFolderId Id="inbox" /></ParentFolderId>"#,
+                "</FindConversation>"
+            )),
+            "unexpected output: {xml}"
+        );
+    }
+
+    #[test]
+    fn get_conversation_items_lists_conversations() {
+        let conversation_id: ConversationId =
+            serde_xml_rs::from_str(r#"<ConversationId Id="AAQkAD" />"#).unwrap();
+        let request = GetConversationItems::new(
+            ItemShape {
+                base_shape: BaseShape::IdOnly,
+                additional_properties: None,
+            },
+            vec![conversation_id],
+        );
+
+        let xml = write_to_string(&request);
+        assert!(
+            xml.contains(concat!(
+                "<ItemShape><t:BaseShape>IdOnly</t:BaseShape></ItemShape>",
+                r#"<Conversations><t:Conversation><t:ConversationId Id="AAQkAD" />"#,
+                "</t:Conversation></Conversations>"
+            )),
+            "unexpected output: {xml}"
+        );
+    }
+
+    #[test]
+    fn conversation_listing_is_parsed() {
+        let envelope: SoapEnvelope = serde_xml_rs::from_str(
+            r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/">
+                <s:Body>
+                    <m:FindConversationResponse ResponseClass="Success"
+                        xmlns:m="http://schemas.microsoft.com/exchange/services/2006/messages"
+                        xmlns:t="http://schemas.microsoft.com/exchange/services/2006/types">
+                        <m:ResponseCode>NoError</m:ResponseCode>
+                        <m:Conversations>
+                            <t:Conversation>
+                                <t:ConversationId Id="AAQkAD" />
+                                <t:ConversationTopic>Quarterly report</t:ConversationTopic>
+                                <t:MessageCount>3</t:MessageCount>
+                                <t:UnreadCount>1</t:UnreadCount>
+                            </t:Conversation>
+                            <t:Conversation>
+                                <t:ConversationId Id="AAQkAE" />
+                                <t:ConversationTopic>Lunch?</t:ConversationTopic>
+                                <t:MessageCount>1</t:MessageCount>
+                                <t:UnreadCount>0</t:UnreadCount>
+                            </t:Conversation>
+                        </m:Conversations>
+                    </m:FindConversationResponse>
+                </s:Body>
+            </s:Envelope>"#,
+        )
+        .expect("parsing should succeed");
+
+        let Response::FindConversationResponse(response) = envelope.body.contents else {
+            panic!("expected a FindConversationResponse");
+        };
+        let response = response
+            .into_result()
+            .expect("response should be successful");
+
+        let conversations = response.conversations();
+        assert_eq!(conversations.len(), 2);
+        assert_eq!(conversations[0].conversation_id().id(), "AAQkAD");
+        assert_eq!(conversations[0].topic(), Some("Quarterly report"));
+        assert_eq!(conversations[0].message_count(), Some(3));
+        assert_eq!(conversations[0].unread_count(), Some(1));
+        assert_eq!(conversations[1].topic(), Some("Lunch?"));
+    }
+
+    #[test]
+    fn conversation_items_are_parsed() {
+        let envelope: SoapEnvelope = serde_xml_rs::from_str(
+            r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/">
+                <s:Body>
+                    <m:GetConversationItemsResponse
+                        xmlns:m="http://schemas.microsoft.com/exchange/services/2006/messages"
+                        xmlns:t="http://schemas.microsoft.com/exchange/services/2006/types">
+                        <m:ResponseMessages>
+                            <m:GetConversationItemsResponseMessage ResponseClass="Success">
+                                <m:ResponseCode>NoError</m:ResponseCode>
+                                <m:Conversation>
+                                    <t:ConversationId Id="AAQkAD" />
+                                    <t:SyncState>H4sIAAAA</t:SyncState>
+                                    <t:ConversationNodes>
+                                        <t:ConversationNode>
+                                            <t:InternetMessageId>&lt;a@example.com&gt;</t:InternetMessageId>
+                                            <t:Items>
+                                                <t:Message>
+                                                    <t:ItemId Id="AAMkAD" ChangeKey="CQAAAB" />
+                                                    <t:Subject>Quarterly report</t:Subject>
+                                                </t:Message>
+                                            </t:Items>
+                                        </t:ConversationNode>
+                                    </t:ConversationNodes>
+                                </m:Conversation>
+                            </m:GetConversationItemsResponseMessage>
+                        </m:ResponseMessages>
+                    </m:GetConversationItemsResponse>
+                </s:Body>
+            </s:Envelope>"#,
+        )
+        .expect("parsing should succeed");
+
+        let Response::GetConversationItemsResponse(response) = envelope.body.contents else {
+            panic!("expected a GetConversationItemsResponse");
+        };
+        let response = response
+            .into_result()
+            .expect("response should be successful");
+
+        let conversations = response.conversations();
+        assert_eq!(conversations.len(), 1);
+        assert_eq!(conversations[0].conversation_id().id(), "AAQkAD");
+        assert_eq!(conversations[0].sync_state(), Some("H4sIAAAA"));
+
+        let nodes = conversations[0].nodes();
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0].internet_message_id(), Some("<a@example.com>"));
+        assert_eq!(nodes[0].parent_internet_message_id(), None);
+        assert_eq!(nodes[0].messages()[0].subject(), Some("Quarterly report"));
+    }
+
+    #[test]
+    fn error_response_cannot_be_read() {
+        let envelope: SoapEnvelope = serde_xml_rs::from_str(
+            r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/">
+                <s:Body>
+                    <m:FindItemResponse
+                        xmlns:m="http://schemas.microsoft.com/exchange/services/2006/messages">
+                        <m:ResponseMessages>
+                            <m:FindItemResponseMessage ResponseClass="Error">
+                                <m:MessageText>The specified folder could not be found in the store.</m:MessageText>
+                                <m:ResponseCode>ErrorFolderNotFound</m:ResponseCode>
+                                <m:DescriptiveLinkKey>0</m:DescriptiveLinkKey>
+                            </m:FindItemResponseMessage>
+                        </m:ResponseMessages>
+                    </m:FindItemResponse>
+                </s:Body>
+            </s:Envelope>"#,
+        )
+        .expect("parsing should succeed");
+
+        let Response::FindItemResponse(response) = envelope.body.contents else {
+            panic!("expected a FindItemResponse");
+        };
+
+        let err = match response.into_result() {
+            Ok(_) => panic!("error response should not pass the check"),
+            Err(err) => err,
+        };
+        assert_eq!(err.response_class(), ResponseClass::Error);
+        assert_eq!(
+            err.response_code(),
+            Some(&ResponseCode::ErrorFolderNotFound)
+        );
+        assert_eq!(
+            err.message_text(),
+            Some("The specified folder could not be found in the store.")
+        );
+    }
+
+    #[test]
+    fn response_errors_propagate_as_typed_errors() {
+        let envelope: SoapEnvelope = serde_xml_rs::from_str(
+            r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/">
+                <s:Body>
+                    <m:GetItemResponse
+                        xmlns:m="http://schemas.microsoft.com/exchange/services/2006/messages">
+                        <m:ResponseMessages>
+                            <m:GetItemResponseMessage ResponseClass="Error">
+                                <m:MessageText>The specified object was not found in the store.</m:MessageText>
+                                <m:ResponseCode>ErrorItemNotFound</m:ResponseCode>
+                                <m:DescriptiveLinkKey>0</m:DescriptiveLinkKey>
+                                <m:Items />
+                            </m:GetItemResponseMessage>
+                        </m:ResponseMessages>
+                    </m:GetItemResponse>
+                </s:Body>
+            </s:Envelope>"#,
+        )
+        .expect("parsing should succeed");
+
+        let Response::GetItemResponse(response) = envelope.body.contents else {
+            panic!("expected a GetItemResponse");
+        };
+
+        fn check(response: GetItemResponse) -> Result<(), EwsError> {
+            response.into_result()?;
+            Ok(())
+        }
+        assert!(matches!(check(response), Err(EwsError::ItemNotFound)));
+
+        // Without a code, there's nothing more specific to report.
+        let err = EwsError::from(EwsResponseError {
+            response_class: ResponseClass::Warning,
+            response_code: None,
+            message_text: None,
+        });
+        assert!(matches!(err, EwsError::Response(_)));
+    }
+
+    #[test]
+    fn unknown_response_messages_are_rejected() {
+        // A message we can't read the outcome of mustn't pass as a success.
+        let result = serde_xml_rs::from_str::<SoapEnvelope>(
+            r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/">
+                <s:Body>
+                    <m:FindItemResponse
+                        xmlns:m="http://schemas.microsoft.com/exchange/services/2006/messages">
+                        <m:ResponseMessages>
+                            <m:GetRemindersResponseMessage ResponseClass="Error">
+                                <m:ResponseCode>ErrorAccessDenied</m:ResponseCode>
+                            </m:GetRemindersResponseMessage>
+                        </m:ResponseMessages>
+                    </m:FindItemResponse>
+                </s:Body>
+            </s:Envelope>"#,
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn empty_response_messages_have_no_messages() {
+        let envelope: SoapEnvelope = serde_xml_rs::from_str(
+            r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/">
+                <s:Body>
+                    <m:FindItemResponse
+                        xmlns:m="http://schemas.microsoft.com/exchange/services/2006/messages">
+                        <m:ResponseMessages />
+                    </m:FindItemResponse>
+                </s:Body>
+            </s:Envelope>"#,
+        )
+        .expect("parsing should succeed");
+
+        let Response::FindItemResponse(response) = envelope.body.contents else {
+            panic!("expected a FindItemResponse");
+        };
+
+        let response = response
+            .into_result()
+            .expect("empty response should pass the check");
+        assert!(response.messages().is_empty());
+        assert!(response.root_folders().is_empty());
+    }
+
+    #[test]
+    fn custom_headers_are_written() {
+        let message = Message {
+            item_id: Some(ItemId {
+                id: "AAMkAD".to_string(),
+                change_key: "CQAAAB".to_string(),
+            }),
+            subject: Some("Re: Quarterly report".to_string()),
+            internet_message_headers: Some(InternetMessageHeaders {
+                headers: vec![
+                    InternetMessageHeader::new("X-Mailer", "Thunderbird"),
+                    InternetMessageHeader::new("References", "<a@example.com>"),
+                ],
+            }),
+            ..Default::default()
+        };
+
+        let xml = write_to_string(&message);
+        assert!(
+            xml.contains(concat!(
+                "<t:InternetMessageHeaders>",
+                r#"<t:InternetMessageHeader HeaderName="X-Mailer">Thunderbird</t:InternetMessageHeader>"#,
+                r#"<t:InternetMessageHeader HeaderName="References">&lt;a@example.com&gt;</t:InternetMessageHeader>"#,
+                "</t:InternetMessageHeaders>"
+            )),
+            "unexpected output: {xml}"
+        );
+
+        let items: Items = serde_xml_rs::from_str(&write_to_string(&EwsItem::Message(message)))
+            .expect("parsing should succeed");
+        let EwsItem::Message(message) = &items.items[0];
+        let headers = message.internet_message_headers();
+        assert_eq!(headers.len(), 2);
+        assert_eq!(headers[1].name(), "References");
+        assert_eq!(headers[1].value(), "<a@example.com>");
+    }
+
+    #[test]
+    fn get_events_sends_watermark() {
+        let xml = write_to_string(&GetEvents::new("dwBzAHUAYgA=", "AQAAAJ4="));
+        assert!(
+            xml.contains(concat!(
+                "<GetEvents><SubscriptionId>dwBzAHUAYgA=</SubscriptionId>",
+                "<Watermark>AQAAAJ4=</Watermark></GetEvents>"
+            )),
+            "unexpected output: {xml}"
+        );
+    }
+
+    #[test]
+    fn pull_notification_batch_advances_watermark() {
+        let envelope: SoapEnvelope = serde_xml_rs::from_str(
+            r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/">
+                <s:Body>
+                    <m:GetEventsResponse
+                        xmlns:m="http://schemas.microsoft.com/exchange/services/2006/messages"
+                        xmlns:t="http://schemas.microsoft.com/exchange/services/2006/types">
+                        <m:ResponseMessages>
+                            <m:GetEventsResponseMessage ResponseClass="Success">
+                                <m:ResponseCode>NoError</m:ResponseCode>
+                                <m:Notification>
+                                    <t:SubscriptionId>dwBzAHUAYgA=</t:SubscriptionId>
+                                    <t:PreviousWatermark>AQAAAJ4=</t:PreviousWatermark>
+                                    <t:MoreEvents>false</t:MoreEvents>
+                                    <t:CreatedEvent>
+                                        <t:Watermark>AQAAAJ8=</t:Watermark>
+                                        <t:TimeStamp>2024-02-29T10:00:00Z</t:TimeStamp>
+                                        <t:ItemId Id="AAMkAD" ChangeKey="CQAAAB" />
+                                        <t:ParentFolderId Id="AAMkAE" ChangeKey="AQAAAA" />
+                                    </t:CreatedEvent>
+                                    <t:NewMailEvent>
+                                        <t:Watermark>AQAAAKA=</t:Watermark>
+                                        <t:TimeStamp>2024-02-29T10:00:01Z</t:TimeStamp>
+                                        <t:ItemId Id="AAMkAD" ChangeKey="CQAAAB" />
+                                        <t:ParentFolderId Id="AAMkAE" ChangeKey="AQAAAA" />
+                                    </t:NewMailEvent>
+                                </m:Notification>
+                            </m:GetEventsResponseMessage>
+                        </m:ResponseMessages>
+                    </m:GetEventsResponse>
+                </s:Body>
+            </s:Envelope>"#,
+        )
+        .expect("parsing should succeed");
+
+        let Response::GetEventsResponse(response) = envelope.body.contents else {
+            panic!("expected a GetEventsResponse");
+        };
+        let response = response
+            .into_result()
+            .expect("response should be successful");
+
+        let notifications = response.notifications();
+        assert_eq!(notifications.len(), 1);
+
+        let notification = notifications[0];
+        assert_eq!(notification.subscription_id(), Some("dwBzAHUAYgA="));
+        assert_eq!(notification.previous_watermark(), Some("AQAAAJ4="));
+        assert!(!notification.more_events());
+
+        let events = notification.events();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].0, EventKind::Created);
+        assert_eq!(events[0].1.item_id().unwrap().id(), "AAMkAD");
+        assert_eq!(
+            events[0].1.time_stamp(),
+            Some(&DateTime::from_unix_timestamp(1_709_200_800))
+        );
+        assert_eq!(events[1].0, EventKind::NewMail);
+
+        assert_eq!(notification.watermark(), Some("AQAAAKA="));
+    }
+
+    #[test]
+    fn sync_folder_items_resumes_from_state() {
+        let request = SyncFolderItems::new(
+            ItemShape {
+                base_shape: BaseShape::IdOnly,
+                additional_properties: None,
+            },
+            FolderId::FolderId {
+                id: "AQMkAD".to_string(),
+                change_key: None,
+            },
+            100,
+        )
+        .with_sync_state("H4sIAAAA");
+
+        let xml = write_to_string(&request);
+        assert!(
+            xml.contains(concat!(
+                "<SyncFolderItems><ItemShape><t:BaseShape>IdOnly</t:BaseShape></ItemShape>",
+                r#"<SyncFolderId><t:FolderId Id="AQMkAD" /></SyncFolderId>"#,
+                "<SyncState>H4sIAAAA</SyncState>",
+                "<MaxChangesReturned>100</MaxChangesReturned></SyncFolderItems>"
+            )),
+            "unexpected output: {xml}"
+        );
+    }
+
+    #[test]
+    fn sync_folder_items_changes_are_parsed() {
+        let envelope: SoapEnvelope = serde_xml_rs::from_str(
+            r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/">
+                <s:Body>
+                    <m:SyncFolderItemsResponse
+                        xmlns:m="http://schemas.microsoft.com/exchange/services/2006/messages"
+                        xmlns:t="http://schemas.microsoft.com/exchange/services/2006/types">
+                        <m:ResponseMessages>
+                            <m:SyncFolderItemsResponseMessage ResponseClass="Success">
+                                <m:ResponseCode>NoError</m:ResponseCode>
+                                <m:SyncState>H4sIAAAB</m:SyncState>
+                                <m:IncludesLastItemInRange>false</m:IncludesLastItemInRange>
+                                <m:Changes>
+                                    <t:Create>
+                                        <t:Message>
+                                            <t:ItemId Id="AAMkAD" ChangeKey="CQAAAB" />
+                                            <t:Subject>Quarterly report</t:Subject>
+                                        </t:Message>
+                                    </t:Create>
+                                    <t:Delete>
+                                        <t:ItemId Id="AAMkAE" ChangeKey="CQAAAC" />
+                                    </t:Delete>
+                                    <t:ReadFlagChange>
+                                        <t:ItemId Id="AAMkAF" ChangeKey="CQAAAD" />
+                                        <t:IsRead>true</t:IsRead>
+                                    </t:ReadFlagChange>
+                                </m:Changes>
+                            </m:SyncFolderItemsResponseMessage>
+                        </m:ResponseMessages>
+                    </m:SyncFolderItemsResponse>
+                </s:Body>
+            </s:Envelope>"#,
+        )
+        .expect("parsing should succeed");
+
+        let Response::SyncFolderItemsResponse(response) = envelope.body.contents else {
+            panic!("expected a SyncFolderItemsResponse");
+        };
+        let response = response
+            .into_result()
+            .expect("response should be successful");
+
+        let results = response.results();
+        assert_eq!(results.len(), 1);
+
+        let result = results[0];
+        assert_eq!(result.sync_state(), Some("H4sIAAAB"));
+        assert!(!result.includes_last_item_in_range());
+
+        let changes = result.changes();
+        assert_eq!(changes.len(), 3);
+
+        let ItemChange::Create(created) = &changes[0] else {
+            panic!("expected the first change to be a creation");
+        };
+        let EwsItem::Message(message) = created.item();
+        assert_eq!(message.item_id().unwrap().id(), "AAMkAD");
+        assert_eq!(message.subject(), Some("Quarterly report"));
+
+        let ItemChange::Delete(deleted) = &changes[1] else {
+            panic!("expected the second change to be a deletion");
+        };
+        assert_eq!(deleted.item_id().id(), "AAMkAE");
+
+        let ItemChange::ReadFlagChange(read_flag_change) = &changes[2] else {
+            panic!("expected the third change to be a read flag change");
+        };
+        assert_eq!(read_flag_change.item_id().id(), "AAMkAF");
+        assert!(read_flag_change.is_read());
+    }
+
+    #[test]
+    fn sync_folder_hierarchy_is_written() {
+        let request = SyncFolderHierarchy::new(FolderShape {
+            base_shape: BaseShape::AllProperties,
+        })
+        .with_sync_state("H4sIAAAA");
+
+        let xml = write_to_string(&request);
+        assert!(
+            xml.contains(concat!(
+                "<SyncFolderHierarchy><FolderShape><t:BaseShape>AllProperties</t:BaseShape>",
+                "</FolderShape><SyncState>H4sIAAAA</SyncState></SyncFolderHierarchy>"
+            )),
+            "unexpected output: {xml}"
+        );
+    }
+
+    #[test]
+    fn sync_folder_hierarchy_changes_are_parsed() {
+        let envelope: SoapEnvelope = serde_xml_rs::from_str(
+            r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/">
+                <s:Body>
+                    <m:SyncFolderHierarchyResponse
+                        xmlns:m="http://schemas.microsoft.com/exchange/services/2006/messages"
+                        xmlns:t="http://schemas.microsoft.com/exchange/services/2006/types">
+                        <m:ResponseMessages>
+                            <m:SyncFolderHierarchyResponseMessage ResponseClass="Success">
+                                <m:ResponseCode>NoError</m:ResponseCode>
+                                <m:SyncState>H4sIAAAB</m:SyncState>
+                                <m:IncludesLastFolderInRange>true</m:IncludesLastFolderInRange>
+                                <m:Changes>
+                                    <t:Create>
+                                        <t:Folder>
+                                            <t:FolderId Id="AQMkAD" ChangeKey="AQAAAA" />
+                                            <t:ParentFolderId Id="AQMkAC" ChangeKey="AQAAAA" />
+                                            <t:FolderClass>IPF.Note</t:FolderClass>
+                                            <t:DisplayName>Inbox</t:DisplayName>
+                                            <t:TotalCount>12</t:TotalCount>
+                                            <t:ChildFolderCount>1</t:ChildFolderCount>
+                                            <t:UnreadCount>3</t:UnreadCount>
+                                        </t:Folder>
+                                    </t:Create>
+                                    <t:Create>
+                                        <t:CalendarFolder>
+                                            <t:FolderId Id="AQMkAE" ChangeKey="AgAAAA" />
+                                            <t:FolderClass>IPF.Appointment</t:FolderClass>
+                                            <t:DisplayName>Calendar</t:DisplayName>
+                                            <t:TotalCount>0</t:TotalCount>
+                                            <t:ChildFolderCount>0</t:ChildFolderCount>
+                                        </t:CalendarFolder>
+                                    </t:Create>
+                                </m:Changes>
+                            </m:SyncFolderHierarchyResponseMessage>
+                        </m:ResponseMessages>
+                    </m:SyncFolderHierarchyResponse>
+                </s:Body>
+            </s:Envelope>"#,
+        )
+        .expect("parsing should succeed");
+
+        let Response::SyncFolderHierarchyResponse(response) = envelope.body.contents else {
+            panic!("expected a SyncFolderHierarchyResponse");
+        };
+        let response = response
+            .into_result()
+            .expect("response should be successful");
+
+        let results = response.results();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].sync_state(), Some("H4sIAAAB"));
+        assert!(results[0].includes_last_folder_in_range());
+
+        let folders: Vec<_> = results[0]
+            .changes()
+            .iter()
+            .map(|change| match change {
+                FolderChange::Create(created) => created.folder().folder(),
+                _ => panic!("expected only creations"),
+            })
+            .collect();
+        assert_eq!(folders.len(), 2);
+
+        let inbox = folders[0];
+        assert!(matches!(
+            inbox.folder_id(),
+            Some(FolderId::FolderId { id, change_key: Some(change_key) })
+                if id == "AQMkAD" && change_key == "AQAAAA"
+        ));
+        assert_eq!(inbox.display_name(), Some("Inbox"));
+        assert_eq!(inbox.folder_class(), Some("IPF.Note"));
+        assert_eq!(inbox.total_count(), Some(12));
+        assert_eq!(inbox.child_folder_count(), Some(1));
+        assert_eq!(inbox.unread_count(), Some(3));
+
+        let calendar = folders[1];
+        assert_eq!(calendar.display_name(), Some("Calendar"));
+        assert_eq!(calendar.unread_count(), None);
+    }
+
+    #[test]
+    fn get_folder_lists_folder_ids() {
+        let request = GetFolder::new(
+            FolderShape {
+                base_shape: BaseShape::Default,
+            },
+            vec![
+                FolderId::DistinguishedFolderId {
+                    id: DistinguishedFolderIdName::Inbox,
+                    change_key: None,
+                    mailbox: None,
+                },
+                FolderId::FolderId {
+                    id: "AQMkAD".to_string(),
+                    change_key: Some("AQAAAA".to_string()),
+                },
+            ],
+        );
+
+        let xml = write_to_string(&request);
+        assert!(
+            xml.contains(concat!(
+                "<GetFolder><FolderShape><t:BaseShape>Default</t:BaseShape></FolderShape>",
+                r#"<FolderIds><t:DistinguishedFolderId Id="inbox" />"#,
+                r#"<t:FolderId Id="AQMkAD" ChangeKey="AQAAAA" /></FolderIds></GetFolder>"#
+            )),
+            "unexpected output: {xml}"
+        );
+    }
+
+    #[test]
+    fn get_folder_counts_are_parsed() {
+        let envelope: SoapEnvelope = serde_xml_rs::from_str(
+            r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/">
+                <s:Body>
+                    <m:GetFolderResponse
+                        xmlns:m="http://schemas.microsoft.com/exchange/services/2006/messages"
+                        xmlns:t="http://schemas.microsoft.com/exchange/services/2006/types">
+                        <m:ResponseMessages>
+                            <m:GetFolderResponseMessage ResponseClass="Success">
+                                <m:ResponseCode>NoError</m:ResponseCode>
+                                <m:Folders>
+                                    <t:Folder>
+                                        <t:FolderId Id="AQMkAD" ChangeKey="AQAAAA" />
+                                        <t:DisplayName>Inbox</t:DisplayName>
+                                        <t:TotalCount>12</t:TotalCount>
+                                        <t:ChildFolderCount>1</t:ChildFolderCount>
+                                        <t:UnreadCount>3</t:UnreadCount>
+                                    </t:Folder>
+                                </m:Folders>
+                            </m:GetFolderResponseMessage>
+                        </m:ResponseMessages>
+                    </m:GetFolderResponse>
+                </s:Body>
+            </s:Envelope>"#,
+        )
+        .expect("parsing should succeed");
+
+        let Response::GetFolderResponse(response) = envelope.body.contents else {
+            panic!("expected a GetFolderResponse");
+        };
+        let response = response
+            .into_result()
+            .expect("response should be successful");
+
+        let folders = response.folders();
+        assert_eq!(folders.len(), 1);
+        assert_eq!(folders[0].display_name(), Some("Inbox"));
+        assert_eq!(folders[0].total_count(), Some(12));
+        assert_eq!(folders[0].child_folder_count(), Some(1));
+        assert_eq!(folders[0].unread_count(), Some(3));
+    }
+
+    #[test]
+    fn find_folder_traverses_deeply() {
+        let request = FindFolder::new(
+            Traversal::Deep,
+            FolderShape {
+                base_shape: BaseShape::Default,
+            },
+            vec![FolderId::DistinguishedFolderId {
+                id: DistinguishedFolderIdName::MsgFolderRoot,
+                change_key: None,
+                mailbox: None,
+            }],
+        );
+
+        let xml = write_to_string(&request);
+        assert!(
+            xml.contains(concat!(
+                r#"<FindFolder Traversal="Deep"><FolderShape><t:BaseShape>Default</t:BaseShape>"#,
+                r#"</FolderShape><ParentFolderIds><t:DistinguishedFolderId Id="msgfolderroot" />"#,
+                "</ParentFolderIds></FindFolder>"
+            )),
+            "unexpected output: {xml}"
+        );
+    }
+
+    #[test]
+    fn find_folder_results_are_parsed() {
+        let envelope: SoapEnvelope = serde_xml_rs::from_str(
+            r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/">
+                <s:Body>
+                    <m:FindFolderResponse
+                        xmlns:m="http://schemas.microsoft.com/exchange/services/2006/messages"
+                        xmlns:t="http://schemas.microsoft.com/exchange/services/2006/types">
+                        <m:ResponseMessages>
+                            <m:FindFolderResponseMessage ResponseClass="Success">
+                                <m:ResponseCode>NoError</m:ResponseCode>
+                                <m:RootFolder TotalItemsInView="2" IncludesLastItemInRange="true">
+                                    <t:Folders>
+                                        <t:Folder>
+                                            <t:FolderId Id="AQMkAD" ChangeKey="AQAAAA" />
+                                            <t:DisplayName>Inbox</t:DisplayName>
+                                        </t:Folder>
+                                        <t:ContactsFolder>
+                                            <t:FolderId Id="AQMkAE" ChangeKey="AwAAAA" />
+                                            <t:DisplayName>Contacts</t:DisplayName>
+                                        </t:ContactsFolder>
+                                    </t:Folders>
+                                </m:RootFolder>
+                            </m:FindFolderResponseMessage>
+                        </m:ResponseMessages>
+                    </m:FindFolderResponse>
+                </s:Body>
+            </s:Envelope>"#,
+        )
+        .expect("parsing should succeed");
+
+        let Response::FindFolderResponse(response) = envelope.body.contents else {
+            panic!("expected a FindFolderResponse");
+        };
+        let response = response
+            .into_result()
+            .expect("response should be successful");
+
+        assert_eq!(response.root_folders()[0].total_items_in_view(), Some(2));
+
+        let folders = response.folders();
+        let names: Vec<_> = folders.iter().map(|folder| folder.display_name()).collect();
+        assert_eq!(names, [Some("Inbox"), Some("Contacts")]);
+        assert!(matches!(
+            folders[1].folder_id(),
+            Some(FolderId::FolderId { id, .. }) if id == "AQMkAE"
+        ));
+    }
+
+    #[test]
+    fn mime_content_is_base64_encoded() {
+        let content = b"Subject: =?ISO-8859-1?Q?caf=E9?=\r\n\r\ncaf\xe9\r\n".to_vec();
+        let mime_content = MimeContent::new(content.clone(), Some("ISO-8859-1"));
+
+        let xml = write_to_string(&mime_content);
+        let expected = format!(
+            r#"<t:MimeContent CharacterSet="ISO-8859-1">{}</t:MimeContent>"#,
+            BASE64_STANDARD.encode(&content)
+        );
+        assert!(xml.contains(&expected), "unexpected output: {xml}");
+
+        let parsed: MimeContent = serde_xml_rs::from_str(&format!(
+            r#"<MimeContent CharacterSet="ISO-8859-1">{}</MimeContent>"#,
+            BASE64_STANDARD.encode(&content)
+        ))
+        .unwrap();
+        assert_eq!(parsed.character_set(), Some("ISO-8859-1"));
+        assert_eq!(parsed.content(), content);
+    }
+
+    #[test]
+    fn response_code_maps_to_error() {
+        let code: ResponseCode =
+            serde_xml_rs::from_str("<ResponseCode>ErrorItemNotFound</ResponseCode>").unwrap();
+        assert!(matches!(code.to_result(), Err(EwsError::ItemNotFound)));
+
+        assert!(ResponseCode::NoError.to_result().is_ok());
+        assert!(matches!(
+            ResponseCode::from("ErrorQuotaExceeded").to_result(),
+            Err(EwsError::Other(code)) if code == "ErrorQuotaExceeded"
+        ));
+    }
+
+    #[test]
+    fn body_type_is_case_insensitive() {
+        #[derive(Deserialize)]
+        struct Body {
+            #[serde(rename = "BodyType")]
+            body_type: BodyType,
+        }
+
+        for (value, expected) in [
+            ("html", BodyType::Html),
+            ("HTML", BodyType::Html),
+            ("Text", BodyType::Text),
+            ("TEXT", BodyType::Text),
+        ] {
+            let body: Body =
+                serde_xml_rs::from_str(&format!(r#"<Body BodyType="{value}">Hi</Body>"#)).unwrap();
+            assert_eq!(body.body_type, expected);
+        }
+
+        assert!(serde_xml_rs::from_str::<Body>(r#"<Body BodyType="rtf">Hi</Body>"#).is_err());
+
+        // We always write the documented casing.
+        assert_eq!(<&str>::from(BodyType::Html), "HTML");
+        assert_eq!(<&str>::from(BodyType::Text), "Text");
+    }
+
+    #[test]
+    fn find_item_groups_by_sender() {
+        let find_item = FindItem::new(
+            Traversal::Shallow,
+            ItemShape {
+                base_shape: BaseShape::IdOnly,
+                additional_properties: None,
+            },
+            vec![],
+        )
+        .with_group_by(GroupBy {
+            order: SortDirection::Descending,
+            field: FieldUri::MessageFrom,
+            aggregate: Aggregate::Maximum,
+            aggregate_on: FieldUri::ItemDateTimeReceived,
+        });
+
+        let xml = write_to_string(&find_item);
+        assert!(
+            xml.contains(concat!(
+                r#"<GroupBy Order="Descending"><t:FieldURI FieldURI="message:From" />"#,
+                r#"<t:AggregateOn Aggregate="Maximum">"#,
+                r#"<t:FieldURI FieldURI="item:DateTimeReceived" /></t:AggregateOn></GroupBy>"#
+            )),
+            "unexpected output: {xml}"
+        );
+
+        let envelope: SoapEnvelope = serde_xml_rs::from_str(
+            r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/">
+                <s:Body>
+                    <m:FindItemResponse
+                        xmlns:m="http://schemas.microsoft.com/exchange/services/2006/messages"
+                        xmlns:t="http://schemas.microsoft.com/exchange/services/2006/types">
+                        <m:ResponseMessages>
+                            <m:FindItemResponseMessage ResponseClass="Success">
+                                <m:ResponseCode>NoError</m:ResponseCode>
+                                <m:RootFolder TotalItemsInView="3" IncludesLastItemInRange="true">
+                                    <t:Groups>
+                                        <t:GroupedItems>
+                                            <t:GroupIndex>Alice</t:GroupIndex>
+                                            <t:Items>
+                                                <t:Message>
+                                                    <t:ItemId Id="AAMkAD" ChangeKey="CQAAAB" />
+                                                </t:Message>
+                                                <t:Message>
+                                                    <t:ItemId Id="AAMkAE" ChangeKey="CQAAAC" />
+                                                </t:Message>
+                                            </t:Items>
+                                        </t:GroupedItems>
+                                        <t:GroupedItems>
+                                            <t:GroupIndex>Bob</t:GroupIndex>
+                                            <t:Items>
+                                                <t:Message>
+                                                    <t:ItemId Id="AAMkAF" ChangeKey="CQAAAD" />
+                                                </t:Message>
+                                            </t:Items>
+                                        </t:GroupedItems>
+                                    </t:Groups>
+                                </m:RootFolder>
+                            </m:FindItemResponseMessage>
+                        </m:ResponseMessages>
+                    </m:FindItemResponse>
+                </s:Body>
+            </s:Envelope>"#,
+        )
+        .expect("parsing should succeed");
+
+        let Response::FindItemResponse(response) = envelope.body.contents else {
+            panic!("expected a FindItemResponse");
+        };
+        let response = response.into_result().unwrap();
+
+        let groups = response.groups();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].group_index(), "Alice");
+        assert_eq!(groups[0].messages().len(), 2);
+        assert_eq!(groups[1].group_index(), "Bob");
+        assert_eq!(groups[1].messages()[0].item_id().unwrap().id(), "AAMkAF");
+
+        assert_eq!(response.messages().len(), 3);
+    }
+
+    #[test]
+    fn effective_rights_are_parsed() {
+        let message: Message = serde_xml_rs::from_str(
+            r#"<t:Message xmlns:t="http://schemas.microsoft.com/exchange/services/2006/types">
+                <t:ItemId Id="AAMkAD" ChangeKey="CQAAAB" />
+                <t:EffectiveRights>
+                    <t:CreateAssociated>false</t:CreateAssociated>
+                    <t:Delete>true</t:Delete>
+                    <t:Modify>false</t:Modify>
+                    <t:Read>true</t:Read>
+                </t:EffectiveRights>
+            </t:Message>"#,
+        )
+        .expect("parsing should succeed");
+
+        let rights = message
+            .effective_rights()
+            .expect("effective rights should be present");
+        assert!(rights.delete);
+        assert!(rights.read);
+        assert!(!rights.modify);
+        assert!(!rights.create_associated);
+
+        // Rights the server didn't mention aren't granted.
+        assert!(!rights.create_contents);
+        assert!(!rights.view_private_items);
+    }
+
+    #[test]
+    fn message_timestamps_are_parsed() {
+        let message: Message = serde_xml_rs::from_str(
+            r#"<t:Message xmlns:t="http://schemas.microsoft.com/exchange/services/2006/types">
+                <t:ItemId Id="AAMkAD" ChangeKey="CQAAAB" />
+                <t:DateTimeReceived>2023-05-16T09:30:12Z</t:DateTimeReceived>
+                <t:DateTimeSent>2023-05-16T11:29:58+02:00</t:DateTimeSent>
+            </t:Message>"#,
+        )
+        .expect("parsing should succeed");
+
+        let received = message.date_time_received().unwrap();
+        assert_eq!(received.unix_timestamp(), 1_684_229_412);
+        assert_eq!(received.to_string(), "2023-05-16T09:30:12Z");
+
+        // The offset is folded into the time, which is held in UTC.
+        let sent = message.date_time_sent().unwrap();
+        assert_eq!(sent.to_string(), "2023-05-16T09:29:58Z");
+        assert!(sent < received);
+    }
+
+    #[test]
+    fn message_metadata_is_parsed() {
+        let message: Message = serde_xml_rs::from_str(
+            r#"<t:Message xmlns:t="http://schemas.microsoft.com/exchange/services/2006/types">
+                <t:ItemId Id="AAMkAD" ChangeKey="CQAAAB" />
+                <t:Subject>Quarterly report</t:Subject>
+                <t:Size>48213</t:Size>
+                <t:HasAttachments>true</t:HasAttachments>
+                <t:From>
+                    <t:Mailbox>
+                        <t:Name>Alice</t:Name>
+                        <t:EmailAddress>alice@example.com</t:EmailAddress>
+                    </t:Mailbox>
+                </t:From>
+                <t:IsRead>false</t:IsRead>
+            </t:Message>"#,
+        )
+        .expect("parsing should succeed");
+
+        assert_eq!(message.subject(), Some("Quarterly report"));
+        assert_eq!(message.size(), Some(48213));
+        assert_eq!(message.has_attachments(), Some(true));
+        assert_eq!(message.is_read(), Some(false));
+        assert_eq!(
+            message.from().map(|from| from.email_address()),
+            Some("alice@example.com")
+        );
+
+        // A draft with no subject, fetched with the IdOnly shape.
+        let message: Message = serde_xml_rs::from_str(
+            r#"<t:Message xmlns:t="http://schemas.microsoft.com/exchange/services/2006/types">
+                <t:ItemId Id="AAMkAD" ChangeKey="CQAAAB" />
+            </t:Message>"#,
+        )
+        .expect("parsing should succeed");
+
+        assert_eq!(message.item_id().unwrap().id(), "AAMkAD");
+        assert_eq!(message.subject(), None);
+        assert_eq!(message.size(), None);
+        assert_eq!(message.has_attachments(), None);
+        assert_eq!(message.is_read(), None);
+        assert!(message.from().is_none());
+    }
+
+    #[test]
+    fn importance_and_flag_are_parsed() {
+        let message: Message = serde_xml_rs::from_str(
+            r#"<t:Message xmlns:t="http://schemas.microsoft.com/exchange/services/2006/types">
+                <t:ItemId Id="AAMkAD" ChangeKey="CQAAAB" />
+                <t:Subject>Contract renewal</t:Subject>
+                <t:Importance>High</t:Importance>
+                <t:Flag>
+                    <t:FlagStatus>Flagged</t:FlagStatus>
+                    <t:StartDate>2023-05-16T00:00:00Z</t:StartDate>
+                    <t:DueDate>2023-05-19T00:00:00Z</t:DueDate>
+                </t:Flag>
+            </t:Message>"#,
+        )
+        .expect("parsing should succeed");
+
+        assert_eq!(message.importance(), Some(Importance::High));
+
+        let flag = message.flag().expect("flag should be present");
+        assert_eq!(flag.flag_status, FlagStatus::Flagged);
+        assert_eq!(
+            flag.due_date.map(|date| date.to_string()).as_deref(),
+            Some("2023-05-19T00:00:00Z")
+        );
+        assert!(flag.start_date.unwrap() < flag.due_date.unwrap());
+        assert!(flag.complete_date.is_none());
+
+        // Unflagged messages may still carry a flag element.
+        let message: Message = serde_xml_rs::from_str(
+            r#"<t:Message xmlns:t="http://schemas.microsoft.com/exchange/services/2006/types">
+                <t:Flag>
+                    <t:FlagStatus>NotFlagged</t:FlagStatus>
+                </t:Flag>
+            </t:Message>"#,
+        )
+        .expect("parsing should succeed");
+
+        assert_eq!(message.importance(), None);
+        assert_eq!(message.flag().unwrap().flag_status, FlagStatus::NotFlagged);
+    }
+
+    #[test]
+    fn date_times_are_normalized_to_utc() {
+        let date_time: DateTime = "1999-12-31T20:15:00.250-05:00".parse().unwrap();
+        assert_eq!(date_time.to_string(), "2000-01-01T01:15:00.25Z");
+        assert_eq!(date_time.subsec_nanos(), 250_000_000);
+
+        let date_time: DateTime = "1969-12-31T23:59:59Z".parse().unwrap();
+        assert_eq!(date_time.unix_timestamp(), -1);
+
+        assert_eq!(
+            DateTime::from_unix_timestamp(951_782_400).to_string(),
+            "2000-02-29T00:00:00Z"
+        );
+    }
+
+    #[test]
+    fn invalid_date_times_are_rejected() {
+        for value in [
+            "",
+            "2023-05-16",
+            "2023-05-16T09:30:12",
+            "2023-02-29T09:30:12Z",
+            "2023-05-16T24:00:00Z",
+            "2023-05-16T09:30:12.Z",
+            "2023-05-16T09:30:12+0200",
+        ] {
+            assert!(
+                value.parse::<DateTime>().is_err(),
+                "{value:?} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn get_item_requests_items_by_id() {
+        let item_id = ItemId {
+            id: "AAMkAD".to_string(),
+            change_key: "CQAAAB".to_string(),
+        };
+        let get_item = GetItem::new(
+            ItemShape {
+                base_shape: BaseShape::Default,
+                additional_properties: Some(vec![FieldUri::ItemBody]),
+            },
+            vec![item_id],
+        );
+
+        let xml = write_to_string(&get_item);
+        assert!(
+            xml.contains(concat!(
+                "<GetItem><ItemShape><t:BaseShape>Default</t:BaseShape>",
+                r#"<t:AdditionalProperties><t:FieldURI FieldURI="item:Body" />"#,
+                "</t:AdditionalProperties></ItemShape>",
+                r#"<ItemIds><t:ItemId Id="AAMkAD" ChangeKey="CQAAAB" /></ItemIds></GetItem>"#
+            )),
+            "unexpected output: {xml}"
+        );
+
+        let envelope: SoapEnvelope = serde_xml_rs::from_str(
+            r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/">
+                <s:Body>
+                    <m:GetItemResponse
+                        xmlns:m="http://schemas.microsoft.com/exchange/services/2006/messages"
+                        xmlns:t="http://schemas.microsoft.com/exchange/services/2006/types">
+                        <m:ResponseMessages>
+                            <m:GetItemResponseMessage ResponseClass="Success">
+                                <m:ResponseCode>NoError</m:ResponseCode>
+                                <m:Items>
+                                    <t:Message>
+                                        <t:ItemId Id="AAMkAD" ChangeKey="CQAAAB" />
+                                        <t:Subject>Quarterly report</t:Subject>
+                                    </t:Message>
+                                </m:Items>
+                            </m:GetItemResponseMessage>
+                        </m:ResponseMessages>
+                    </m:GetItemResponse>
+                </s:Body>
+            </s:Envelope>"#,
+        )
+        .expect("parsing should succeed");
+
+        let Response::GetItemResponse(response) = envelope.body.contents else {
+            panic!("expected a GetItemResponse");
+        };
+        let response = response.into_result().unwrap();
+
+        let messages = response.messages();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].item_id().unwrap().id(), "AAMkAD");
+        assert_eq!(messages[0].subject(), Some("Quarterly report"));
+    }
+
+    #[test]
+    fn message_lists_attachments() {
+        let message: Message = serde_xml_rs::from_str(
+            r#"<t:Message xmlns:t="http://schemas.microsoft.com/exchange/services/2006/types">
+                <t:ItemId Id="AAMkAD" ChangeKey="CQAAAB" />
+                <t:Attachments>
+                    <t:FileAttachment>
+                        <t:AttachmentId Id="AAMkADAtt1" />
+                        <t:Name>report.pdf</t:Name>
+                        <t:ContentType>application/pdf</t:ContentType>
+                        <t:Size>48213</t:Size>
+                        <t:IsInline>false</t:IsInline>
+                    </t:FileAttachment>
+                    <t:ItemAttachment>
+                        <t:AttachmentId Id="AAMkADAtt2" />
+                        <t:Name>Fwd: Minutes</t:Name>
+                    </t:ItemAttachment>
+                </t:Attachments>
+            </t:Message>"#,
+        )
+        .expect("parsing should succeed");
+
+        let attachments = message.attachments();
+        assert_eq!(attachments.len(), 2);
+        assert!(matches!(attachments[0], Attachment::FileAttachment(_)));
+        assert_eq!(attachments[0].attachment_id().id(), "AAMkADAtt1");
+        assert_eq!(attachments[0].name(), Some("report.pdf"));
+        assert_eq!(attachments[0].content_type(), Some("application/pdf"));
+        assert_eq!(attachments[0].size(), Some(48213));
+        assert!(matches!(attachments[1], Attachment::ItemAttachment(_)));
+        assert_eq!(attachments[1].name(), Some("Fwd: Minutes"));
+    }
+
+    #[test]
+    fn get_attachment_decodes_file_content() {
+        let get_attachment = GetAttachment::new(vec![AttachmentId::new("AAMkADAtt1")]);
+
+        let xml = write_to_string(&get_attachment);
+        assert!(
+            xml.contains(concat!(
+                "<GetAttachment><AttachmentIds>",
+                r#"<t:AttachmentId Id="AAMkADAtt1" />"#,
+                "</AttachmentIds></GetAttachment>"
+            )),
+            "unexpected output: {xml}"
+        );
+
+        let envelope: SoapEnvelope = serde_xml_rs::from_str(
+            r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/">
+                <s:Body>
+                    <m:GetAttachmentResponse
+                        xmlns:m="http://schemas.microsoft.com/exchange/services/2006/messages"
+                        xmlns:t="http://schemas.microsoft.com/exchange/services/2006/types">
+                        <m:ResponseMessages>
+                            <m:GetAttachmentResponseMessage ResponseClass="Success">
+                                <m:ResponseCode>NoError</m:ResponseCode>
+                                <m:Attachments>
+                                    <t:FileAttachment>
+                                        <t:AttachmentId Id="AAMkADAtt1" />
+                                        <t:Name>hello.txt</t:Name>
+                                        <t:ContentType>text/plain</t:ContentType>
+                                        <t:Content>SGVsbG8sIHdvcmxkIQ==</t:Content>
+                                    </t:FileAttachment>
+                                </m:Attachments>
+                            </m:GetAttachmentResponseMessage>
+                        </m:ResponseMessages>
+                    </m:GetAttachmentResponse>
+                </s:Body>
+            </s:Envelope>"#,
+        )
+        .expect("parsing should succeed");
+
+        let Response::GetAttachmentResponse(response) = envelope.body.contents else {
+            panic!("expected a GetAttachmentResponse");
+        };
+        let response = response.into_result().unwrap();
+
+        let attachments = response.attachments();
+        assert_eq!(attachments.len(), 1);
+        let Attachment::FileAttachment(attachment) = attachments[0] else {
+            panic!("expected a file attachment");
+        };
+        assert_eq!(attachment.content(), Some("SGVsbG8sIHdvcmxkIQ=="));
+        assert_eq!(
+            attachment.decoded_content().unwrap().as_deref(),
+            Some(&b"Hello, world!"[..])
+        );
+    }
+
+    #[test]
+    fn create_item_sends_message() {
+        let message = Message::default()
+            .with_subject("Lunch?")
+            .with_body(Body::new(BodyType::Text, "Are you free at noon?"))
+            .with_to_recipients(vec![Mailbox::new(
+                &"bob@example.com".parse().unwrap(),
+                Some("Bob"),
+            )]);
+        let create_item = CreateItem::new(
+            MessageDisposition::SendAndSaveCopy,
+            Some(FolderId::DistinguishedFolderId {
+                id: DistinguishedFolderIdName::SentItems,
+                change_key: None,
+                mailbox: None,
+            }),
+            vec![EwsItem::Message(message)],
+        );
+
+        let xml = write_to_string(&create_item);
+        assert!(
+            xml.contains(concat!(
+                r#"<CreateItem MessageDisposition="SendAndSaveCopy">"#,
+                r#"<SavedItemFolderId><t:DistinguishedFolderId Id="sentitems" /></SavedItemFolderId>"#,
+                "<Items><t:Message><t:Subject>Lunch?</t:Subject>",
+                r#"<t:Body BodyType="Text">Are you free at noon?</t:Body>"#,
+                "<t:ToRecipients><t:Mailbox><t:Name>Bob</t:Name>",
+                "<t:EmailAddress>bob@example.com</t:EmailAddress></t:Mailbox></t:ToRecipients>",
+                "</t:Message></Items></CreateItem>"
+            )),
+            "unexpected output: {xml}"
+        );
+
+        let envelope: SoapEnvelope = serde_xml_rs::from_str(
+            r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/">
+                <s:Body>
+                    <m:CreateItemResponse
+                        xmlns:m="http://schemas.microsoft.com/exchange/services/2006/messages"
+                        xmlns:t="http://schemas.microsoft.com/exchange/services/2006/types">
+                        <m:ResponseMessages>
+                            <m:CreateItemResponseMessage ResponseClass="Success">
+                                <m:ResponseCode>NoError</m:ResponseCode>
+                                <m:Items>
+                                    <t:Message>
+                                        <t:ItemId Id="AAMkAD" ChangeKey="CQAAAB" />
+                                    </t:Message>
+                                </m:Items>
+                            </m:CreateItemResponseMessage>
+                        </m:ResponseMessages>
+                    </m:CreateItemResponse>
+                </s:Body>
+            </s:Envelope>"#,
+        )
+        .expect("parsing should succeed");
+
+        let Response::CreateItemResponse(response) = envelope.body.contents else {
+            panic!("expected a CreateItemResponse");
+        };
+        let response = response.into_result().unwrap();
+
+        let item_ids = response.item_ids();
+        assert_eq!(item_ids.len(), 1);
+        assert_eq!(item_ids[0].id(), "AAMkAD");
+    }
+
+    #[test]
+    fn update_item_marks_message_read() {
+        let update_item = UpdateItem::mark_read(ItemId::new("AAMkAD", "CQAAAB"), true);
+
+        let xml = write_to_string(&update_item);
+        assert!(
+            xml.contains(concat!(
+                r#"<UpdateItem ConflictResolution="AutoResolve" MessageDisposition="SaveOnly">"#,
+                r#"<ItemChanges><t:ItemChange><t:ItemId Id="AAMkAD" ChangeKey="CQAAAB" />"#,
+                r#"<t:Updates><t:SetItemField><t:FieldURI FieldURI="message:IsRead" />"#,
+                "<t:Message><t:IsRead>true</t:IsRead></t:Message></t:SetItemField>",
+                "</t:Updates></t:ItemChange></ItemChanges></UpdateItem>"
+            )),
+            "unexpected output: {xml}"
+        );
+
+        let update_item = UpdateItem::new(
+            ConflictResolution::NeverOverwrite,
+            vec![UpdateItemChange::new(
+                ItemId::new("AAMkAD", "CQAAAB"),
+                vec![ItemUpdate::DeleteItemField(FieldUri::ItemFlag)],
+            )],
+        );
+
+        let xml = write_to_string(&update_item);
+        assert!(
+            xml.contains(concat!(
+                r#"<UpdateItem ConflictResolution="NeverOverwrite">"#,
+                r#"<ItemChanges><t:ItemChange><t:ItemId Id="AAMkAD" ChangeKey="CQAAAB" />"#,
+                r#"<t:Updates><t:DeleteItemField><t:FieldURI FieldURI="item:Flag" />"#,
+                "</t:DeleteItemField></t:Updates></t:ItemChange></ItemChanges></UpdateItem>"
+            )),
+            "unexpected output: {xml}"
+        );
+
+        let envelope: SoapEnvelope = serde_xml_rs::from_str(
+            r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/">
+                <s:Body>
+                    <m:UpdateItemResponse
+                        xmlns:m="http://schemas.microsoft.com/exchange/services/2006/messages"
+                        xmlns:t="http://schemas.microsoft.com/exchange/services/2006/types">
+                        <m:ResponseMessages>
+                            <m:UpdateItemResponseMessage ResponseClass="Success">
+                                <m:ResponseCode>NoError</m:ResponseCode>
+                                <m:Items>
+                                    <t:Message>
+                                        <t:ItemId Id="AAMkAD" ChangeKey="CQAAAC" />
+                                    </t:Message>
+                                </m:Items>
+                                <m:ConflictResults>
+                                    <t:Count>0</t:Count>
+                                </m:ConflictResults>
+                            </m:UpdateItemResponseMessage>
+                        </m:ResponseMessages>
+                    </m:UpdateItemResponse>
+                </s:Body>
+            </s:Envelope>"#,
+        )
+        .expect("parsing should succeed");
+
+        let Response::UpdateItemResponse(response) = envelope.body.contents else {
+            panic!("expected an UpdateItemResponse");
+        };
+        let response = response.into_result().unwrap();
+
+        // The change key changes with every update.
+        let item_ids = response.item_ids();
+        assert_eq!(item_ids.len(), 1);
+        assert_eq!(item_ids[0].change_key(), "CQAAAC");
+    }
+
+    #[test]
+    fn html_body_round_trips() {
+        let html = "<p>Hello, <b>world</b> &amp; everyone</p>";
+        let item = EwsItem::Message(
+            Message::default()
+                .with_subject("Greetings")
+                .with_body(Body::new(BodyType::Html, html)),
+        );
+
+        let xml = write_to_string(&item);
+        let items: Items = serde_xml_rs::from_str(&xml).expect("parsing should succeed");
+        let EwsItem::Message(message) = &items.items[0];
+
+        let body = message.body().expect("body should be present");
+        assert_eq!(body.body_type(), BodyType::Html);
+        assert_eq!(body.content(), html);
+    }
+
+    #[test]
+    fn html_body_is_written_as_cdata() {
+        let body = Body::new(BodyType::Html, "<b>hi</b>");
+        assert!(
+            write_to_string(&body)
+                .contains(r#"<t:Body BodyType="HTML"><![CDATA[<b>hi</b>]]></t:Body>"#),
+            "unexpected output: {}",
+            write_to_string(&body)
+        );
+
+        let body = Body::new(BodyType::Text, "<b>hi</b>");
+        assert!(
+            write_to_string(&body)
+                .contains(r#"<t:Body BodyType="Text">&lt;b&gt;hi&lt;/b&gt;</t:Body>"#),
+            "unexpected output: {}",
+            write_to_string(&body)
+        );
+
+        // Content which would end the section early is split across two.
+        let html = "<p>a[1]]>b</p>";
+        let xml = write_to_string(&Body::new(BodyType::Html, html));
+        assert!(
+            xml.contains("<![CDATA[<p>a[1]]]]><![CDATA[>b</p>]]>"),
+            "unexpected output: {xml}"
+        );
+
+        let start = xml.find("<t:Body").unwrap();
+        let end = xml.find("</t:Body>").unwrap() + "</t:Body>".len();
+        let body: Body = serde_xml_rs::from_str(&xml[start..end].replace("t:Body", "Body"))
+            .expect("parsing should succeed");
+        assert_eq!(body.content(), html);
+    }
+
+    #[test]
+    fn recipients_are_written() {
+        let message = Message::default()
+            .with_to_recipients(vec![Mailbox::new(
+                &"alice@example.com".parse().unwrap(),
+                None,
+            )
+            .with_routing_type("SMTP")])
+            .with_cc_recipients(vec![])
+            .with_bcc_recipients(vec![
+                Mailbox::new(&"carol@example.com".parse().unwrap(), Some("Carol")),
+                Mailbox::new(&"dave@example.com".parse().unwrap(), None),
+            ]);
+
+        let xml = write_to_string(&message);
+        assert!(
+            xml.contains(concat!(
+                "<t:Message><t:ToRecipients><t:Mailbox>",
+                "<t:EmailAddress>alice@example.com</t:EmailAddress>",
+                "<t:RoutingType>SMTP</t:RoutingType></t:Mailbox></t:ToRecipients>",
+                "<t:CcRecipients /><t:BccRecipients>",
+                "<t:Mailbox><t:Name>Carol</t:Name><t:EmailAddress>carol@example.com</t:EmailAddress></t:Mailbox>",
+                "<t:Mailbox><t:EmailAddress>dave@example.com</t:EmailAddress></t:Mailbox>",
+                "</t:BccRecipients></t:Message>"
+            )),
+            "unexpected output: {xml}"
+        );
+
+        let items: Items = serde_xml_rs::from_str(&write_to_string(&EwsItem::Message(message)))
+            .expect("parsing should succeed");
+        let EwsItem::Message(message) = &items.items[0];
+        assert_eq!(message.to_recipients()[0].routing_type(), Some("SMTP"));
+        assert!(message.cc_recipients().is_empty());
+        assert_eq!(message.bcc_recipients().len(), 2);
+        assert_eq!(message.bcc_recipients()[0].name(), Some("Carol"));
+    }
+
+    #[test]
+    fn distinguished_folder_names_are_lowercase() {
+        let folder_id = |id| FolderId::DistinguishedFolderId {
+            id,
+            change_key: None,
+            mailbox: None,
+        };
+
+        let xml = write_to_string(&folder_id(DistinguishedFolderIdName::DeletedItems));
+        assert!(
+            xml.contains(r#"<t:DistinguishedFolderId Id="deleteditems" />"#),
+            "unexpected output: {xml}"
+        );
+
+        let xml = write_to_string(&folder_id(DistinguishedFolderIdName::Other(
+            "archivemsgfolderroot".to_string(),
+        )));
+        assert!(
+            xml.contains(r#"<t:DistinguishedFolderId Id="archivemsgfolderroot" />"#),
+            "unexpected output: {xml}"
+        );
+    }
+
+    #[test]
+    fn absent_optional_values_are_omitted() {
+        let folder_id = |change_key: Option<&str>| FolderId::DistinguishedFolderId {
+            id: DistinguishedFolderIdName::Inbox,
+            change_key: change_key.map(str::to_string),
+            mailbox: None,
+        };
+
+        let xml = write_to_string(&folder_id(None));
+        assert!(
+            xml.contains(r#"<t:DistinguishedFolderId Id="inbox" />"#),
+            "unexpected output: {xml}"
+        );
+
+        // An empty value is still a value, so it must be written out.
+        let xml = write_to_string(&folder_id(Some("")));
+        assert!(
+            xml.contains(r#"<t:DistinguishedFolderId Id="inbox" ChangeKey="" />"#),
+            "unexpected output: {xml}"
+        );
+
+        let xml = write_to_string(&Message::default());
+        assert!(xml.contains("<t:Message />"), "unexpected output: {xml}");
+
+        let xml = write_to_string(&Message::default().with_subject(""));
+        assert!(
+            xml.contains("<t:Message><t:Subject></t:Subject></t:Message>"),
+            "unexpected output: {xml}"
+        );
+    }
+
+    #[test]
+    fn option_is_written_only_when_present() {
+        let xml = write_to_string(&Some(ItemId::new("AAMkAD", "CQAAAB")));
+        assert!(
+            xml.contains(r#"<t:ItemId Id="AAMkAD" ChangeKey="CQAAAB" />"#),
+            "unexpected output: {xml}"
+        );
+
+        let xml = write_to_string(&None::<ItemId>);
+        assert!(xml.ends_with(" />"), "unexpected output: {xml}");
+        assert!(!xml.contains("ItemId"), "unexpected output: {xml}");
+        assert_eq!(EwsWrite::<Vec<u8>>::size_hint(&None::<ItemId>), 0);
+    }
+
+    #[test]
+    fn vec_is_written_as_repeated_elements() {
+        let ids: Vec<_> = ["AAMkA1", "AAMkA2", "AAMkA3"]
+            .into_iter()
+            .map(|id| ItemId::new(id, "CQAAAB"))
+            .collect();
+
+        let xml = write_to_string(&ids);
+        assert!(
+            xml.contains(concat!(
+                r#"<Wrapper xmlns="http://schemas.microsoft.com/exchange/services/2006/messages" "#,
+                r#"xmlns:t="http://schemas.microsoft.com/exchange/services/2006/types">"#,
+                r#"<t:ItemId Id="AAMkA1" ChangeKey="CQAAAB" />"#,
+                r#"<t:ItemId Id="AAMkA2" ChangeKey="CQAAAB" />"#,
+                r#"<t:ItemId Id="AAMkA3" ChangeKey="CQAAAB" />"#,
+                "</Wrapper>"
+            )),
+            "unexpected output: {xml}"
+        );
+    }
+
+    #[test]
+    fn delete_item_lists_item_ids() {
+        let delete_item = DeleteItem::new(
+            DeleteType::MoveToDeletedItems,
+            vec![
+                ItemId::new("AAMkAD", "CQAAAB"),
+                ItemId::new("AAMkAE", "CQAAAC"),
+            ],
+        );
+
+        let xml = write_to_string(&delete_item);
+        assert!(
+            xml.contains(concat!(
+                r#"<DeleteItem DeleteType="MoveToDeletedItems"><ItemIds>"#,
+                r#"<t:ItemId Id="AAMkAD" ChangeKey="CQAAAB" />"#,
+                r#"<t:ItemId Id="AAMkAE" ChangeKey="CQAAAC" />"#,
+                "</ItemIds></DeleteItem>"
+            )),
+            "unexpected output: {xml}"
+        );
+    }
+
+    #[test]
+    fn move_item_returns_new_item_ids() {
+        let move_item = MoveItem::new(
+            FolderId::DistinguishedFolderId {
+                id: DistinguishedFolderIdName::JunkEmail,
+                change_key: None,
+                mailbox: None,
+            },
+            vec![ItemId::new("AAMkAD", "CQAAAB")],
+        );
+
+        let xml = write_to_string(&move_item);
+        assert!(
+            xml.contains(concat!(
+                r#"<MoveItem><ToFolderId><t:DistinguishedFolderId Id="junkemail" /></ToFolderId>"#,
+                r#"<ItemIds><t:ItemId Id="AAMkAD" ChangeKey="CQAAAB" /></ItemIds></MoveItem>"#
+            )),
+            "unexpected output: {xml}"
+        );
+
+        let envelope: SoapEnvelope = serde_xml_rs::from_str(
+            r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/">
+                <s:Body>
+                    <m:MoveItemResponse
+                        xmlns:m="http://schemas.microsoft.com/exchange/services/2006/messages"
+                        xmlns:t="http://schemas.microsoft.com/exchange/services/2006/types">
+                        <m:ResponseMessages>
+                            <m:MoveItemResponseMessage ResponseClass="Success">
+                                <m:ResponseCode>NoError</m:ResponseCode>
+                                <m:Items>
+                                    <t:Message>
+                                        <t:ItemId Id="AAMkAF" ChangeKey="CQAAAD" />
+                                    </t:Message>
+                                </m:Items>
+                            </m:MoveItemResponseMessage>
+                        </m:ResponseMessages>
+                    </m:MoveItemResponse>
+                </s:Body>
+            </s:Envelope>"#,
+        )
+        .expect("parsing should succeed");
+
+        let Response::MoveItemResponse(response) = envelope.body.contents else {
+            panic!("expected a MoveItemResponse");
+        };
+        let response = response.into_result().unwrap();
+        let item_ids = response.item_ids();
+        assert_eq!(item_ids.len(), 1);
+        assert_eq!(item_ids[0].id(), "AAMkAF");
+    }
+
+    #[test]
+    fn copy_item_is_written() {
+        let copy_item = CopyItem::new(
+            FolderId::DistinguishedFolderId {
+                id: DistinguishedFolderIdName::Drafts,
+                change_key: None,
+                mailbox: None,
+            },
+            vec![
+                ItemId::new("AAMkAD", "CQAAAB"),
+                ItemId::new("AAMkAE", "CQAAAC"),
+            ],
+        );
+
+        let xml = write_to_string(&copy_item);
+        assert!(
+            xml.contains(concat!(
+                r#"<CopyItem><ToFolderId><t:DistinguishedFolderId Id="drafts" /></ToFolderId>"#,
+                r#"<ItemIds><t:ItemId Id="AAMkAD" ChangeKey="CQAAAB" />"#,
+                r#"<t:ItemId Id="AAMkAE" ChangeKey="CQAAAC" /></ItemIds></CopyItem>"#
+            )),
+            "unexpected output: {xml}"
+        );
+    }
+
+    #[test]
+    fn find_item_filters_unread_messages() {
+        let find_item = FindItem::new(
+            Traversal::Shallow,
+            ItemShape {
+                base_shape: BaseShape::IdOnly,
+                additional_properties: None,
+            },
+            vec![],
+        )
+        .with_restriction(Restriction::IsEqualTo {
+            field: FieldUri::MessageIsRead,
+            value: "false".to_string(),
+        });
+
+        let xml = write_to_string(&find_item);
+        assert!(
+            xml.contains(concat!(
+                "</ItemShape><Restriction><t:IsEqualTo>",
+                r#"<t:FieldURI FieldURI="message:IsRead" />"#,
+                r#"<t:FieldURIOrConstant><t:Constant Value="false" /></t:FieldURIOrConstant>"#,
+                "</t:IsEqualTo></Restriction><ParentFolderIds"
+            )),
+            "unexpected output: {xml}"
+        );
+    }
+
+    #[test]
+    fn restrictions_are_combined() {
+        let restriction = Restriction::And(vec![
+            Restriction::Contains {
+                field: FieldUri::ItemSubject,
+                value: "report".to_string(),
+            },
+            Restriction::Not(Box::new(Restriction::IsEqualTo {
+                field: FieldUri::MessageIsRead,
+                value: "true".to_string(),
+            })),
+        ]);
+
+        let xml = write_to_string(&restriction);
+        assert!(
+            xml.contains(concat!(
+                "<Restriction><t:And>",
+                r#"<t:Contains ContainmentMode="Substring" ContainmentComparison="IgnoreCase">"#,
+                r#"<t:FieldURI FieldURI="item:Subject" /><t:Constant Value="report" /></t:Contains>"#,
+                r#"<t:Not><t:IsEqualTo><t:FieldURI FieldURI="message:IsRead" />"#,
+                r#"<t:FieldURIOrConstant><t:Constant Value="true" /></t:FieldURIOrConstant>"#,
+                "</t:IsEqualTo></t:Not></t:And></Restriction>"
+            )),
+            "unexpected output: {xml}"
+        );
+    }
+
+    #[test]
+    fn find_item_is_paged() {
+        let find_item = FindItem::new(
+            Traversal::Shallow,
+            ItemShape {
+                base_shape: BaseShape::IdOnly,
+                additional_properties: None,
+            },
+            vec![],
+        )
+        .with_view(IndexedPageItemView {
+            max_entries_returned: Some(10),
+            offset: 20,
+            base_point: BasePoint::Beginning,
+        });
+
+        let xml = write_to_string(&find_item);
+        assert!(
+            xml.contains(concat!(
+                "</ItemShape>",
+                r#"<IndexedPageItemView MaxEntriesReturned="10" Offset="20" BasePoint="Beginning" />"#,
+                "<ParentFolderIds"
+            )),
+            "unexpected output: {xml}"
+        );
+
+        let envelope: SoapEnvelope = serde_xml_rs::from_str(
+            r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/">
+                <s:Body>
+                    <m:FindItemResponse
+                        xmlns:m="http://schemas.microsoft.com/exchange/services/2006/messages"
+                        xmlns:t="http://schemas.microsoft.com/exchange/services/2006/types">
+                        <m:ResponseMessages>
+                            <m:FindItemResponseMessage ResponseClass="Success">
+                                <m:ResponseCode>NoError</m:ResponseCode>
+                                <m:RootFolder IndexedPagingOffset="21" TotalItemsInView="42"
+                                    IncludesLastItemInRange="false">
+                                    <t:Items>
+                                        <t:Message>
+                                            <t:ItemId Id="AAMkAD" ChangeKey="CQAAAB" />
+                                        </t:Message>
+                                    </t:Items>
+                                </m:RootFolder>
+                            </m:FindItemResponseMessage>
+                        </m:ResponseMessages>
+                    </m:FindItemResponse>
+                </s:Body>
+            </s:Envelope>"#,
+        )
+        .expect("parsing should succeed");
+
+        let Response::FindItemResponse(response) = envelope.body.contents else {
+            panic!("expected a FindItemResponse");
+        };
+        let response = response.into_result().unwrap();
+
+        let root_folder = response.root_folders()[0];
+        assert_eq!(root_folder.total_items_in_view(), Some(42));
+        assert_eq!(root_folder.indexed_paging_offset(), Some(21));
+        assert!(!root_folder.includes_last_item_in_range());
+    }
+
+    #[test]
+    fn find_item_is_sorted() {
+        let find_item = FindItem::new(
+            Traversal::Shallow,
+            ItemShape {
+                base_shape: BaseShape::IdOnly,
+                additional_properties: None,
+            },
+            vec![],
+        )
+        .with_sort_order(SortOrder(vec![FieldOrder {
+            field: FieldUri::ItemDateTimeReceived,
+            order: SortDirection::Descending,
+        }]));
+
+        let xml = write_to_string(&find_item);
+        assert!(
+            xml.contains(concat!(
+                r#"</ItemShape><SortOrder><t:FieldOrder Order="Descending">"#,
+                r#"<t:FieldURI FieldURI="item:DateTimeReceived" /></t:FieldOrder></SortOrder>"#,
+                "<ParentFolderIds"
+            )),
+            "unexpected output: {xml}"
+        );
+    }
+
+    #[test]
+    fn fault_is_returned_as_error() {
+        let envelope: SoapEnvelope = serde_xml_rs::from_str(
+            r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/">
+                <s:Body>
+                    <s:Fault>
+                        <faultcode xmlns:a="http://schemas.microsoft.com/exchange/services/2006/types">a:ErrorSchemaValidation</faultcode>
+                        <faultstring xml:lang="en-US">The request failed schema validation: The element 'FindItem' has incomplete content.</faultstring>
+                        <detail>
+                            <e:ResponseCode xmlns:e="http://schemas.microsoft.com/exchange/services/2006/errors">ErrorSchemaValidation</e:ResponseCode>
+                            <e:Message xmlns:e="http://schemas.microsoft.com/exchange/services/2006/errors">The request failed schema validation.</e:Message>
+                            <t:MessageXml xmlns:t="http://schemas.microsoft.com/exchange/services/2006/types">
+                                <t:LineNumber>2</t:LineNumber>
+                                <t:LinePosition>630</t:LinePosition>
+                            </t:MessageXml>
+                        </detail>
+                    </s:Fault>
+                </s:Body>
+            </s:Envelope>"#,
+        )
+        .expect("parsing should succeed");
+
+        let fault = match envelope.into_result() {
+            Err(fault) => fault,
+            Ok(_) => panic!("expected a fault"),
+        };
+        assert_eq!(fault.fault_code(), "a:ErrorSchemaValidation");
+        assert!(fault
+            .fault_string()
+            .starts_with("The request failed schema validation"));
+        assert_eq!(
+            fault.response_code(),
+            Some(&ResponseCode::Other("ErrorSchemaValidation".to_string()))
+        );
+        assert_eq!(
+            fault.message(),
+            Some("The request failed schema validation.")
+        );
+    }
 }
@@ -0,0 +1,265 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Recording and replaying of EWS interactions for deterministic tests.
+//!
+//! In record mode, requests go through a real [`EwsClient`] and each
+//! request/response pair is appended to a cassette, which can then be saved
+//! to disk. In replay mode, no request leaves the process: responses are read
+//! back from the cassette in order, after checking that the request matches
+//! the one recorded.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::net::{EwsClient, HttpError};
+
+/// A single recorded request and the response the server sent to it.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct Interaction {
+    request: String,
+    response: String,
+}
+
+/// The on-disk representation of a recording.
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Cassette {
+    #[serde(default)]
+    interactions: Vec<Interaction>,
+}
+
+enum Mode {
+    Record(Box<EwsClient>),
+    Replay { next: usize },
+}
+
+/// An error occurring while sending a request through a [`VcrClient`].
+#[derive(Debug)]
+pub enum VcrError {
+    /// Sending the request to the server failed while recording.
+    Http(HttpError),
+
+    /// The request differs from the one recorded at the same position.
+    Mismatch { expected: String, actual: String },
+
+    /// More requests were sent than were recorded.
+    Exhausted,
+}
+
+impl std::fmt::Display for VcrError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VcrError::Http(err) => write!(f, "request failed: {err}"),
+            VcrError::Mismatch { expected, actual } => write!(
+                f,
+                "request does not match the recording\nexpected: {expected}\nactual: {actual}"
+            ),
+            VcrError::Exhausted => write!(f, "no more interactions in the recording"),
+        }
+    }
+}
+
+impl std::error::Error for VcrError {}
+
+impl From<HttpError> for VcrError {
+    fn from(err: HttpError) -> Self {
+        VcrError::Http(err)
+    }
+}
+
+/// A client which records the interactions of an [`EwsClient`] to a cassette
+/// file, or replays them from one.
+pub struct VcrClient {
+    path: PathBuf,
+    mode: Mutex<Mode>,
+    cassette: Mutex<Cassette>,
+}
+
+impl VcrClient {
+    /// Creates a client which sends requests through `client` and records
+    /// them. The recording is written to `path` by [`VcrClient::save`].
+    pub fn record<P: AsRef<Path>>(client: EwsClient, path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            mode: Mutex::new(Mode::Record(Box::new(client))),
+            cassette: Mutex::new(Cassette::default()),
+        }
+    }
+
+    /// Creates a client which replays the recording stored at `path`.
+    pub fn replay<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let cassette = fs::read_to_string(&path)?;
+        let cassette: Cassette = toml::from_str(&cassette)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        Ok(Self {
+            path: path.as_ref().to_path_buf(),
+            mode: Mutex::new(Mode::Replay { next: 0 }),
+            cassette: Mutex::new(cassette),
+        })
+    }
+
+    /// Sends an already-serialized SOAP envelope and returns the raw response
+    /// body, as [`EwsClient::send_raw`] does.
+    pub async fn send_raw(&self, body: &str) -> Result<String, VcrError> {
+        // Don't hold the lock across the request, so that the client can be
+        // shared between tasks.
+        let client = match &mut *self.mode.lock().unwrap() {
+            Mode::Record(client) => EwsClient::clone(client),
+            Mode::Replay { next } => {
+                let cassette = self.cassette.lock().unwrap();
+                let interaction = cassette
+                    .interactions
+                    .get(*next)
+                    .ok_or(VcrError::Exhausted)?;
+                if interaction.request != body {
+                    return Err(VcrError::Mismatch {
+                        expected: interaction.request.clone(),
+                        actual: body.to_string(),
+                    });
+                }
+
+                *next += 1;
+                return Ok(interaction.response.clone());
+            }
+        };
+
+        let response = client.send_raw(body.to_string()).await?;
+        self.cassette
+            .lock()
+            .unwrap()
+            .interactions
+            .push(Interaction {
+                request: body.to_string(),
+                response: response.clone(),
+            });
+
+        Ok(response)
+    }
+
+    /// Writes the interactions recorded so far to the cassette file.
+    ///
+    /// This does nothing when replaying.
+    pub fn save(&self) -> io::Result<()> {
+        if let Mode::Replay { .. } = *self.mode.lock().unwrap() {
+            return Ok(());
+        }
+
+        let cassette = toml::to_string(&*self.cassette.lock().unwrap())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        fs::write(&self.path, cassette)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        types::{
+            BaseShape, DistinguishedFolderIdName, FindItem, FolderId, HasResponseMessages,
+            ItemShape, Response, Traversal,
+        },
+        xml::{read_response, request_to_string},
+    };
+
+    const FIND_ITEM_RESPONSE: &str = r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/">
+    <s:Body>
+        <m:FindItemResponse
+            xmlns:m="http://schemas.microsoft.com/exchange/services/2006/messages"
+            xmlns:t="http://schemas.microsoft.com/exchange/services/2006/types">
+            <m:ResponseMessages>
+                <m:FindItemResponseMessage ResponseClass="Success">
+                    <m:ResponseCode>NoError</m:ResponseCode>
+                    <m:RootFolder TotalItemsInView="1" IncludesLastItemInRange="true">
+                        <t:Items>
+                            <t:Message>
+                                <t:ItemId Id="AAMkAD" ChangeKey="CQAAAB" />
+                                <t:Subject>Hello, world</t:Subject>
+                            </t:Message>
+                        </t:Items>
+                    </m:RootFolder>
+                </m:FindItemResponseMessage>
+            </m:ResponseMessages>
+        </m:FindItemResponse>
+    </s:Body>
+</s:Envelope>"#;
+
+    fn find_item_request() -> String {
+        request_to_string(FindItem::new(
+            Traversal::Shallow,
+            ItemShape {
+                base_shape: BaseShape::IdOnly,
+                additional_properties: None,
+            },
+            vec![FolderId::DistinguishedFolderId {
+                id: DistinguishedFolderIdName::Inbox,
+                change_key: None,
+                mailbox: None,
+            }],
+        ))
+        .unwrap()
+    }
+
+    fn subjects(response: &str) -> Vec<String> {
+        let Response::FindItemResponse(response) = read_response(response).unwrap().body.contents
+        else {
+            panic!("expected a FindItemResponse");
+        };
+
+        response
+            .into_result()
+            .unwrap()
+            .messages()
+            .into_iter()
+            .map(|message| message.subject().unwrap().to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn find_item_is_recorded_and_replayed() {
+        let path = std::env::temp_dir().join(format!("ews-vcr-{}.toml", std::process::id()));
+        let request = find_item_request();
+
+        {
+            let mut server = mockito::Server::new_async().await;
+            let mock = server
+                .mock("POST", "/EWS/Exchange.asmx")
+                .match_body(request.as_str())
+                .with_body(FIND_ITEM_RESPONSE)
+                .expect(1)
+                .create_async()
+                .await;
+
+            let client = EwsClient::new(
+                &format!("{}/EWS/Exchange.asmx", server.url()),
+                "user",
+                "pass",
+            )
+            .unwrap();
+            let recorder = VcrClient::record(client, &path);
+            let response = recorder.send_raw(&request).await.unwrap();
+            recorder.save().unwrap();
+
+            mock.assert_async().await;
+            assert_eq!(subjects(&response), ["Hello, world"]);
+        }
+
+        // The server is gone by now, so this can only succeed by replaying.
+        let player = VcrClient::replay(&path).unwrap();
+        let response = player.send_raw(&request).await.unwrap();
+        assert_eq!(subjects(&response), ["Hello, world"]);
+
+        assert!(matches!(
+            player.send_raw(&request).await,
+            Err(VcrError::Exhausted)
+        ));
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -2,26 +2,477 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use std::fmt::Display;
+
 use xml::writer;
 
-use crate::types::{EwsWrite, SOAP_NS_URI, TYPES_NS_URI};
+use crate::types::{
+    EwsError, EwsWrite, ExchangeVersion, RequestHeader, SoapEnvelope, SoapHeader, SOAP_NS_URI,
+    TYPES_NS_URI,
+};
+
+/// The byte order mark some servers prepend to UTF-8 documents.
+const UTF8_BOM: char = '\u{feff}';
 
-/// Writes a struct as the body of a SOAP request.
-pub fn write_request<W: std::io::Write, X: EwsWrite<W>>(
+/// The headers sent with requests unless others are given: only the version
+/// of the schema requests are written against, which is
+/// [`ExchangeVersion::default`].
+fn default_headers() -> [RequestHeader; 1] {
+    [RequestHeader::new(SoapHeader::RequestServerVersion {
+        version: ExchangeVersion::default(),
+    })]
+}
+
+/// Writes a struct as the body of a SOAP request, with a
+/// `RequestServerVersion` header for [`ExchangeVersion::default`].
+pub fn write_request<W: std::io::Write, X: EwsWrite<W>>(sink: W, body: X) -> Result<(), EwsError> {
+    write_request_with_headers(sink, &default_headers(), body)
+}
+
+/// Writes a struct as the body of a SOAP request, preceded by the given
+/// headers instead of the default ones. The `soap:Header` element is omitted
+/// if there are none.
+pub fn write_request_with_headers<W: std::io::Write, X: EwsWrite<W>>(
     sink: W,
+    headers: &[RequestHeader],
     body: X,
-) -> Result<(), writer::Error> {
-    let mut writer = xml::EventWriter::new(sink);
+) -> Result<(), EwsError> {
+    write_request_with_config(sink, writer::EmitterConfig::new(), headers, body)
+}
+
+/// Writes a SOAP request as [`write_request_with_headers`] does, using the
+/// given configuration for the underlying `xml-rs` writer.
+///
+/// The default configuration writes an XML declaration with the document's
+/// encoding and no indentation, which is what [`write_request`] sends over
+/// the wire. Enabling `perform_indent` makes requests easier to read when
+/// debugging.
+pub fn write_request_with_config<W: std::io::Write, X: EwsWrite<W>>(
+    sink: W,
+    config: writer::EmitterConfig,
+    headers: &[RequestHeader],
+    body: X,
+) -> Result<(), EwsError> {
+    let mut writer = config.create_writer(sink);
 
     writer.write(
         xml::writer::XmlEvent::start_element("soap:Envelope")
             .ns("soap", SOAP_NS_URI)
             .ns("t", TYPES_NS_URI),
     )?;
+
+    if !headers.is_empty() {
+        writer.write(xml::writer::XmlEvent::start_element("soap:Header"))?;
+        for header in headers {
+            header.write(&mut writer)?;
+        }
+        writer.write(xml::writer::XmlEvent::end_element())?;
+    }
+
     writer.write(xml::writer::XmlEvent::start_element("soap:Body"))?;
 
     body.write(&mut writer)?;
 
     writer.write(xml::writer::XmlEvent::end_element())?;
+    writer.write(xml::writer::XmlEvent::end_element())?;
+
+    Ok(())
+}
+
+/// Parses the body of a response to a SOAP request.
+///
+/// A UTF-8 byte order mark at the start of the response is skipped, as some
+/// servers send one and not every version of `xml-rs` copes with it.
+pub fn read_response(response: &str) -> Result<SoapEnvelope, EwsError> {
+    let response = response.strip_prefix(UTF8_BOM).unwrap_or(response);
+    Ok(serde_xml_rs::from_str(response)?)
+}
+
+/// An estimate of the number of bytes taken up by the SOAP envelope around a
+/// request body.
+const ENVELOPE_SIZE_HINT: usize = 256;
+
+/// Writes a struct as the body of a SOAP request, as [`write_request`] does,
+/// and returns the result as a string.
+///
+/// The buffer backing the string is preallocated based on the body's
+/// [`EwsWrite::size_hint`].
+pub fn request_to_string<X: for<'a> EwsWrite<&'a mut Vec<u8>>>(
+    body: X,
+) -> Result<String, EwsError> {
+    request_to_string_with_headers(&default_headers(), body)
+}
+
+/// Writes a struct as the body of a SOAP request, preceded by the given
+/// headers instead of the default ones, and returns the result as a string.
+pub fn request_to_string_with_headers<X: for<'a> EwsWrite<&'a mut Vec<u8>>>(
+    headers: &[RequestHeader],
+    body: X,
+) -> Result<String, EwsError> {
+    let capacity = ENVELOPE_SIZE_HINT + body.size_hint();
+    write_to_string(headers, body, capacity)
+}
+
+/// Writes a struct as the body of a SOAP request and returns the result as a
+/// string, preallocating `capacity` bytes for it.
+///
+/// This is useful to avoid reallocations when the caller has a better idea of
+/// the request's size than [`EwsWrite::size_hint`]. The hint only affects
+/// performance, never the output.
+pub fn request_to_string_with_capacity<X: for<'a> EwsWrite<&'a mut Vec<u8>>>(
+    body: X,
+    capacity: usize,
+) -> Result<String, EwsError> {
+    write_to_string(&default_headers(), body, capacity)
+}
+
+fn write_to_string<X: for<'a> EwsWrite<&'a mut Vec<u8>>>(
+    headers: &[RequestHeader],
+    body: X,
+    capacity: usize,
+) -> Result<String, EwsError> {
+    let mut buf = Vec::with_capacity(capacity);
+    write_request_with_headers(&mut buf, headers, body)?;
+
+    // `xml-rs` only ever emits UTF-8, so this shouldn't fail in practice.
+    String::from_utf8(buf).map_err(|err| {
+        writer::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, err)).into()
+    })
+}
+
+/// Writes a single element, rather than a whole request, and returns the
+/// result as a string without an XML declaration.
+///
+/// Namespace prefixes such as `t:` are written as they are, without being
+/// declared, so this is mostly useful to check the output of an [`EwsWrite`]
+/// implementation in isolation.
+pub fn element_to_string<X: for<'a> EwsWrite<&'a mut Vec<u8>>>(
+    element: &X,
+) -> Result<String, EwsError> {
+    let mut buf = Vec::with_capacity(element.size_hint());
+    let mut writer = writer::EmitterConfig::new()
+        .write_document_declaration(false)
+        .create_writer(&mut buf);
+    element.write(&mut writer)?;
+
+    // `xml-rs` only ever emits UTF-8, so this shouldn't fail in practice.
+    String::from_utf8(buf).map_err(|err| {
+        writer::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, err)).into()
+    })
+}
+
+/// Writes an element with the given name whose only content is the text
+/// representation of `value`.
+///
+/// This saves [`EwsWrite`] implementations from needing a dedicated type for
+/// each plain text field.
+///
+/// Strings, booleans and integers have no element name of their own, so the
+/// name always comes from the field being written. Their `Display` output
+/// already matches the schema's lexical forms: `bool` is written as `true` or
+/// `false` and integers in decimal.
+///
+/// The text is written as character data, which `xml-rs` escapes, so values
+/// may freely contain characters such as `<` and `&`. Implementations writing
+/// text directly should likewise go through [`XmlEvent::characters`] rather
+/// than writing to the underlying sink.
+///
+/// [`XmlEvent::characters`]: xml::writer::XmlEvent::characters
+pub fn write_text_element<W: std::io::Write, T: Display>(
+    writer: &mut xml::EventWriter<W>,
+    name: &str,
+    value: T,
+) -> Result<(), writer::Error> {
+    writer.write(xml::writer::XmlEvent::start_element(name))?;
+    writer.write(xml::writer::XmlEvent::characters(&value.to_string()))?;
     writer.write(xml::writer::XmlEvent::end_element())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{
+        BasePoint, BaseShape, DistinguishedFolderIdName, ExchangeVersion, FindItem, FolderId,
+        IndexedPageItemView, ItemShape, SoapHeader, Traversal,
+    };
+
+    struct Subjected {
+        subject: String,
+    }
+
+    impl<W: std::io::Write> EwsWrite<W> for Subjected {
+        fn write(&self, writer: &mut xml::EventWriter<W>) -> Result<(), writer::Error> {
+            writer.write(xml::writer::XmlEvent::start_element("Subjected"))?;
+            write_text_element(writer, "t:Subject", &self.subject)?;
+            writer.write(xml::writer::XmlEvent::end_element())
+        }
+    }
+
+    #[test]
+    fn plain_string_field_is_written_as_named_element() {
+        let mut buf = Vec::new();
+        write_request(
+            &mut buf,
+            Subjected {
+                subject: "Hello, world".to_string(),
+            },
+        )
+        .expect("writing should succeed");
+
+        let xml = String::from_utf8(buf).unwrap();
+        assert!(
+            xml.contains("<Subjected><t:Subject>Hello, world</t:Subject></Subjected>"),
+            "unexpected output: {xml}"
+        );
+    }
+
+    #[test]
+    fn reserved_characters_are_escaped() {
+        let mut buf = Vec::new();
+        write_request(
+            &mut buf,
+            Subjected {
+                subject: "Q3 <draft> & notes".to_string(),
+            },
+        )
+        .expect("writing should succeed");
+
+        let xml = String::from_utf8(buf).unwrap();
+        assert!(
+            xml.contains("<t:Subject>Q3 &lt;draft&gt; &amp; notes</t:Subject>"),
+            "unexpected output: {xml}"
+        );
+
+        // Round-tripping through a parser gives the original text back.
+        let start = xml.find("<t:Subject>").unwrap();
+        let end = xml.find("</t:Subject>").unwrap() + "</t:Subject>".len();
+        let subject = &xml[start..end].replace("t:Subject", "Subject");
+        let parsed: String = serde_xml_rs::from_str(subject).unwrap();
+        assert_eq!(parsed, "Q3 <draft> & notes");
+    }
+
+    #[test]
+    fn primitives_are_written_as_text() {
+        let mut writer = xml::EventWriter::new(Vec::new());
+        writer
+            .write(xml::writer::XmlEvent::start_element("Primitives"))
+            .unwrap();
+        write_text_element(&mut writer, "Str", "text").unwrap();
+        write_text_element(&mut writer, "String", "text".to_string()).unwrap();
+        write_text_element(&mut writer, "True", true).unwrap();
+        write_text_element(&mut writer, "False", false).unwrap();
+        write_text_element(&mut writer, "Unsigned", 50u32).unwrap();
+        write_text_element(&mut writer, "Signed", -7i64).unwrap();
+        writer.write(xml::writer::XmlEvent::end_element()).unwrap();
+
+        let xml = String::from_utf8(writer.into_inner()).unwrap();
+        assert!(
+            xml.ends_with(concat!(
+                "<Primitives><Str>text</Str><String>text</String>",
+                "<True>true</True><False>false</False>",
+                "<Unsigned>50</Unsigned><Signed>-7</Signed></Primitives>"
+            )),
+            "unexpected output: {xml}"
+        );
+    }
+
+    #[test]
+    fn request_can_be_pretty_printed() {
+        let subjected = || Subjected {
+            subject: "Hello, world".to_string(),
+        };
+
+        let mut buf = Vec::new();
+        write_request(&mut buf, subjected()).unwrap();
+        let compact = String::from_utf8(buf).unwrap();
+        assert!(
+            compact.starts_with(r#"<?xml version="1.0" encoding="UTF-8"?>"#),
+            "unexpected output: {compact}"
+        );
+        assert!(!compact.contains('\n'), "unexpected output: {compact}");
+
+        let mut buf = Vec::new();
+        write_request_with_config(
+            &mut buf,
+            writer::EmitterConfig::new().perform_indent(true),
+            &[],
+            subjected(),
+        )
+        .unwrap();
+        let pretty = String::from_utf8(buf).unwrap();
+        assert!(
+            pretty.starts_with(r#"<?xml version="1.0" encoding="UTF-8"?>"#),
+            "unexpected output: {pretty}"
+        );
+        assert!(
+            pretty.contains("\n  <soap:Body>\n    <Subjected>"),
+            "unexpected output: {pretty}"
+        );
+    }
+
+    #[test]
+    fn find_item_matches_fixture() {
+        let find_item = FindItem::new(
+            Traversal::Shallow,
+            ItemShape {
+                base_shape: BaseShape::IdOnly,
+                additional_properties: None,
+            },
+            vec![FolderId::DistinguishedFolderId {
+                id: DistinguishedFolderIdName::Inbox,
+                change_key: None,
+                mailbox: None,
+            }],
+        )
+        .with_view(IndexedPageItemView {
+            max_entries_returned: Some(50),
+            offset: 0,
+            base_point: BasePoint::Beginning,
+        });
+
+        let xml = request_to_string(find_item).expect("writing should succeed");
+        assert_eq!(
+            xml,
+            include_str!("../fixtures/find_item_request.xml").trim_end()
+        );
+    }
+
+    #[test]
+    fn capacity_hint_does_not_affect_output() {
+        let subjected = || Subjected {
+            subject: "Hello, world".to_string(),
+        };
+
+        let mut expected = Vec::new();
+        write_request(&mut expected, subjected()).unwrap();
+        let expected = String::from_utf8(expected).unwrap();
+
+        assert_eq!(request_to_string(subjected()).unwrap(), expected);
+        for capacity in [0, 1, 4096] {
+            assert_eq!(
+                request_to_string_with_capacity(subjected(), capacity).unwrap(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn response_with_bom_is_parsed() {
+        let response = concat!(
+            "\u{feff}",
+            r#"<?xml version="1.0" encoding="utf-8"?>"#,
+            r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/"><s:Body>"#,
+            "<ExportItemsResponse>hello</ExportItemsResponse>",
+            "</s:Body></s:Envelope>"
+        );
+
+        let envelope = read_response(response).expect("parsing should succeed");
+        assert!(matches!(
+            envelope.body.contents,
+            crate::types::Response::ExportItemsResponse(_)
+        ));
+    }
+
+    #[test]
+    fn malformed_response_is_a_deserialization_error() {
+        let err = match read_response("<s:Envelope>") {
+            Ok(_) => panic!("parsing should fail"),
+            Err(err) => err,
+        };
+
+        assert!(
+            matches!(err, crate::Error::Deserialize(_)),
+            "unexpected error: {err}"
+        );
+    }
+
+    #[test]
+    fn server_version_header_precedes_body() {
+        let header = RequestHeader::new(SoapHeader::RequestServerVersion {
+            version: ExchangeVersion::default(),
+        });
+        let subjected = Subjected {
+            subject: "Hello, world".to_string(),
+        };
+
+        let mut buf = Vec::new();
+        write_request_with_headers(&mut buf, &[header], subjected).unwrap();
+        let xml = String::from_utf8(buf).unwrap();
+        assert!(
+            xml.contains(concat!(
+                r#"<soap:Header><t:RequestServerVersion Version="Exchange2013_SP1" />"#,
+                "</soap:Header><soap:Body><Subjected>"
+            )),
+            "unexpected output: {xml}"
+        );
+    }
+
+    #[test]
+    fn must_understand_is_only_written_when_requested() {
+        let impersonation = || {
+            RequestHeader::new(SoapHeader::ExchangeImpersonation {
+                primary_smtp_address: "alice@example.com".to_string(),
+            })
+        };
+        let subjected = || Subjected {
+            subject: "Hello, world".to_string(),
+        };
+
+        let mut buf = Vec::new();
+        write_request_with_headers(&mut buf, &[impersonation()], subjected()).unwrap();
+        let xml = String::from_utf8(buf).unwrap();
+        assert!(
+            xml.contains(concat!(
+                "<soap:Header><t:ExchangeImpersonation><t:ConnectingSID>",
+                "<t:PrimarySmtpAddress>alice@example.com</t:PrimarySmtpAddress>",
+                "</t:ConnectingSID></t:ExchangeImpersonation></soap:Header>"
+            )),
+            "unexpected output: {xml}"
+        );
+
+        let mut buf = Vec::new();
+        write_request_with_headers(
+            &mut buf,
+            &[impersonation().must_understand(true)],
+            subjected(),
+        )
+        .unwrap();
+        let xml = String::from_utf8(buf).unwrap();
+        assert!(
+            xml.contains(r#"<t:ExchangeImpersonation soap:mustUnderstand="1">"#),
+            "unexpected output: {xml}"
+        );
+
+        let mut buf = Vec::new();
+        write_request_with_headers(&mut buf, &[], subjected()).unwrap();
+        let xml = String::from_utf8(buf).unwrap();
+        assert!(!xml.contains("soap:Header"), "unexpected output: {xml}");
+    }
+
+    #[test]
+    fn server_version_is_sent_by_default() {
+        let subjected = || Subjected {
+            subject: "Hello, world".to_string(),
+        };
+        let expected = concat!(
+            r#"<soap:Header><t:RequestServerVersion Version="Exchange2013_SP1" />"#,
+            "</soap:Header><soap:Body><Subjected>"
+        );
+
+        let mut buf = Vec::new();
+        write_request(&mut buf, subjected()).unwrap();
+        let xml = String::from_utf8(buf).unwrap();
+        assert!(xml.contains(expected), "unexpected output: {xml}");
+
+        let xml = request_to_string(subjected()).unwrap();
+        assert!(xml.contains(expected), "unexpected output: {xml}");
+    }
+
+    #[test]
+    fn single_element_is_written_to_string() {
+        assert_eq!(
+            element_to_string(&BaseShape::IdOnly).unwrap(),
+            "<t:BaseShape>IdOnly</t:BaseShape>"
+        );
+    }
+}
@@ -4,10 +4,21 @@
 
 //! Support for the autoconfig XML format
 
+use std::io::{Read, Write};
+
+use email_address::EmailAddress;
 use serde::Deserialize;
+use xml::writer::{self, EmitterConfig, EventWriter, XmlEvent};
+
+/// The byte order mark some servers prepend to UTF-8 documents.
+const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+
+/// The major version of the autoconfig format understood by this module.
+const SUPPORTED_MAJOR_VERSION: &str = "1";
 
-#[derive(Debug)]
-enum AuthenticationMethod {
+#[derive(Debug, PartialEq, Eq)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum AuthenticationMethod {
     None,
     PasswordCleartext,
     PasswordEncrypted,
@@ -22,24 +33,112 @@ enum AuthenticationMethod {
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "lowercase")]
-enum ServerKind {
+#[allow(clippy::upper_case_acronyms)]
+pub enum ServerKind {
     POP3,
     IMAP,
     SMTP,
 }
 
 #[derive(Debug, Deserialize)]
-enum SocketKind {
+#[allow(clippy::upper_case_acronyms)]
+pub enum SocketKind {
     /// Unencrypted
+    #[serde(rename = "plain")]
     Plain,
 
     /// SSL3/TLS1
     SSL,
 
     /// Upgrade to TLS on plain socket
+    #[serde(rename = "STARTTLS")]
     StartTLS,
 }
 
+impl ServerKind {
+    /// The value of the `type` attribute for this kind of server.
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::POP3 => "pop3",
+            Self::IMAP => "imap",
+            Self::SMTP => "smtp",
+        }
+    }
+
+    /// The well-known ports for this kind of server over implicit TLS.
+    fn implicit_tls_ports(&self) -> &'static [u16] {
+        match self {
+            Self::POP3 => &[995],
+            Self::IMAP => &[993],
+            Self::SMTP => &[465],
+        }
+    }
+
+    /// The well-known ports for this kind of server over a plain socket,
+    /// possibly upgraded with STARTTLS.
+    fn plain_ports(&self) -> &'static [u16] {
+        match self {
+            Self::POP3 => &[110],
+            Self::IMAP => &[143],
+            Self::SMTP => &[25, 587],
+        }
+    }
+}
+
+impl SocketKind {
+    /// The value of the `socketType` element for this kind of socket.
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Plain => "plain",
+            Self::SSL => "SSL",
+            Self::StartTLS => "STARTTLS",
+        }
+    }
+}
+
+/// How TLS is used on a connection to a server
+#[derive(Debug, PartialEq, Eq)]
+pub enum TlsMode {
+    /// TLS is negotiated as soon as the connection is opened
+    Implicit,
+
+    /// The connection starts in plain text and is upgraded with STARTTLS
+    StartTls,
+
+    /// The connection is never encrypted
+    None,
+}
+
+/// A possible problem with the port a server is configured to use
+#[derive(Debug, PartialEq, Eq)]
+pub enum PortWarning {
+    /// The connection is never encrypted, so credentials and messages are
+    /// sent in the clear
+    Unencrypted,
+
+    /// The port is the well-known one for the other way of using TLS, which
+    /// suggests the socket type is wrong
+    MismatchedPort,
+}
+
+impl AuthenticationMethod {
+    /// The value of the `authentication` element for this method.
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::PasswordCleartext => "password-cleartext",
+            Self::PasswordEncrypted => "password-encrypted",
+            Self::NTLM => "NTLM",
+            Self::GSSAPI => "GSSAPI",
+            Self::ClientIPAddress => "client-IP-address",
+            Self::TLSClientCert => "TLS-client-cert",
+            Self::OAuth2 => "OAuth2",
+            Self::HTTPBasic => "http-basic",
+            Self::HTTPDigest => "http-digest",
+        }
+    }
+}
+
 impl<'de> Deserialize<'de> for AuthenticationMethod {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -64,96 +163,429 @@ impl<'de> Deserialize<'de> for AuthenticationMethod {
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
-struct Server {
-    #[serde(rename(deserialize = "type"))]
-    kind: ServerKind,
+pub struct Server {
+    #[serde(rename = "type")]
+    pub kind: ServerKind,
 
     /// Remote hostname
-    hostname: String,
+    pub hostname: String,
 
     /// Username substitution to apply
-    username: String,
+    pub username: String,
 
     /// Remote port
-    port: u16,
+    pub port: u16,
 
     /// Kind of socket in use
-    #[serde(rename(deserialize = "socketType"))]
-    socket_kind: SocketKind,
+    #[serde(rename = "socketType")]
+    pub socket_kind: SocketKind,
 
     /// Supported authentication methods
-    authentication: Vec<AuthenticationMethod>,
+    pub authentication: Vec<AuthenticationMethod>,
 
     /// Possible restrictions on auth
-    restriction: Option<Vec<AuthenticationMethod>>,
+    pub restriction: Option<Vec<AuthenticationMethod>>,
+}
+
+impl Server {
+    /// The username to log in with for the given email address.
+    ///
+    /// The `%EMAILADDRESS%`, `%EMAILLOCALPART%` and `%EMAILDOMAIN%`
+    /// placeholders are replaced with the matching parts of `email`, as the
+    /// user entered it.
+    pub fn resolve_username(&self, email: &EmailAddress) -> String {
+        self.username
+            .replace("%EMAILADDRESS%", email.as_str())
+            .replace("%EMAILLOCALPART%", email.local_part())
+            .replace("%EMAILDOMAIN%", email.domain())
+    }
+
+    /// How TLS should be used when connecting to the server.
+    pub fn effective_tls(&self) -> TlsMode {
+        match self.socket_kind {
+            SocketKind::Plain => TlsMode::None,
+            SocketKind::SSL => TlsMode::Implicit,
+            SocketKind::StartTLS => TlsMode::StartTls,
+        }
+    }
+
+    /// Checks the server's port against its socket type, returning a warning
+    /// if the connection would be insecure or the port looks wrong.
+    pub fn port_warning(&self) -> Option<PortWarning> {
+        match self.effective_tls() {
+            TlsMode::None => Some(PortWarning::Unencrypted),
+            TlsMode::Implicit if self.kind.plain_ports().contains(&self.port) => {
+                Some(PortWarning::MismatchedPort)
+            }
+            TlsMode::StartTls if self.kind.implicit_tls_ports().contains(&self.port) => {
+                Some(PortWarning::MismatchedPort)
+            }
+            _ => None,
+        }
+    }
+
+    /// The most preferred authentication method which is also in `supported`.
+    ///
+    /// Methods are listed in order of preference, and any which are in
+    /// [restriction](Self::restriction) are never picked.
+    pub fn preferred_auth(
+        &self,
+        supported: &[AuthenticationMethod],
+    ) -> Option<&AuthenticationMethod> {
+        let restriction = self.restriction.as_deref().unwrap_or_default();
+
+        self.authentication
+            .iter()
+            .filter(|method| !restriction.contains(method))
+            .find(|method| supported.contains(method))
+    }
 }
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
-struct EmailDocumentation {
-    url: String,
-    #[serde(rename(deserialize = "descr"))]
-    description: String,
+pub struct EmailDocumentation {
+    pub url: String,
+    #[serde(rename = "descr")]
+    pub description: String,
+}
+
+/// A step the user must take before the servers can be used
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Enable {
+    /// Page on which the step can be carried out
+    #[serde(rename = "visiturl")]
+    pub visit_url: String,
+
+    /// Description of the step, possibly in several languages
+    #[serde(rename = "instruction", default)]
+    pub instructions: Vec<Instruction>,
+}
+
+/// Human-readable text describing a step
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Instruction {
+    /// Language of the text, if not English
+    pub lang: Option<String>,
+
+    #[serde(rename = "$value")]
+    pub text: String,
 }
 
 /// Contains the matching domains and connection settings
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
-struct EmailProvider {
+pub struct EmailProvider {
     /// Unique identity for the provider
-    id: String,
+    pub id: String,
 
     /// Domains serviced by this provider
-    #[serde(rename(deserialize = "domain"))]
-    domains: Vec<String>,
+    #[serde(rename = "domain")]
+    pub domains: Vec<String>,
 
     /// Primary name within the UI
-    display_name: String,
+    pub display_name: String,
 
     /// Shortened name for UI purposes
-    display_short_name: String,
+    pub display_short_name: String,
 
     /// Links to documentation
-    documentation: Vec<EmailDocumentation>,
+    pub documentation: Vec<EmailDocumentation>,
+
+    pub incoming_server: Vec<Server>,
+    pub outgoing_server: Vec<Server>,
 
-    incoming_server: Vec<Server>,
-    outgoing_server: Vec<Server>,
+    /// Optional step required to enable access to the servers
+    pub enable: Option<Enable>,
 }
 
 /// Contains OAuth2 negotiation settings
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
-struct OAuth2 {
+pub struct OAuth2 {
     /// Token issuing authority
-    issuer: String,
+    pub issuer: String,
+
+    /// Space-separated scopes to request
+    pub scope: Option<String>,
+
+    /// Authorization endpoint
+    #[serde(rename = "authURL")]
+    pub auth_url: Option<String>,
+
+    /// Token endpoint
+    #[serde(rename = "tokenURL")]
+    pub token_url: Option<String>,
 }
 
 /// Contains links for the WebMail implementation
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
-struct WebMail {}
+pub struct WebMail {}
+
+/// Points to a newer version of the configuration
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientConfigUpdate {
+    /// Location of the updated configuration
+    pub url: String,
+}
 
 /// A deserialized autoconfig XML file, containing at minimum
 /// an [EmailProvider]
 #[derive(Deserialize, Debug)]
-#[serde(rename_all = "camelCase")]
-struct AutoconfigXML {
+#[serde(rename = "clientConfig", rename_all = "camelCase")]
+pub struct AutoconfigXML {
+    /// Version of the format the document is written in, such as "1.1"
+    #[serde(default, deserialize_with = "deserialize_version")]
+    pub version: Option<String>,
+
     /// Mandatory email provider record
-    email_provider: EmailProvider,
+    pub email_provider: EmailProvider,
 
     /// Optional OAuth2 info
-    #[serde(rename(deserialize = "oAuth2"))]
-    oauth2: Option<OAuth2>,
+    #[serde(rename = "oAuth2")]
+    pub oauth2: Option<OAuth2>,
 
     /// Optional WebMail info
-    web_mail: Option<WebMail>,
+    pub web_mail: Option<WebMail>,
+
+    /// Optional location of a newer configuration
+    pub client_config_update: Option<ClientConfigUpdate>,
+
+    /// Optional step required to enable access to the servers, for documents
+    /// which place it outside of the [EmailProvider]
+    pub enable: Option<Enable>,
+}
+
+/// Deserializes the `version` attribute, rejecting major versions which may
+/// not be laid out the way this module expects.
+fn deserialize_version<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let version = String::deserialize(deserializer)?;
+
+    let major = version.split('.').next().unwrap_or_default();
+    if major != SUPPORTED_MAJOR_VERSION {
+        return Err(serde::de::Error::custom(format!(
+            "unsupported autoconfig version {version}"
+        )));
+    }
+
+    Ok(Some(version))
+}
+
+impl AutoconfigXML {
+    /// The URL from which a fresher copy of this configuration can be
+    /// fetched, if the provider publishes one.
+    pub fn update_url(&self) -> Option<&str> {
+        self.client_config_update
+            .as_ref()
+            .map(|update| update.url.as_str())
+    }
+
+    /// The step the user must take before the servers can be used, if any.
+    pub fn enable(&self) -> Option<&Enable> {
+        self.email_provider.enable.as_ref().or(self.enable.as_ref())
+    }
+}
+
+/// Parses an autoconfig XML document.
+///
+/// A UTF-8 byte order mark at the start of the document is skipped, as some
+/// servers send one and not every version of `xml-rs` copes with it.
+///
+/// ```
+/// use ispdb_rs::autoconfig::{parse_autoconfig, ServerKind};
+///
+/// let document = r#"<clientConfig version="1.1">
+///   <emailProvider id="example.com">
+///     <domain>example.com</domain>
+///     <displayName>Example Mail</displayName>
+///     <displayShortName>Example</displayShortName>
+///     <incomingServer type="imap">
+///       <hostname>imap.example.com</hostname>
+///       <port>993</port>
+///       <socketType>SSL</socketType>
+///       <username>%EMAILADDRESS%</username>
+///       <authentication>password-cleartext</authentication>
+///     </incomingServer>
+///     <outgoingServer type="smtp">
+///       <hostname>smtp.example.com</hostname>
+///       <port>465</port>
+///       <socketType>SSL</socketType>
+///       <username>%EMAILADDRESS%</username>
+///       <authentication>password-cleartext</authentication>
+///     </outgoingServer>
+///     <documentation url="https://example.com/help/email">
+///       <descr>How to set up your email client</descr>
+///     </documentation>
+///   </emailProvider>
+/// </clientConfig>"#;
+///
+/// let config = parse_autoconfig(document.as_bytes()).unwrap();
+/// let provider = &config.email_provider;
+/// assert_eq!(provider.display_name, "Example Mail");
+///
+/// let incoming = &provider.incoming_server[0];
+/// assert!(matches!(incoming.kind, ServerKind::IMAP));
+/// assert_eq!(incoming.hostname, "imap.example.com");
+/// assert_eq!(incoming.port, 993);
+/// ```
+pub fn parse_autoconfig(mut reader: impl Read) -> Result<AutoconfigXML, serde_xml_rs::Error> {
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf)?;
+
+    let document = buf.strip_prefix(UTF8_BOM).unwrap_or(&buf);
+    serde_xml_rs::from_reader(document)
+}
+
+/// Writes an autoconfig XML document.
+///
+/// The document is written by hand rather than through `serde-xml-rs`, whose
+/// serializer can't write attributes on elements in a sequence, such as the
+/// `type` of each `incomingServer`.
+pub fn write_autoconfig(value: &AutoconfigXML, writer: impl Write) -> Result<(), writer::Error> {
+    let mut writer = EmitterConfig::new()
+        .perform_indent(true)
+        .create_writer(writer);
+
+    let root = XmlEvent::start_element("clientConfig");
+    writer.write(match &value.version {
+        Some(version) => root.attr("version", version),
+        None => root,
+    })?;
+
+    let provider = &value.email_provider;
+    writer.write(XmlEvent::start_element("emailProvider").attr("id", &provider.id))?;
+    for domain in &provider.domains {
+        write_text_element(&mut writer, "domain", domain)?;
+    }
+    write_text_element(&mut writer, "displayName", &provider.display_name)?;
+    write_text_element(
+        &mut writer,
+        "displayShortName",
+        &provider.display_short_name,
+    )?;
+    for server in &provider.incoming_server {
+        write_server(&mut writer, "incomingServer", server)?;
+    }
+    for server in &provider.outgoing_server {
+        write_server(&mut writer, "outgoingServer", server)?;
+    }
+    for documentation in &provider.documentation {
+        writer.write(XmlEvent::start_element("documentation").attr("url", &documentation.url))?;
+        write_text_element(&mut writer, "descr", &documentation.description)?;
+        writer.write(XmlEvent::end_element())?;
+    }
+    if let Some(enable) = &provider.enable {
+        write_enable(&mut writer, enable)?;
+    }
+    writer.write(XmlEvent::end_element())?;
+
+    if let Some(oauth2) = &value.oauth2 {
+        writer.write(XmlEvent::start_element("oAuth2"))?;
+        write_text_element(&mut writer, "issuer", &oauth2.issuer)?;
+        if let Some(scope) = &oauth2.scope {
+            write_text_element(&mut writer, "scope", scope)?;
+        }
+        if let Some(auth_url) = &oauth2.auth_url {
+            write_text_element(&mut writer, "authURL", auth_url)?;
+        }
+        if let Some(token_url) = &oauth2.token_url {
+            write_text_element(&mut writer, "tokenURL", token_url)?;
+        }
+        writer.write(XmlEvent::end_element())?;
+    }
+
+    if let Some(enable) = &value.enable {
+        write_enable(&mut writer, enable)?;
+    }
+
+    if value.web_mail.is_some() {
+        writer.write(XmlEvent::start_element("webMail"))?;
+        writer.write(XmlEvent::end_element())?;
+    }
+
+    if let Some(update) = &value.client_config_update {
+        writer.write(XmlEvent::start_element("clientConfigUpdate").attr("url", &update.url))?;
+        writer.write(XmlEvent::end_element())?;
+    }
+
+    writer.write(XmlEvent::end_element())
+}
+
+/// Writes a server's settings as an element with the given name.
+fn write_server<W: Write>(
+    writer: &mut EventWriter<W>,
+    name: &str,
+    server: &Server,
+) -> Result<(), writer::Error> {
+    writer.write(XmlEvent::start_element(name).attr("type", server.kind.as_str()))?;
+    write_text_element(writer, "hostname", &server.hostname)?;
+    write_text_element(writer, "port", &server.port.to_string())?;
+    write_text_element(writer, "socketType", server.socket_kind.as_str())?;
+    write_text_element(writer, "username", &server.username)?;
+    for method in &server.authentication {
+        write_text_element(writer, "authentication", method.as_str())?;
+    }
+    for method in server.restriction.iter().flatten() {
+        write_text_element(writer, "restriction", method.as_str())?;
+    }
+    writer.write(XmlEvent::end_element())
+}
+
+/// Writes an `enable` element and its instructions.
+fn write_enable<W: Write>(
+    writer: &mut EventWriter<W>,
+    enable: &Enable,
+) -> Result<(), writer::Error> {
+    writer.write(XmlEvent::start_element("enable").attr("visiturl", &enable.visit_url))?;
+    for instruction in &enable.instructions {
+        let element = XmlEvent::start_element("instruction");
+        writer.write(match &instruction.lang {
+            Some(lang) => element.attr("lang", lang),
+            None => element,
+        })?;
+        writer.write(XmlEvent::characters(&instruction.text))?;
+        writer.write(XmlEvent::end_element())?;
+    }
+    writer.write(XmlEvent::end_element())
+}
+
+/// Writes an element containing only the given text.
+fn write_text_element<W: Write>(
+    writer: &mut EventWriter<W>,
+    name: &str,
+    text: &str,
+) -> Result<(), writer::Error> {
+    writer.write(XmlEvent::start_element(name))?;
+    writer.write(XmlEvent::characters(text))?;
+    writer.write(XmlEvent::end_element())
 }
 
 #[cfg(test)]
 mod tests {
     use std::io::Cursor;
 
-    use super::AutoconfigXML;
+    use super::{
+        parse_autoconfig, write_autoconfig, AuthenticationMethod, AutoconfigXML, PortWarning,
+        Server, ServerKind, SocketKind, TlsMode, UTF8_BOM,
+    };
+
+    fn server_with_username(username: &str) -> Server {
+        Server {
+            kind: ServerKind::IMAP,
+            hostname: "imap.example.com".to_string(),
+            username: username.to_string(),
+            port: 993,
+            socket_kind: SocketKind::SSL,
+            authentication: vec![AuthenticationMethod::PasswordCleartext],
+            restriction: None,
+        }
+    }
 
     #[test]
     fn test_basic() {
@@ -161,9 +593,302 @@ mod tests {
         let a: AutoconfigXML = serde_xml_rs::from_reader(Cursor::new(test_file)).unwrap();
         assert_eq!(a.email_provider.id, "googlemail.com");
         assert_eq!(a.email_provider.domains.len(), 4);
+        assert_eq!(a.update_url(), None);
         eprintln!("AutoConfigXML: {a:?}");
 
         let oauth2 = a.oauth2.expect("Require oAuth2 spec");
         assert_eq!(oauth2.issuer, "accounts.google.com");
+        assert_eq!(
+            oauth2.scope.as_deref(),
+            Some("https://mail.google.com/ https://www.googleapis.com/auth/contacts https://www.googleapis.com/auth/calendar https://www.googleapis.com/auth/carddav")
+        );
+        assert_eq!(
+            oauth2.auth_url.as_deref(),
+            Some("https://accounts.google.com/o/oauth2/auth")
+        );
+        assert_eq!(
+            oauth2.token_url.as_deref(),
+            Some("https://www.googleapis.com/oauth2/v3/token")
+        );
+    }
+
+    #[test]
+    fn test_oauth2_issuer_only() {
+        let test_file = include_str!("../autoconfig/ispdb/googlemail.com.xml");
+        let document: String = test_file
+            .lines()
+            .filter(|line| {
+                !["<scope>", "<authURL>", "<tokenURL>"]
+                    .iter()
+                    .any(|tag| line.trim_start().starts_with(tag))
+            })
+            .collect();
+
+        let a = parse_autoconfig(document.as_bytes()).unwrap();
+        let oauth2 = a.oauth2.as_ref().expect("Require oAuth2 spec");
+        assert_eq!(oauth2.issuer, "accounts.google.com");
+        assert_eq!(oauth2.scope, None);
+        assert_eq!(oauth2.auth_url, None);
+        assert_eq!(oauth2.token_url, None);
+
+        // Absent fields are left out when writing, rather than written empty.
+        let mut written = Vec::new();
+        write_autoconfig(&a, &mut written).unwrap();
+        let written = String::from_utf8(written).unwrap();
+        assert!(!written.contains("<scope"), "unexpected output: {written}");
+
+        let b = parse_autoconfig(written.as_bytes()).unwrap();
+        let oauth2 = b.oauth2.expect("Require oAuth2 spec");
+        assert_eq!(oauth2.issuer, "accounts.google.com");
+        assert_eq!(oauth2.scope, None);
+    }
+
+    #[test]
+    fn test_enable() {
+        let test_file = include_bytes!("../fixtures/enable.xml");
+
+        let a = parse_autoconfig(test_file.as_slice()).unwrap();
+        let enable = a.enable().expect("Require enable block");
+        assert_eq!(enable.visit_url, "https://example.com/settings/imap");
+        assert_eq!(enable.instructions.len(), 2);
+        assert_eq!(enable.instructions[0].lang, None);
+        assert_eq!(
+            enable.instructions[0].text,
+            "Allow IMAP access in your account settings"
+        );
+        assert_eq!(enable.instructions[1].lang.as_deref(), Some("de"));
+
+        // Gmail's document places the block outside of the email provider.
+        let test_file = include_bytes!("../autoconfig/ispdb/googlemail.com.xml");
+
+        let a = parse_autoconfig(test_file.as_slice()).unwrap();
+        assert!(a.email_provider.enable.is_none());
+        let enable = a.enable().expect("Require enable block");
+        assert_eq!(
+            enable.instructions[0].text,
+            "You need to enable IMAP access"
+        );
+    }
+
+    #[test]
+    fn test_bom() {
+        let test_file = include_bytes!("../autoconfig/ispdb/googlemail.com.xml");
+        let document = [UTF8_BOM, test_file].concat();
+
+        let a = parse_autoconfig(document.as_slice()).expect("Failed to parse with BOM");
+        assert_eq!(a.email_provider.id, "googlemail.com");
+    }
+
+    #[test]
+    fn test_client_config_update() {
+        let test_file = include_bytes!("../fixtures/client-config-update.xml");
+
+        let a = parse_autoconfig(test_file.as_slice()).unwrap();
+        assert_eq!(a.version.as_deref(), Some("1.1"));
+        assert_eq!(
+            a.update_url(),
+            Some("https://example.com/autoconfig/config-v1.1.xml")
+        );
+    }
+
+    #[test]
+    fn test_resolve_username() {
+        let email = &"jane.doe@example.com".parse().unwrap();
+
+        let server = server_with_username("%EMAILADDRESS%");
+        assert_eq!(server.resolve_username(email), "jane.doe@example.com");
+
+        let server = server_with_username("%EMAILLOCALPART%");
+        assert_eq!(server.resolve_username(email), "jane.doe");
+
+        let server = server_with_username("%EMAILLOCALPART%+mail@%EMAILDOMAIN%");
+        assert_eq!(server.resolve_username(email), "jane.doe+mail@example.com");
+
+        let server = server_with_username("shared-mailbox");
+        assert_eq!(server.resolve_username(email), "shared-mailbox");
+    }
+
+    #[test]
+    fn test_resolve_username_with_idn_domain() {
+        let email = &"jane.doe@bücher.example".parse().unwrap();
+
+        let server = server_with_username("%EMAILLOCALPART%@%EMAILDOMAIN%");
+        assert_eq!(server.resolve_username(email), "jane.doe@bücher.example");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for test_file in [
+            include_bytes!("../autoconfig/ispdb/googlemail.com.xml").as_slice(),
+            include_bytes!("../fixtures/client-config-update.xml").as_slice(),
+            include_bytes!("../fixtures/enable.xml").as_slice(),
+        ] {
+            let original = parse_autoconfig(test_file).unwrap();
+
+            let mut document = Vec::new();
+            write_autoconfig(&original, &mut document).unwrap();
+
+            let reparsed = parse_autoconfig(document.as_slice()).unwrap();
+            assert_eq!(format!("{reparsed:?}"), format!("{original:?}"));
+        }
+    }
+
+    #[test]
+    fn test_socket_types() {
+        let test_file = include_str!("../fixtures/client-config-update.xml")
+            .replacen("<socketType>SSL", "<socketType>plain", 1)
+            .replacen("<socketType>SSL", "<socketType>STARTTLS", 1);
+
+        let a = parse_autoconfig(test_file.as_bytes()).unwrap();
+        assert!(matches!(
+            a.email_provider.incoming_server[0].socket_kind,
+            SocketKind::Plain
+        ));
+        assert!(matches!(
+            a.email_provider.outgoing_server[0].socket_kind,
+            SocketKind::StartTLS
+        ));
+
+        let mut document = Vec::new();
+        write_autoconfig(&a, &mut document).unwrap();
+
+        let document = String::from_utf8(document).unwrap();
+        assert!(document.contains("<socketType>plain</socketType>"));
+        assert!(document.contains("<socketType>STARTTLS</socketType>"));
+    }
+
+    #[test]
+    fn test_preferred_auth() {
+        let mut server = server_with_username("%EMAILADDRESS%");
+        server.authentication = vec![
+            AuthenticationMethod::OAuth2,
+            AuthenticationMethod::PasswordEncrypted,
+            AuthenticationMethod::PasswordCleartext,
+        ];
+
+        assert_eq!(
+            server.preferred_auth(&[
+                AuthenticationMethod::PasswordCleartext,
+                AuthenticationMethod::OAuth2,
+            ]),
+            Some(&AuthenticationMethod::OAuth2)
+        );
+        assert_eq!(
+            server.preferred_auth(&[AuthenticationMethod::PasswordCleartext]),
+            Some(&AuthenticationMethod::PasswordCleartext)
+        );
+        assert_eq!(server.preferred_auth(&[AuthenticationMethod::NTLM]), None);
+        assert_eq!(server.preferred_auth(&[]), None);
+    }
+
+    #[test]
+    fn test_preferred_auth_restriction() {
+        let mut server = server_with_username("%EMAILADDRESS%");
+        server.authentication = vec![
+            AuthenticationMethod::OAuth2,
+            AuthenticationMethod::PasswordCleartext,
+        ];
+        server.restriction = Some(vec![AuthenticationMethod::OAuth2]);
+
+        let supported = [
+            AuthenticationMethod::OAuth2,
+            AuthenticationMethod::PasswordCleartext,
+        ];
+        assert_eq!(
+            server.preferred_auth(&supported),
+            Some(&AuthenticationMethod::PasswordCleartext)
+        );
+
+        server.restriction = Some(vec![
+            AuthenticationMethod::OAuth2,
+            AuthenticationMethod::PasswordCleartext,
+        ]);
+        assert_eq!(server.preferred_auth(&supported), None);
+    }
+
+    #[test]
+    fn test_effective_tls() {
+        let mut server = server_with_username("%EMAILADDRESS%");
+
+        server.socket_kind = SocketKind::Plain;
+        assert_eq!(server.effective_tls(), TlsMode::None);
+
+        server.socket_kind = SocketKind::SSL;
+        assert_eq!(server.effective_tls(), TlsMode::Implicit);
+
+        server.socket_kind = SocketKind::StartTLS;
+        assert_eq!(server.effective_tls(), TlsMode::StartTls);
+    }
+
+    #[test]
+    fn test_port_warning() {
+        use PortWarning::{MismatchedPort, Unencrypted};
+
+        let cases = [
+            (ServerKind::IMAP, SocketKind::SSL, 993, None),
+            (ServerKind::IMAP, SocketKind::SSL, 143, Some(MismatchedPort)),
+            (ServerKind::IMAP, SocketKind::StartTLS, 143, None),
+            (
+                ServerKind::IMAP,
+                SocketKind::StartTLS,
+                993,
+                Some(MismatchedPort),
+            ),
+            (ServerKind::IMAP, SocketKind::Plain, 143, Some(Unencrypted)),
+            (ServerKind::POP3, SocketKind::SSL, 995, None),
+            (ServerKind::POP3, SocketKind::SSL, 110, Some(MismatchedPort)),
+            (ServerKind::POP3, SocketKind::StartTLS, 110, None),
+            (
+                ServerKind::POP3,
+                SocketKind::StartTLS,
+                995,
+                Some(MismatchedPort),
+            ),
+            (ServerKind::POP3, SocketKind::Plain, 110, Some(Unencrypted)),
+            (ServerKind::SMTP, SocketKind::SSL, 465, None),
+            (ServerKind::SMTP, SocketKind::SSL, 587, Some(MismatchedPort)),
+            (ServerKind::SMTP, SocketKind::StartTLS, 587, None),
+            (ServerKind::SMTP, SocketKind::StartTLS, 25, None),
+            (
+                ServerKind::SMTP,
+                SocketKind::StartTLS,
+                465,
+                Some(MismatchedPort),
+            ),
+            (ServerKind::SMTP, SocketKind::Plain, 25, Some(Unencrypted)),
+            // Non-standard ports can't be checked against the socket type.
+            (ServerKind::IMAP, SocketKind::SSL, 2993, None),
+            (ServerKind::IMAP, SocketKind::StartTLS, 2143, None),
+        ];
+
+        for (kind, socket_kind, port, expected) in cases {
+            let mut server = server_with_username("%EMAILADDRESS%");
+            server.kind = kind;
+            server.socket_kind = socket_kind;
+            server.port = port;
+
+            assert_eq!(server.port_warning(), expected, "{server:?}");
+        }
+    }
+
+    #[test]
+    fn test_version() {
+        let test_file = include_str!("../fixtures/client-config-update.xml");
+
+        let document = test_file.replace(r#"version="1.1""#, r#"version="1.0""#);
+        let a = parse_autoconfig(document.as_bytes()).unwrap();
+        assert_eq!(a.version.as_deref(), Some("1.0"));
+
+        let document = test_file.replace(r#" version="1.1""#, "");
+        let a = parse_autoconfig(document.as_bytes()).unwrap();
+        assert_eq!(a.version, None);
+
+        let document = test_file.replace(r#"version="1.1""#, r#"version="2.0""#);
+        let err = parse_autoconfig(document.as_bytes()).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("unsupported autoconfig version 2.0"),
+            "unexpected error: {err}"
+        );
     }
 }
@@ -0,0 +1,137 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Retrieval of autoconfig documents from Thunderbird's ISP database
+
+use std::fmt;
+
+use reqwest::{Client, StatusCode, Url};
+
+use crate::autoconfig::{parse_autoconfig, AutoconfigXML};
+
+/// The ISP database, which serves one autoconfig document per domain.
+const ISPDB_URL: &str = "https://autoconfig.thunderbird.net/v1.1/";
+
+/// An error preventing an autoconfig document from being retrieved.
+#[derive(Debug)]
+pub enum Error {
+    /// The domain isn't in the ISP database.
+    NotFound,
+
+    /// The ISP database responded with an unexpected status.
+    Status(StatusCode),
+
+    /// The request couldn't be sent, or its response couldn't be received.
+    Transport(reqwest::Error),
+
+    /// The response isn't a valid autoconfig document.
+    Parse(serde_xml_rs::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotFound => write!(f, "domain not found in the ISP database"),
+            Error::Status(status) => write!(f, "unexpected response status: {status}"),
+            Error::Transport(err) => write!(f, "request failed: {err}"),
+            Error::Parse(err) => write!(f, "malformed autoconfig document: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        Error::Transport(err)
+    }
+}
+
+impl From<serde_xml_rs::Error> for Error {
+    fn from(err: serde_xml_rs::Error) -> Self {
+        Error::Parse(err)
+    }
+}
+
+/// Fetches the autoconfig document for `domain` from the ISP database.
+pub async fn fetch_autoconfig(domain: &str) -> Result<AutoconfigXML, Error> {
+    fetch_autoconfig_with_client(&Client::new(), domain).await
+}
+
+/// Fetches the autoconfig document for `domain` from the ISP database, using
+/// the given client.
+pub async fn fetch_autoconfig_with_client(
+    client: &Client,
+    domain: &str,
+) -> Result<AutoconfigXML, Error> {
+    let base = Url::parse(ISPDB_URL).expect("ISPDB_URL should be a valid URL");
+    fetch_from_database(client, base, domain).await
+}
+
+/// Fetches the autoconfig document for `domain` from the database at `base`.
+async fn fetch_from_database(
+    client: &Client,
+    mut base: Url,
+    domain: &str,
+) -> Result<AutoconfigXML, Error> {
+    // The domain is pushed as a single path segment so that it is escaped
+    // rather than being able to point anywhere else in the database.
+    base.path_segments_mut()
+        .expect("database URL should be a base")
+        .pop_if_empty()
+        .push(&domain.trim().to_ascii_lowercase());
+
+    let response = client.get(base).send().await?;
+    match response.status() {
+        StatusCode::OK => {}
+        StatusCode::NOT_FOUND => return Err(Error::NotFound),
+        status => return Err(Error::Status(status)),
+    }
+
+    let body = response.bytes().await?;
+    Ok(parse_autoconfig(body.as_ref())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::{Client, Url};
+
+    use super::{fetch_from_database, Error};
+
+    // Test that the document for a domain is requested from the database and
+    // parsed.
+    #[tokio::test]
+    async fn test_fetch_autoconfig() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/v1.1/googlemail.com")
+            .with_body(include_bytes!("../autoconfig/ispdb/googlemail.com.xml"))
+            .create_async()
+            .await;
+
+        let base = Url::parse(&format!("{}/v1.1/", server.url())).unwrap();
+        let config = fetch_from_database(&Client::new(), base, "GoogleMail.com")
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(config.email_provider.id, "googlemail.com");
+    }
+
+    // Test that a domain missing from the database is reported as such.
+    #[tokio::test]
+    async fn test_fetch_unknown_domain() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/v1.1/unknown.test")
+            .with_status(404)
+            .create_async()
+            .await;
+
+        let base = Url::parse(&format!("{}/v1.1/", server.url())).unwrap();
+        let result = fetch_from_database(&Client::new(), base, "unknown.test").await;
+
+        assert!(matches!(result, Err(Error::NotFound)), "got {result:?}");
+    }
+}
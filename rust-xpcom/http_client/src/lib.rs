@@ -3,14 +3,16 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use cstr::cstr;
-use std::os::raw::c_void;
+use std::cell::RefCell;
+use std::os::raw::{c_char, c_void};
 use std::ptr;
 
 use nserror::{nsresult, NS_OK};
-use nsstring::{nsACString, nsCString};
+use nsstring::nsACString;
 use xpcom::interfaces::{
-    nsIChannel, nsIContentPolicy, nsIIOService, nsILoadInfo, nsIPrincipal,
-    nsIScriptSecurityManager, nsIStreamListener, nsIStringInputStream, nsIUploadChannel2,
+    nsICancelable, nsIChannel, nsIContentPolicy, nsIHttpChannel, nsIHttpHeaderVisitor,
+    nsIIOService, nsILoadInfo, nsIPrincipal, nsIRequest, nsIScriptSecurityManager,
+    nsIStreamListener, nsIStringInputStream, nsIUploadChannel2,
 };
 use xpcom::{create_instance, get_service, getter_addrefs, nsIID, xpcom_method, RefPtr, XpCom};
 
@@ -38,7 +40,7 @@ impl HttpClient {
             request_body: *const nsACString,
             content_type: *const nsACString,
             listener: *const nsIStreamListener
-        )
+        ) -> *const nsICancelable
     );
 
     fn request(
@@ -48,7 +50,7 @@ impl HttpClient {
         request_body: *const nsACString,
         content_type: *const nsACString,
         listener: *const nsIStreamListener,
-    ) -> Result<(), nsresult> {
+    ) -> Result<RefPtr<nsICancelable>, nsresult> {
         // Get the nsIIOService service to generate the nsIChannel.
         let iosrv = get_service::<nsIIOService>(cstr!("@mozilla.org/network/io-service;1"))
             .ok_or(nserror::NS_ERROR_FAILURE)?;
@@ -77,40 +79,258 @@ impl HttpClient {
             )
         })?;
 
+        // XPCOM guarantees `method` is a valid string, but be defensive about a
+        // null body and treat it the same as an empty one.
+        let (method, request_body) = unsafe { (&*method, request_body.as_ref()) };
+
         // Only set a stream for the body if one is provided, and the method isn't GET.
-        // We're dereferencing a raw pointer in this condition, so the condition itself needs to be unsafe.
-        if unsafe { !(*request_body).is_empty() && *method != nsCString::from("GET") } {
-            // Create an input stream for the body (if any).
-            let body_stream = create_instance::<nsIStringInputStream>(cstr!(
-                "@mozilla.org/io/string-input-stream;1"
-            ))
-            .ok_or(nserror::NS_ERROR_FAILURE)?;
+        if let Some(request_body) =
+            request_body.filter(|request_body| should_upload_body(method, request_body))
+        {
+            set_upload_stream(&channel, method, request_body, content_type)?;
+        }
 
-            // Cast the channel as nsIUploadChannel2 so we can set the input stream and the method.
-            // It's preferrable to use nsIUploadChannel2 over nsIUploadChannel, since it allows us to define both the
-            // body's input stream and the request's method at once.
-            let upload_channel = channel
-                .query_interface::<nsIUploadChannel2>()
-                .ok_or(nserror::NS_ERROR_FAILURE)?;
+        // Send the request asynchronously.
+        unsafe { channel.AsyncOpen(listener) }.to_result()?;
+
+        // Hand back a handle to the channel, so each request can be cancelled
+        // independently of any others made with this client.
+        let handle = RequestHandle::new(channel);
+        Ok(RefPtr::new(handle.coerce()))
+    }
+}
+
+/// A handle to a request sent with [`HttpClient::request`], through which it
+/// can be cancelled.
+#[xpcom::xpcom(implement(nsICancelable), nonatomic)]
+pub struct RequestHandle {
+    channel: RefPtr<nsIChannel>,
+}
+
+impl RequestHandle {
+    fn new(channel: RefPtr<nsIChannel>) -> RefPtr<RequestHandle> {
+        RequestHandle::allocate(InitRequestHandle { channel })
+    }
 
-            unsafe {
-                // Set the data for the stream.
-                // TODO: Is SetUTF8Data the correct method to use? Its doc says it should be used by JS code,
-                //       but it also works pretty nicely for us here - and avoids requiring to faff trying to convert
-                //       nsACString into C-strings.
-                body_stream.SetUTF8Data(request_body).to_result()?;
-
-                // Set the stream as the channel's upload stream.
-                // Note: Here's how we could set the content-type ourself:
-                //     let content_type = nsCString::from("application/json");
-                //     let content_type: *const nsACString = &*content_type;
-                upload_channel
-                    .ExplicitSetUploadStream(body_stream.coerce(), content_type, -1, method, false)
-                    .to_result()?;
-            }
+    xpcom_method!(cancel => Cancel(reason: nsresult));
+
+    /// Cancels the request, so that the listener's `OnStopRequest` is called
+    /// with the given reason.
+    ///
+    /// Cancelling a request that has already completed, or was already
+    /// cancelled, does nothing.
+    fn cancel(&self, reason: nsresult) -> Result<(), nsresult> {
+        let mut pending = false;
+        unsafe { self.channel.IsPending(&mut pending) }.to_result()?;
+        if !pending {
+            return Ok(());
         }
 
-        // Send the request asynchronously.
-        unsafe { channel.AsyncOpen(listener).to_result() }
+        unsafe { self.channel.Cancel(reason) }.to_result()
+    }
+}
+
+/// The status line and headers of an HTTP response.
+///
+/// The listener given to [`HttpClient::request`] only receives the body of
+/// the response, so consumers wanting to tell success from failure should
+/// build this from the request passed to their `OnStartRequest`, before any
+/// data arrives. A status of 200 means the body that follows is the
+/// server's answer; a 401 means the credentials were rejected, and the body
+/// (if any) is an error page that shouldn't be parsed as a response. JS
+/// consumers can do the same by querying the request for `nsIHttpChannel`.
+#[derive(Debug)]
+pub struct HttpResponseHead {
+    status: u32,
+    headers: Vec<(String, String)>,
+}
+
+impl HttpResponseHead {
+    /// Reads the status and headers of the response to the given request.
+    ///
+    /// Fails if the request isn't an HTTP request, or if no response has been
+    /// received yet.
+    pub fn from_request(request: &nsIRequest) -> Result<Self, nsresult> {
+        let channel = request
+            .query_interface::<nsIHttpChannel>()
+            .ok_or(nserror::NS_ERROR_NO_INTERFACE)?;
+
+        let mut status = 0;
+        unsafe { channel.GetResponseStatus(&mut status) }.to_result()?;
+
+        let visitor = HeaderCollector::new();
+        unsafe { channel.VisitResponseHeaders(visitor.coerce()) }.to_result()?;
+
+        Ok(Self {
+            status,
+            headers: visitor.headers.take(),
+        })
+    }
+
+    /// The HTTP status code, e.g. 200 or 401.
+    pub fn status(&self) -> u32 {
+        self.status
+    }
+
+    /// Whether the status code is in the 2xx range.
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// All response headers, in the order the server sent them.
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    /// The value of the first header with the given name, which is matched
+    /// case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
     }
-}
\ No newline at end of file
+}
+
+/// Gathers the headers passed to it by `nsIHttpChannel::VisitResponseHeaders`.
+#[xpcom::xpcom(implement(nsIHttpHeaderVisitor), nonatomic)]
+struct HeaderCollector {
+    headers: RefCell<Vec<(String, String)>>,
+}
+
+impl HeaderCollector {
+    fn new() -> RefPtr<HeaderCollector> {
+        HeaderCollector::allocate(InitHeaderCollector {
+            headers: RefCell::new(Vec::new()),
+        })
+    }
+
+    xpcom_method!(
+        visit_header => VisitHeader(
+            header: *const nsACString,
+            value: *const nsACString
+        )
+    );
+
+    fn visit_header(
+        &self,
+        header: *const nsACString,
+        value: *const nsACString,
+    ) -> Result<(), nsresult> {
+        let (header, value) = unsafe { (&*header, &*value) };
+        self.headers
+            .borrow_mut()
+            .push((header.to_utf8().into_owned(), value.to_utf8().into_owned()));
+        Ok(())
+    }
+}
+
+/// Whether a request with the given method and body needs an upload stream.
+///
+/// GET requests never carry a body, even if the caller provided a non-empty
+/// one, and there's nothing to upload for an empty body.
+fn should_upload_body(method: &[u8], request_body: &[u8]) -> bool {
+    !request_body.is_empty() && method != b"GET"
+}
+
+/// The length in bytes of an upload body, as expected by
+/// `nsIStringInputStream::SetData`, which takes a signed 32-bit length.
+fn upload_length(request_body: &[u8]) -> Result<i32, nsresult> {
+    i32::try_from(request_body.len()).map_err(|_| nserror::NS_ERROR_ILLEGAL_VALUE)
+}
+
+/// Attaches the given body to the channel as its upload stream, along with the
+/// request's method.
+fn set_upload_stream(
+    channel: &nsIChannel,
+    method: &nsACString,
+    request_body: &nsACString,
+    content_type: *const nsACString,
+) -> Result<(), nsresult> {
+    // Create an input stream for the body.
+    let body_stream =
+        create_instance::<nsIStringInputStream>(cstr!("@mozilla.org/io/string-input-stream;1"))
+            .ok_or(nserror::NS_ERROR_FAILURE)?;
+
+    // Cast the channel as nsIUploadChannel2 so we can set the input stream and the method.
+    // It's preferrable to use nsIUploadChannel2 over nsIUploadChannel, since it allows us to define both the
+    // body's input stream and the request's method at once.
+    let upload_channel = channel
+        .query_interface::<nsIUploadChannel2>()
+        .ok_or(nserror::NS_ERROR_FAILURE)?;
+
+    let length = upload_length(request_body)?;
+
+    unsafe {
+        // Copy the raw bytes of the body into the stream. Unlike SetUTF8Data,
+        // which is meant for JS callers, this doesn't require the body to be
+        // valid UTF-8 or free of NUL bytes, so binary bodies survive intact.
+        body_stream
+            .SetData(request_body.as_ptr() as *const c_char, length)
+            .to_result()?;
+
+        // Set the stream as the channel's upload stream.
+        // Note: Here's how we could set the content-type ourself:
+        //     let content_type = nsCString::from("application/json");
+        //     let content_type: *const nsACString = &*content_type;
+        upload_channel
+            .ExplicitSetUploadStream(
+                body_stream.coerce(),
+                content_type,
+                i64::from(length),
+                method,
+                false,
+            )
+            .to_result()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{should_upload_body, upload_length, HttpResponseHead};
+
+    #[test]
+    fn get_never_uploads_a_body() {
+        assert!(!should_upload_body(b"GET", b""));
+        assert!(!should_upload_body(b"GET", b"<soap:Envelope />"));
+    }
+
+    #[test]
+    fn other_methods_upload_non_empty_bodies() {
+        assert!(should_upload_body(b"POST", b"<soap:Envelope />"));
+        assert!(should_upload_body(b"PUT", b"<soap:Envelope />"));
+
+        // A null body is treated as an empty one before reaching this check.
+        assert!(!should_upload_body(b"POST", b""));
+    }
+
+    #[test]
+    fn upload_length_counts_every_byte() {
+        // NUL and non-UTF-8 bytes are part of the body like any other.
+        assert_eq!(upload_length(b"a\0b\xff"), Ok(4));
+        assert_eq!(upload_length(b""), Ok(0));
+    }
+
+    #[test]
+    fn response_head_distinguishes_unauthorized() {
+        let ok = HttpResponseHead {
+            status: 200,
+            headers: vec![],
+        };
+        assert!(ok.is_success());
+
+        let unauthorized = HttpResponseHead {
+            status: 401,
+            headers: vec![(
+                "WWW-Authenticate".to_string(),
+                "Bearer realm=\"\"".to_string(),
+            )],
+        };
+        assert!(!unauthorized.is_success());
+        assert_eq!(
+            unauthorized.header("www-authenticate"),
+            Some("Bearer realm=\"\"")
+        );
+        assert_eq!(unauthorized.header("Content-Type"), None);
+    }
+}